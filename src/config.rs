@@ -14,6 +14,12 @@ pub const ALLOCATION_RATIO: f32 = 1.0;
 /// maximum depth of recursion before a evaluation is interrupted and a stackoverflow signal is emitted
 pub const MAX_RECURSION_DEPTH: usize = 1024;
 
+/// maximum number of function calls recorded in the backtrace of a signal (the outermost ones are left out)
+pub const MAX_BACKTRACE_FRAMES: usize = 64;
+
+/// maximum number of memoized macro expansions (the cache is emptied when it is full)
+pub const EXPANSION_CACHE_SIZE: usize = 1024;
+
 /// the name of the whole application, e.g. it is displayed on the GUI window titlebar
 pub const APPLICATION_NAME: &str = env!("CARGO_PKG_NAME");
 
@@ -10,18 +10,17 @@
             (lookup key (cdr env) env-module)))
       (with-current-module key env-module)))
 
-(defun add-parameters (params args env)
+(defun debug-bind (bindings env eval-env env-module step-in sequential)
   ""
-  (if params
-      (let (first-param (car params)
-            rest-params (cdr params)
-            first-arg   (car args)
-            rest-args   (cdr args))
-        (if (= first-param '&)
-            (cons (cons (car rest-params) args)
-                  env)
-            (cons (cons first-param first-arg)
-                  (add-parameters rest-params rest-args env))))
+  (if bindings
+      (let* (value   (debug-eval-internal (car (cdr bindings)) eval-env env-module step-in)
+             new-env (bind-pattern (car bindings) value env))
+        (debug-bind (cdr (cdr bindings))
+                    new-env
+                    (if sequential new-env eval-env)
+                    env-module
+                    step-in
+                    sequential))
       env))
 
 (defun highlight-list-elem (elems n)
@@ -55,20 +54,28 @@
                               (when step-in
                                 (send (list 'kind 'HIGHLIGHT-ELEM, 'string (highlight-list-elem expr 1))))
                               (car operands)))
-      ((= operator 'if)     (let (condition (car operands)
-                                  then      (car (cdr operands))
-                                  otherwise (car (cdr (cdr operands))))
+      ((= operator 'if)     (let ((condition then otherwise) operands)
                               (if (highlight-and-debug condition 1)
                                   (highlight-and-debug then      2)
                                   (highlight-and-debug otherwise 3))))
       ((= operator 'eval)   (let (operand (highlight-and-debug (car operands) 1))
                               (highlight-and-debug operand 1)))
-      ((= operator 'trap)   (make-trap (car operands) (car (cdr operands))) env)
+      ((= operator 'trap)   (make-trap (car operands) (car (cdr operands))))
       ((= operator 'lambda) (make-function (car operands)
                                            (car (cdr operands))
                                            env
                                            env-module
                                            'lambda-type))
+      ((or (= operator 'parameterize) (or (= operator 'unwind-protect) (= operator 'match)))
+                            (call-native-function eval (list expr) env))
+      ((or (= operator 'letrec)
+           (and (= operator 'let) (= (type-of (car operands)) 'symbol-type)))
+                            (call-native-function eval (list expr) env))
+      ((or (= operator 'let) (= operator 'let*))
+                            (debug-eval-internal (car (cdr operands))
+                                                 (debug-bind (car operands) env env env-module step-in (= operator 'let*))
+                                                 env-module
+                                                 step-in))
       ('otherwise           (let (evaled-expr (map (lambda (xi) (highlight-and-debug (car xi) (cdr xi)))
                                                    (enumerate expr)))
                               (let (evaled-parts (destructure-function (car evaled-expr)))
@@ -79,9 +86,7 @@
                                       (send (list 'kind 'ALL-ELEMS-EVALED, 'expression (print expr), 'result (print evaled-expr)))))
                                   (if (. evaled-parts 'body)
                                       (debug-eval-internal (. evaled-parts 'body)
-                                                           (add-parameters (. evaled-parts 'parameters)
-                                                                           (cdr evaled-expr)
-                                                                           (. evaled-parts 'environment))
+                                                           (bind-arguments (car evaled-expr) (cdr evaled-expr))
                                                            (. evaled-parts 'module)
                                                            step-in)
                                       (call-native-function (car evaled-expr) (cdr evaled-expr) env)))))))))
@@ -92,22 +97,19 @@
          (block
            (when step-in 
              (send (list 'kind 'EVAL, 'string (print expr))))
-           (let (result (let (type (type-of expr))
-                          (case
-                            ((= type 'list-type)   (debug-list expr env env-module step-in))
-                            ((= type 'cons-type)   (cons (debug-eval-internal (car expr) env env-module step-in)
-                                                         (debug-eval-internal (cdr expr) env env-module step-in)))
-                            ((= type 'symbol-type) (lookup expr env env-module))
-                            ((= type 'trap-type)   (let (nt (destructure-trap expr))
-                                                     (let (normal-body (car nt)
-                                                           trap-body   (car (cdr nt)))
-                                                       (eval (trap
-                                                              (debug-eval-internal normal-body env env-module step-in)
-                                                              (block
-                                                                (receive)
-                                                                (send (list 'kind 'SIGNAL-TRAPPED, 'string (print *trapped-signal*)))
-                                                                (debug-eval-internal trap-body (cons (cons '*trapped-signal* *trapped-signal*) env) env-module step-in)))))))
-                            ('otherwise            expr))))
+           (let (result (match expr
+                          ((list-type)   (debug-list expr env env-module step-in))
+                          ((cons-type)   (cons (debug-eval-internal (car expr) env env-module step-in)
+                                               (debug-eval-internal (cdr expr) env env-module step-in)))
+                          ((symbol-type) (lookup expr env env-module))
+                          ((trap-type)   (let ((normal-body trap-body) (destructure-trap expr))
+                                           (eval (trap
+                                                  (debug-eval-internal normal-body env env-module step-in)
+                                                  (block
+                                                    (receive)
+                                                    (send (list 'kind 'SIGNAL-TRAPPED, 'string (print *trapped-signal*)))
+                                                    (debug-eval-internal trap-body (cons (cons '*trapped-signal* *trapped-signal*) env) env-module step-in))))))
+                          (_             expr)))
              (block
                (when step-in
                  (block
@@ -138,7 +140,9 @@
     (case
       ((= operator 'quote) (list 'result  expr
                                  'changed nil))
-      ((= operator 'macro) (list 'result  (make-function (car operands) (car (cdr operands)) env env-module 'macro-type)
+      ((= operator 'quasiquote) (list 'result  (macroexpand expr)
+                                      'changed t))
+      ((= operator 'macro) (list 'result  (make-function (car operands) (macroexpand (car (cdr operands))) env env-module 'macro-type)
                                  'changed nil))
       ('otherwise           (let (expanded-operator-rc (debug-expand operator env env-module step-in)
                                   expanded-operands-rc (sequence-changed (map (lambda (x) (debug-expand x env env-module step-in)) operands)))
@@ -151,9 +155,7 @@
                                       (let (body (. expanded-operator-parts 'body))
                                         (list 'result (if body
                                                           (debug-eval-internal body
-                                                                               (add-parameters (. expanded-operator-parts 'parameters)
-                                                                                               expanded-operands
-                                                                                               (. expanded-operator-parts 'environment))
+                                                                               (bind-arguments expanded-operator expanded-operands)
                                                                                (. expanded-operator-parts 'module)
                                                                                step-in)
                                                           (call-native-function expanded-operator expanded-operands env))
@@ -163,24 +165,23 @@
          
 (defun debug-expand (expr env env-module step-in)
   ""
-  (let (type (type-of expr))
-    (case
-      ((= type 'list-type)   (debug-expand-list expr env env-module step-in)) 
-      ((= type 'cons-type)   (let (expanded-car (debug-expand (car expr) env env-module step-in)
-                                                expanded-cdr (debug-expand (cdr expr) env env-module step-in))
-                               (let (changed (or (. expanded-car 'changed) (. expanded-cdr 'changed)))
-                                 (list 'result  (cons (. expanded-car 'result) (. expanded-cdr 'result))
-                                       'changed changed))))
-      ((= type 'symbol-type) (eval (trap
-                                    (let (expanded (lookup expr env env-module))
-                                      (if (= 'macro (. (destructure-function expanded) 'kind))
-                                          (list 'result expanded, 'changed t)
-                                          (list 'result expr,     'changed nil)))
-                                    (if (= (. *trapped-signal* 'kind) 'unbound-symbol)
-                                        (list 'result expr,     'changed nil)
-                                        (signal *trapped-signal*)))))
-      ('otherwise            (list 'result  expr
-                                   'changed nil)))))
+  (match expr
+    ((list-type)   (debug-expand-list expr env env-module step-in))
+    ((cons-type)   (let (expanded-car (debug-expand (car expr) env env-module step-in)
+                         expanded-cdr (debug-expand (cdr expr) env env-module step-in))
+                     (let (changed (or (. expanded-car 'changed) (. expanded-cdr 'changed)))
+                       (list 'result  (cons (. expanded-car 'result) (. expanded-cdr 'result))
+                             'changed changed))))
+    ((symbol-type) (eval (trap
+                          (let (expanded (lookup expr env env-module))
+                            (if (= 'macro (. (destructure-function expanded) 'kind))
+                                (list 'result expanded, 'changed t)
+                                (list 'result expr,     'changed nil)))
+                          (if (= (. *trapped-signal* 'kind) 'unbound-symbol)
+                              (list 'result expr,     'changed nil)
+                              (signal *trapped-signal*)))))
+    (_             (list 'result  expr
+                         'changed nil))))
 
 (defun keep-expanding (expr env env-module step-in ch)
   ""
@@ -1,13 +1,284 @@
 use crate::memory::*;
 use crate::native::list::make_plist;
+use crate::native::reflection::{location_details, span_details};
+use crate::native::print::print;
+use crate::metadata::{Location, Metadata, Span};
 use crate::util::*;
 
 
 
-pub fn make_error(mem: &mut Memory, kind: &str, source: &str, details: &[(&str, GcRef)]) -> GcRef {
-    let mut vec = vec![("kind", mem.symbol_for(kind)), ("source", mem.symbol_for(source))];
-    vec.extend_from_slice(details);
-    make_plist(mem, &vec)
+/// The built-in signal kinds, each with the kind it is a special case of, and the default message of its errors.
+///
+/// Every signal emitted by a native function has one of these kinds, so catching a kind also catches
+/// all of its descendants, e.g. `(catch arithmetic-error ...)` catches `divide-by-zero`.
+/// The root of the tree is `error`. More kinds can be added with `define-signal-kind`.
+/// The signals of `invoke-restart` (kind `restart-invocation`) are deliberately left out of the tree.
+///
+/// ```text
+/// error
+/// ├── arithmetic-error: arithmetic-overflow, divide-by-zero
+/// ├── argument-error: wrong-number-of-arguments, wrong-argument-type, wrong-argument, wrong-arg-value,
+/// │                   unknown-key-argument, odd-number-of-key-arguments, native-function,
+/// │                   wrong-plist-format, invalid-plist, invalid-signal-kind
+/// ├── lambda-list-error: param-is-not-symbol, misplaced-lambda-list-keyword, invalid-parameter-spec,
+/// │                      multiple-rest-parameters, missing-rest-parameter
+/// ├── binding-error: binding-name-is-not-symbol, incomplete-binding
+/// ├── pattern-error: invalid-pattern, pattern-mismatch, no-match, invalid-match-clause
+/// ├── syntax-rules-error: no-matching-syntax-rule, invalid-syntax-rule, missing-ellipsis, invalid-ellipsis,
+/// │                       ellipsis-length-mismatch
+/// ├── quasiquote-error: invalid-quasiquote, unquote-outside-quasiquote, unquote-splicing-outside-list
+/// ├── eval-error: unbound-symbol, eval-bad-operator, ambiguous-name, no-such-module, stackoverflow,
+/// │               already-defined
+/// ├── read-error: syntax-error, input-incomplete, input-invalid-string, unknown-read-source, invalid-reader,
+/// │               invalid-reader-macro-character, invalid-reader-macro-result
+/// ├── io-error: cannot-read-file, cannot-write-file, eof
+/// ├── interrupted
+/// └── assertion-failed
+/// ```
+pub const SIGNAL_KINDS: &[(&str, &str, &str)] = &[
+    ("arithmetic-error",               "error",              "arithmetic error"),
+    ("arithmetic-overflow",            "arithmetic-error",   "the result of the arithmetic operation does not fit into a number"),
+    ("divide-by-zero",                 "arithmetic-error",   "division by zero"),
+    ("argument-error",                 "error",              "invalid argument"),
+    ("wrong-number-of-arguments",      "argument-error",     "wrong number of arguments"),
+    ("wrong-argument-type",            "argument-error",     "an argument has the wrong type"),
+    ("wrong-argument",                 "argument-error",     "invalid argument"),
+    ("wrong-arg-value",                "argument-error",     "an argument has an invalid value"),
+    ("unknown-key-argument",           "argument-error",     "unknown key argument"),
+    ("odd-number-of-key-arguments",    "argument-error",     "the key arguments are not in key-value pairs"),
+    ("native-function",                "argument-error",     "a native function was given instead of a normal one"),
+    ("wrong-plist-format",             "argument-error",     "invalid property list"),
+    ("invalid-plist",                  "argument-error",     "invalid property list"),
+    ("invalid-signal-kind",            "argument-error",     "a signal kind can't be a special case of itself"),
+    ("lambda-list-error",              "error",              "invalid parameter list"),
+    ("param-is-not-symbol",            "lambda-list-error",  "a parameter is not a symbol or a pattern"),
+    ("misplaced-lambda-list-keyword",  "lambda-list-error",  "misplaced lambda list keyword"),
+    ("invalid-parameter-spec",         "lambda-list-error",  "invalid parameter specification"),
+    ("multiple-rest-parameters",       "lambda-list-error",  "more than one parameter after `&`"),
+    ("missing-rest-parameter",         "lambda-list-error",  "missing parameter after `&`"),
+    ("binding-error",                  "error",              "invalid binding"),
+    ("binding-name-is-not-symbol",     "binding-error",      "the name of a binding is not a symbol"),
+    ("incomplete-binding",             "binding-error",      "a binding has no value"),
+    ("pattern-error",                  "error",              "pattern error"),
+    ("invalid-pattern",                "pattern-error",      "invalid pattern"),
+    ("pattern-mismatch",               "pattern-error",      "the value does not match the pattern"),
+    ("no-match",                       "pattern-error",      "no pattern matches the value"),
+    ("invalid-match-clause",           "pattern-error",      "a match clause is not in the form of `(pattern body)`"),
+    ("syntax-rules-error",             "error",              "syntax rules error"),
+    ("no-matching-syntax-rule",        "syntax-rules-error", "no syntax rule matches the arguments"),
+    ("invalid-syntax-rule",            "syntax-rules-error", "invalid syntax rule"),
+    ("missing-ellipsis",               "syntax-rules-error", "a pattern variable that matched a repetition is used without an ellipsis"),
+    ("invalid-ellipsis",               "syntax-rules-error", "an ellipsis follows a template without repeated pattern variables"),
+    ("ellipsis-length-mismatch",       "syntax-rules-error", "the pattern variables under an ellipsis matched different numbers of forms"),
+    ("quasiquote-error",               "error",              "quasiquote error"),
+    ("invalid-quasiquote",             "quasiquote-error",   "invalid quasiquote form"),
+    ("unquote-outside-quasiquote",     "quasiquote-error",   "unquote outside of a quasiquote"),
+    ("unquote-splicing-outside-list",  "quasiquote-error",   "unquote-splicing outside of a list"),
+    ("eval-error",                     "error",              "evaluation error"),
+    ("unbound-symbol",                 "eval-error",         "unbound symbol"),
+    ("eval-bad-operator",              "eval-error",         "the operator is not a function"),
+    ("ambiguous-name",                 "eval-error",         "the symbol is exported by more than one module"),
+    ("no-such-module",                 "eval-error",         "no such module"),
+    ("stackoverflow",                  "eval-error",         "maximum recursion depth exceeded"),
+    ("already-defined",                "eval-error",         "the symbol is already defined"),
+    ("read-error",                     "error",              "cannot read the input"),
+    ("syntax-error",                   "read-error",         "syntax error"),
+    ("input-incomplete",               "read-error",         "the input ended in the middle of an expression"),
+    ("input-invalid-string",           "read-error",         "the input is not a valid string"),
+    ("unknown-read-source",            "read-error",         "unknown source of the input"),
+    ("invalid-reader",                 "read-error",         "invalid reader"),
+    ("invalid-reader-macro-character", "read-error",         "a reader macro can't be assigned to a whitespace character"),
+    ("invalid-reader-macro-result",    "read-error",         "a reader macro returned an invalid result"),
+    ("io-error",                       "error",              "input/output error"),
+    ("cannot-read-file",               "io-error",           "cannot read the file"),
+    ("cannot-write-file",              "io-error",           "cannot write the file"),
+    ("eof",                            "io-error",           "end of input"),
+    ("interrupted",                    "error",              "the evaluation was interrupted"),
+    ("assertion-failed",               "error",              "an assertion failed"),
+];
+
+
+/// English message templates of the signal kinds, rendered with the details of an error by [render_message].
+///
+/// `{key}` stands for the value of the detail `key`. A template is only used if the error has every detail it refers to,
+/// so a kind can have several templates, from the most specific to the least specific one.
+/// Kinds without a usable template get the default message from [SIGNAL_KINDS].
+pub const MESSAGE_TEMPLATES: &[(&str, &str)] = &[
+    ("arithmetic-overflow",            "arithmetic overflow with the arguments {arguments}"),
+    ("divide-by-zero",                 "division by zero with the arguments {arguments}"),
+    ("wrong-number-of-arguments",      "wrong number of arguments: expected {expected}, got {actual}"),
+    ("wrong-number-of-arguments",      "wrong number of arguments: expected {expected-min} to {expected-max}, got {actual}"),
+    ("wrong-number-of-arguments",      "wrong number of arguments: expected at least {expected-min}, got {actual}"),
+    ("wrong-argument-type",            "expected {expected} for argument `{parameter}`, got {actual}"),
+    ("wrong-argument-type",            "expected {expected}, got {actual}"),
+    ("wrong-argument",                 "expected a {expected}, got a {actual}"),
+    ("unknown-key-argument",           "unknown key argument `{key}`, expected one of {allowed}"),
+    ("odd-number-of-key-arguments",    "the key arguments {arguments} are not in key-value pairs"),
+    ("native-function",                "`{argument-value}` is a native function, expected a normal one"),
+    ("invalid-plist",                  "{argument-value} is not a valid property list"),
+    ("invalid-signal-kind",            "`{kind}` can't be a special case of `{parent}`"),
+    ("param-is-not-symbol",            "the parameter {parameter} is not a symbol or a pattern"),
+    ("misplaced-lambda-list-keyword",  "misplaced lambda list keyword `{parameter}`"),
+    ("invalid-parameter-spec",         "invalid parameter specification {parameter}"),
+    ("binding-name-is-not-symbol",     "the name of the binding {name} is not a symbol"),
+    ("incomplete-binding",             "the binding of `{name}` has no value"),
+    ("invalid-pattern",                "invalid pattern {pattern}"),
+    ("pattern-mismatch",               "the value {value} does not match the pattern {pattern}"),
+    ("no-match",                       "no pattern matches the value {value}"),
+    ("invalid-match-clause",           "the match clause {clause} is not in the form of `(pattern body)`"),
+    ("no-matching-syntax-rule",        "no syntax rule matches the form {form}"),
+    ("invalid-syntax-rule",            "invalid syntax rule {rule}"),
+    ("missing-ellipsis",               "the pattern variable `{variable}` matched a repetition but is used without an ellipsis"),
+    ("invalid-ellipsis",               "the ellipsis after {template} follows no repeated pattern variable"),
+    ("ellipsis-length-mismatch",       "the pattern variables in {template} matched different numbers of forms"),
+    ("invalid-quasiquote",             "invalid quasiquote form {form}"),
+    ("unquote-outside-quasiquote",     "unquote outside of a quasiquote in {form}"),
+    ("unquote-splicing-outside-list",  "unquote-splicing outside of a list in {form}"),
+    ("unbound-symbol",                 "the symbol `{symbol}` is unbound"),
+    ("eval-bad-operator",              "the operator {operator} is not a function"),
+    ("ambiguous-name",                 "the symbol `{symbol}` is exported by more than one module: {conflicting-modules}"),
+    ("no-such-module",                 "there is no module named `{module}`"),
+    ("already-defined",                "the symbol `{symbol}` is already defined"),
+    ("unknown-read-source",            "unknown source of the input: {argument-value}"),
+    ("invalid-reader",                 "{reader} is not a reader"),
+    ("invalid-reader-macro-character", "the reader macro character {character} is a whitespace character"),
+    ("invalid-reader-macro-result",    "the reader macro of {character} returned the invalid result {result}"),
+    ("cannot-read-file",               "cannot read the file: {reason}"),
+    ("cannot-write-file",              "cannot write the file: {reason}"),
+    ("assertion-failed",               "the assertion {expression} failed"),
+];
+
+
+/// The message of an error of kind `kind` with `details`: the first usable template of `kind` in [MESSAGE_TEMPLATES]
+/// with the printed detail values filled in (strings without quotes), or else the default message of `kind`.
+pub fn render_message(mem: &mut Memory, kind: &str, details: &[(String, GcRef)]) -> String {
+    for (_, template) in MESSAGE_TEMPLATES.iter().filter(|(k, _)| *k == kind) {
+        if let Some(message) = render_template(mem, template, details) {
+            return message;
+        }
+    }
+    SIGNAL_KINDS.iter().find(|(k, _, _)| *k == kind).map(|(_, _, m)| m.to_string()).unwrap_or_else(|| kind.to_string())
+}
+
+fn render_template(mem: &mut Memory, template: &str, details: &[(String, GcRef)]) -> Option<String> {
+    let mut message = String::new();
+    let mut rest    = template;
+    while let Some(start) = rest.find('{') {
+        let end   = start + rest[start..].find('}')?;
+        let value = details.iter().find(|(key, _)| *key == rest[start + 1 .. end])?.1.clone();
+        message.push_str(&rest[..start]);
+        message.push_str(&render_value(mem, value));
+        rest = &rest[end + 1 ..];
+    }
+    message.push_str(rest);
+    Some(message)
+}
+
+fn render_value(mem: &mut Memory, value: GcRef) -> String {
+    if !value.is_nil() {
+        if let Some(string) = list_to_string(value.clone()) {
+            return string;
+        }
+    }
+    print(mem, &[value], GcRef::nil(), 0).ok().and_then(list_to_string).unwrap_or_default()
+}
+
+
+/// Builder of the errors emitted by native functions.
+///
+/// Every error is a property list with the same keys, in this order:
+/// - `kind`: a symbol from the signal kind tree (see [SIGNAL_KINDS]),
+/// - `source`: the name of the function that emitted it,
+/// - `message`: a human-readable string, by default rendered from the templates of `kind` (see [MESSAGE_TEMPLATES]),
+/// - `details`: a property list of the values involved (e.g. `symbol`, `argument-value`, `expected`, `actual`),
+/// - `location`: where the offending expression was read (`file`, `line` and `column`, and for an expression
+///   whose span is known also `end-line`, `end-column`, `offset` and `end-offset`), or nil if it is unknown.
+///
+/// Unless it is set explicitly, the location is that of the first detail value that has one.
+pub struct LispError {
+    kind:     String,
+    source:   String,
+    message:  Option<String>,
+    details:  Vec<(String, GcRef)>,
+    location: ErrorLocation,
+}
+
+enum ErrorLocation {
+    Unknown,
+    Known(Location, Option<Span>),
+    Plist(GcRef),
+}
+
+impl LispError {
+    /// An error of kind `kind` emitted by `source`. `kind` must be one of the [SIGNAL_KINDS].
+    pub fn new(kind: &str, source: &str) -> Self {
+        debug_assert!(SIGNAL_KINDS.iter().any(|(k, _, _)| *k == kind), "undeclared signal kind `{kind}`");
+        Self{ kind: kind.to_string(), source: source.to_string(), message: None, details: vec![], location: ErrorLocation::Unknown }
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn detail(mut self, key: &str, value: GcRef) -> Self {
+        self.details.push((key.to_string(), value));
+        self
+    }
+
+    pub fn details(mut self, details: &[(&str, GcRef)]) -> Self {
+        self.details.extend(details.iter().map(|(key, value)| (key.to_string(), value.clone())));
+        self
+    }
+
+    pub fn location(mut self, location: Location) -> Self {
+        self.location = ErrorLocation::Known(location, None);
+        self
+    }
+
+    /// Set the location from the metadata of an expression read from the source, including its span.
+    pub fn location_of(mut self, md: &Metadata) -> Self {
+        self.location = ErrorLocation::Known(md.location.clone(), md.span);
+        self
+    }
+
+    /// Set the location from a property list that is already in the shape of the `location` of errors.
+    pub fn location_plist(mut self, location: GcRef) -> Self {
+        self.location = ErrorLocation::Plist(location);
+        self
+    }
+
+    pub fn build(self, mem: &mut Memory) -> GcRef {
+        let location = match self.location {
+            ErrorLocation::Known(location, span) => Some((location, span)),
+            ErrorLocation::Plist(plist)          => return error_plist(mem, &self.kind, &self.source, self.message, &self.details, plist),
+            ErrorLocation::Unknown               => self.details.iter().find_map(|(_, value)| value.get_meta().map(|md| (md.location.clone(), md.span))),
+        };
+        let location = match location {
+            Some((Location::Native, _)) | None => GcRef::nil(),
+            Some((location, span))             => {
+                let mut details = location_details(mem, &location);
+                if let Some(span) = span {
+                    details.extend(span_details(mem, &span));
+                }
+                make_plist(mem, &details)
+            },
+        };
+        error_plist(mem, &self.kind, &self.source, self.message, &self.details, location)
+    }
+}
+
+
+/// An error in the shape described at [LispError], without checking `kind`.
+pub fn error_plist(mem: &mut Memory, kind: &str, source: &str, message: Option<String>, details: &[(String, GcRef)], location: GcRef) -> GcRef {
+    let message = message.unwrap_or_else(|| render_message(mem, kind, details));
+    let details = details.iter().map(|(key, value)| (key.as_str(), value.clone())).collect::<Vec<_>>();
+    let plist   = vec![("kind",     mem.symbol_for(kind)),
+                       ("source",   mem.symbol_for(source)),
+                       ("message",  string_to_list(mem, &message)),
+                       ("details",  make_plist(mem, &details)),
+                       ("location", location)];
+    make_plist(mem, &plist)
 }
 
 
@@ -21,6 +292,43 @@ pub fn fit_to_number(mem: &mut Memory, x: usize) -> GcRef {
 }
 
 
+/// The name of the parameter at `index` of the native function `source`, as declared in its [NativeFunctionMetaData](crate::native::NativeFunctionMetaData)
+/// (and shown by `describe`), if `source` is a native function whose `count` arguments are its declared parameters one by one.
+pub fn native_parameter_name(mem: &mut Memory, source: &str, index: usize, count: usize) -> Option<GcRef> {
+    let function = mem.get_global_from_module(source, "native").ok()?;
+    let Some(PrimitiveValue::Function(Function::NativeFunction(nf))) = function.get() else {
+        return None;
+    };
+    let names = nf.get_param_names();
+    if names.iter().any(|name| name == "&") {
+        return None;
+    }
+    let names = names.iter().filter(|name| !name.starts_with('&')).collect::<Vec<_>>();
+    if names.len() != count {
+        return None;
+    }
+    Some(mem.symbol_for(names[index]))
+}
+
+
+/// Fill in the missing trailing optional arguments of a native function from `defaults`.
+/// Error if there are less than `required_count` or more than `required_count + defaults.len()` arguments.
+pub fn fill_optional_args(mem: &mut Memory, source: &str, args: &[GcRef], required_count: usize, defaults: &[GcRef]) -> Result<Vec<GcRef>, GcRef> {
+    let max_count = required_count + defaults.len();
+
+    if args.len() < required_count || args.len() > max_count {
+        let error_details = vec![("expected-min", fit_to_number(mem, required_count)),
+                                 ("expected-max", fit_to_number(mem, max_count)),
+                                 ("actual",       fit_to_number(mem, args.len()))];
+        return Err(LispError::new("wrong-number-of-arguments", source).details(&error_details).build(mem));
+    }
+
+    let mut filled = args.to_vec();
+    filled.extend_from_slice(&defaults[args.len() - required_count ..]);
+    Ok(filled)
+}
+
+
 pub fn extended_get_type(thing: GcRef) -> TypeLabel {
     match thing.get_type() {
         TypeLabel::Cons => {
@@ -92,7 +400,7 @@ macro_rules! _validate_args {
 
         if args.len() != 0 {
             let error_details = vec![("expected", mem.allocate_number(0)), ("actual", fit_to_number(mem, args.len()))];
-            let error         = make_error(mem, "wrong-number-of-arguments", source, &error_details);
+            let error         = LispError::new("wrong-number-of-arguments", source).details(&error_details).build(mem);
             return Err(error);
         }
 
@@ -107,13 +415,14 @@ macro_rules! _validate_args {
 
         if args.len() != params_count {
             let error_details = vec![("expected", fit_to_number(mem, params_count)), ("actual", fit_to_number(mem, args.len()))];
-            let error         = make_error(mem, "wrong-number-of-arguments", source, &error_details);
+            let error         = LispError::new("wrong-number-of-arguments", source).details(&error_details).build(mem);
             return Err(error);
         }
 
-        let mut arg_iter = args.iter();
+        let mut arg_iter = args.iter().enumerate();
         $(
-            let arg = arg_iter.next().unwrap().clone();
+            let (arg_index, arg) = arg_iter.next().unwrap();
+            let arg = arg.clone();
             let arg1 = arg.clone();
             let $name =
             {
@@ -121,10 +430,13 @@ macro_rules! _validate_args {
                     x
                 }
                 else {
-                    let error_details = vec![("argument-value", arg.clone()),
-                                             ("expected", mem.symbol_for($($params)+.to_string())),
-                                             ("actual", mem.symbol_for(extended_get_type(arg.clone()).to_string()))];
-                    let error         = make_error(mem, "wrong-argument-type", source, &error_details);
+                    let mut error_details = vec![("argument-value", arg.clone()),
+                                                 ("expected", mem.symbol_for($($params)+.to_string())),
+                                                 ("actual", mem.symbol_for(extended_get_type(arg.clone()).to_string()))];
+                    if let Some(parameter) = native_parameter_name(mem, source, arg_index, params_count) {
+                        error_details.insert(1, ("parameter", parameter));
+                    }
+                    let error = LispError::new("wrong-argument-type", source).details(&error_details).build(mem);
                     return Err(error);
                 }
             };
@@ -133,3 +445,7 @@ macro_rules! _validate_args {
 }
 
 pub(crate) use _validate_args as validate_args;
+
+
+#[cfg(test)]
+mod tests;
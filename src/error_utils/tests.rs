@@ -0,0 +1,35 @@
+use pretty_assertions::assert_eq;
+use crate::native::list::property;
+use super::*;
+
+
+#[test]
+fn lisp_error_shape() {
+    let mut mem = Memory::new();
+
+    let value = mem.allocate_number(0);
+    let error = LispError::new("divide-by-zero", "/").detail("argument-value", value).build(&mut mem);
+
+    assert_eq!(property(&mut mem, "kind", error.clone()).unwrap().get().unwrap().as_symbol().get_name(), "divide-by-zero");
+    assert_eq!(property(&mut mem, "source", error.clone()).unwrap().get().unwrap().as_symbol().get_name(), "/");
+    assert_eq!(list_to_string(property(&mut mem, "message", error.clone()).unwrap()).unwrap(), "division by zero");
+    assert!(property(&mut mem, "location", error.clone()).unwrap().is_nil());
+
+    let details = property(&mut mem, "details", error.clone()).unwrap();
+    assert_eq!(*property(&mut mem, "argument-value", details).unwrap().get().unwrap().as_number(), 0);
+}
+
+#[test]
+fn lisp_error_message_and_location() {
+    let mut mem = Memory::new();
+
+    let location = Location::Stdin{ line: 2, column: 3 };
+    let error    = LispError::new("wrong-argument", "f").message("custom message").location(location).build(&mut mem);
+
+    assert_eq!(list_to_string(property(&mut mem, "message", error.clone()).unwrap()).unwrap(), "custom message");
+    assert!(property(&mut mem, "details", error.clone()).unwrap().is_nil());
+
+    let location = property(&mut mem, "location", error).unwrap();
+    assert_eq!(*property(&mut mem, "line", location.clone()).unwrap().get().unwrap().as_number(), 2);
+    assert_eq!(*property(&mut mem, "column", location).unwrap().get().unwrap().as_number(), 3);
+}
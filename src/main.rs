@@ -15,6 +15,10 @@ fn main() -> Result<(), String> {
             let filename = args.next().ok_or_else(|| "Missing filename. Use --help flag for help.")?;
             ui::terminal::run_file(&filename)
         },
+        Some("--check") => {
+            let filename = args.next().ok_or("Missing filename. Use --help flag for help.")?;
+            ui::terminal::check_file(&filename)
+        },
         Some("--gui")  => ui::gui::run(),
         Some("--help") => {
             println!("{}", usage());
@@ -31,6 +35,7 @@ fn usage() -> String {
 {name}                         start interactive REPL
 {name} --load <filename>       load the {name}-module defined in <filename>
 {name} --expression <expr>     evaluate <expr>, print its result to standard output, then exit
+{name} --check <filename>      report all syntax errors in <filename> without evaluating it
 {name} --gui                   start the graphical debugger
 {name} --help                  print this help message")
 }
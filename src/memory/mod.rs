@@ -103,10 +103,24 @@ impl FunctionKind {
 }
 
 
+/// The parameters of a normal function:
+/// `required* [&optional spec*] [& rest | &key spec*]`
+///
+/// Defaults are unevaluated expressions (nil if there is no default).
+pub struct LambdaList {
+    pub required: Vec<GcRef>,
+    pub optional: Vec<(GcRef, GcRef)>, // (pattern, default)
+    pub rest:     Option<GcRef>,
+    pub key:      Vec<(GcRef, GcRef)>, // (key, default)
+}
+
+
 pub struct NormalFunction {
     kind: FunctionKind,
-    has_rest_params: bool,
     parameters: Vec<*mut CellContent>,
+    optional_parameters: Vec<(*mut CellContent, *mut CellContent)>,
+    rest_parameter: Option<*mut CellContent>,
+    key_parameters: Vec<(*mut CellContent, *mut CellContent)>,
     body: *mut CellContent,
     environment: *mut CellContent,
     environment_module: String,
@@ -117,22 +131,19 @@ impl NormalFunction {
         GcRef::new(self.body)
     }
 
-    pub fn non_rest_params(&self) -> ParameterIterator {
-        ParameterIterator{ function: self, index: 0 }
-    }
-
     pub fn rest_param(&self) -> Option<GcRef> {
-        if self.has_rest_params {
-            let p = self.parameters.last().unwrap();
-            Some(GcRef::new(*p))
-        }
-        else {
-            None
-        }
+        self.rest_parameter.map(GcRef::new)
     }
 
-    pub fn get_params(&self) -> Vec<GcRef> {
-        self.parameters.iter().map(|p| GcRef::new(*p)).collect()
+    pub fn get_lambda_list(&self) -> LambdaList {
+        let pairs = |ps: &Vec<(*mut CellContent, *mut CellContent)>| ps.iter().map(|(p, d)| (GcRef::new(*p), GcRef::new(*d))).collect();
+
+        LambdaList {
+            required: self.parameters.iter().map(|p| GcRef::new(*p)).collect(),
+            optional: pairs(&self.optional_parameters),
+            rest:     self.rest_param(),
+            key:      pairs(&self.key_parameters),
+        }
     }
 
     pub fn get_kind(&self) -> FunctionKind {
@@ -148,35 +159,6 @@ impl NormalFunction {
     }
 }
 
-pub struct ParameterIterator<'a> {
-    function: &'a NormalFunction,
-    index: usize,
-}
-
-impl<'a> Iterator for ParameterIterator<'a> {
-    type Item = GcRef;
-
-    fn next(&mut self) -> Option<GcRef> {
-        let n =
-        if self.function.has_rest_params {
-            self.function.parameters.len() - 1
-        }
-        else {
-            self.function.parameters.len()
-        };
-
-        if self.index < n {
-            let er = GcRef::new(self.function.parameters[self.index]);
-            self.index += 1;
-            Some(er)
-        }
-        else {
-            None
-        }
-    }
-}
-
-
 pub struct NativeFunction {
     kind: FunctionKind,
               // memory,     argumetns, environment, recursion depth           value  signal
@@ -192,6 +174,10 @@ impl NativeFunction {
     pub fn is_the_same_as(&self, function: fn(&mut Memory, &[GcRef], GcRef, usize) -> Result<GcRef, GcRef>) -> bool {
         self.function == function
     }
+
+    pub fn get_param_names(&self) -> Vec<String> {
+        self.parameters.clone()
+    }
 }
 
 
@@ -250,37 +236,6 @@ impl Function {
             _ => String::new(),
         }
     }
-
-    pub fn get_param_names(&self) -> Vec<String> {
-        match self {
-            Self::NormalFunction(nf) => {
-                let mut param_names = vec![];
-                for p in nf.non_rest_params() {
-                    if let Some(PrimitiveValue::Symbol(s)) = p.get() {
-                        param_names.push(s.get_name());
-                    }
-                    else {
-                        param_names.push(format!("#<invalid-parameter-name>"));
-                    }
-                }
-                
-                if let Some(rp) = nf.rest_param() {
-                    if let Some(PrimitiveValue::Symbol(s)) = rp.get() {
-                        param_names.push("&".to_string());
-                        param_names.push(s.get_name());
-                    }
-                    else {
-                        param_names.push(format!("#<invalid-parameter-name>"));
-                    }
-                }
-
-                param_names
-            },
-            Self::NativeFunction(nf) => {
-                nf.parameters.clone()
-            },
-        }
-    }
 }
 
 
@@ -489,6 +444,11 @@ impl GcRef {
         content.is_nil()
     }
 
+    /// Whether `self` and `other` refer to the same object, not just to equal ones.
+    pub fn is_same_object(&self, other: &GcRef) -> bool {
+        self.pointer == other.pointer
+    }
+
     pub fn get(&self) -> Option<&PrimitiveValue> {
         if self.pointer.is_null() {
             return None;
@@ -604,13 +564,48 @@ pub enum ModulError {
 }
 
 
+/// The result of expanding the macros in `expression` (see [Memory::get_cached_expansion]).
+struct CachedExpansion {
+    // the cache is keyed by the address of `expression`, holding a reference makes sure it isn't reused by another cell
+    expression:     GcRef,
+    module:         String,
+    current_module: String,
+    expanded:       GcRef,
+}
+
+
+fn is_macro(value: &GcRef) -> bool {
+    matches!(value.get(), Some(PrimitiveValue::Function(f)) if f.get_kind() == FunctionKind::Macro)
+}
+
+
+/// A function call that a signal propagated through.
+pub struct BacktraceFrame {
+    pub function: String,
+    pub call_site: Option<Location>,
+}
+
+
+pub struct Backtrace {
+    signal: *const CellContent,      // only compared, so that the backtrace doesn't keep the signal alive
+    pub frames: Vec<BacktraceFrame>, // innermost first
+    pub omitted: usize,              // the number of outer frames not recorded (see `config::MAX_BACKTRACE_FRAMES`)
+}
+
+
 pub struct Memory {
     // Order of fields matter!
     // Fields are dropped in declaration order.
-    // `modules` and `current_module` must be dropped before `cells`,
+    // `modules`, `current_module`, `expansion_cache` and `readtable` must be dropped before `cells`,
     // because on drop `GcRef` wants to access `cells`.
     modules: HashMap<String, Rc<RefCell<Module>>>,
     current_module: Rc<RefCell<Module>>,
+    expansion_cache: HashMap<*const CellContent, CachedExpansion>,
+    readtable: HashMap<char, GcRef>,
+    backtrace: Option<Backtrace>,
+    signal_kinds: HashMap<String, String>,
+    dynamic_environment: GcRef,
+    string_outputs: Vec<(GcRef, String)>,
     symbols: HashMap<String, *const CellContent>,
     cells: Vec<Cell>,
     first_free: usize,
@@ -624,6 +619,12 @@ impl Memory {
         let default_module = Rc::new(RefCell::new(Module{ name: "default".to_string(), definitions: HashMap::new(), exports: None }));
         Self { modules:        HashMap::from([("default".to_string(), default_module.clone())]),
                current_module: default_module,
+               expansion_cache: HashMap::new(),
+               readtable:      HashMap::new(),
+               backtrace:      None,
+               signal_kinds:   crate::error_utils::SIGNAL_KINDS.iter().map(|(k, p, _)| (k.to_string(), p.to_string())).collect(),
+               dynamic_environment: GcRef::nil(),
+               string_outputs: Vec::new(),
                symbols:        HashMap::new(),
                cells:          (0 .. config::INITIAL_FREE_CELLS).map(|_| Default::default()).collect(),
                first_free:     0,
@@ -644,6 +645,128 @@ impl Memory {
         self.umbilical = Some(umbilical);
     }
 
+    pub fn get_dynamic_environment(&self) -> GcRef {
+        self.dynamic_environment.clone()
+    }
+
+    pub fn set_dynamic_environment(&mut self, environment: GcRef) {
+        self.dynamic_environment = environment;
+    }
+
+    /// Return the innermost dynamic binding of the symbol called `name`, if any.
+    pub fn get_dynamic(&self, name: &str) -> Option<GcRef> {
+        let mut cursor = self.dynamic_environment.clone();
+
+        while let Some(PrimitiveValue::Cons(cons)) = cursor.get() {
+            let key_value = cons.get_car();
+            let pair      = key_value.get().unwrap().as_conscell();
+
+            if pair.get_car().get().unwrap().as_symbol().get_name() == name {
+                return Some(pair.get_cdr());
+            }
+
+            cursor = cons.get_cdr();
+        }
+
+        None
+    }
+
+    /// Create a new string buffer that can be used as an output target instead of `*stdout*`.
+    /// Returns the unique symbol identifying the buffer.
+    pub fn open_string_output(&mut self) -> GcRef {
+        let port = self.unique_symbol();
+        self.string_outputs.push((port.clone(), String::new()));
+        port
+    }
+
+    /// Remove the most recently opened string buffer and return its contents.
+    pub fn close_string_output(&mut self) -> String {
+        self.string_outputs.pop().map(|(_, string)| string).unwrap_or_default()
+    }
+
+    pub fn get_string_output(&mut self, port: &GcRef) -> Option<&mut String> {
+        let Some(PrimitiveValue::Symbol(symbol)) = port.get() else {
+            return None;
+        };
+
+        self.string_outputs.iter_mut().find(|(p, _)| p.get().unwrap().as_symbol() == symbol).map(|(_, string)| string)
+    }
+
+    /// Make `read` call `function` whenever an expression starts with `character`.
+    pub fn set_reader_macro(&mut self, character: char, function: GcRef) {
+        self.readtable.insert(character, function);
+    }
+
+    pub fn get_reader_macro(&self, character: char) -> Option<GcRef> {
+        self.readtable.get(&character).cloned()
+    }
+
+    pub fn remove_reader_macro(&mut self, character: char) -> Option<GcRef> {
+        self.readtable.remove(&character)
+    }
+
+    pub fn get_reader_macro_characters(&self) -> HashSet<char> {
+        self.readtable.keys().copied().collect()
+    }
+
+    /// Forget the backtrace of `signal`, because it has been handled: if it is emitted again, it starts a new backtrace.
+    pub fn forget_backtrace(&mut self, signal: &GcRef) {
+        if self.backtrace.as_ref().map(|backtrace| backtrace.signal == signal.pointer).unwrap_or(false) {
+            self.backtrace = None;
+        }
+    }
+
+    /// Record that `signal` left the call described by `frame`.
+    ///
+    /// The frames are recorded from the innermost call outwards, as long as the same signal keeps propagating;
+    /// a different signal starts a new backtrace.
+    pub fn record_backtrace_frame(&mut self, signal: &GcRef, frame: BacktraceFrame) {
+        match &mut self.backtrace {
+            Some(backtrace) if backtrace.signal == signal.pointer => {
+                if backtrace.frames.len() < config::MAX_BACKTRACE_FRAMES {
+                    backtrace.frames.push(frame);
+                }
+                else {
+                    backtrace.omitted += 1;
+                }
+            },
+            _ => {
+                self.backtrace = Some(Backtrace{ signal: signal.pointer, frames: vec![frame], omitted: 0 });
+            },
+        }
+    }
+
+    /// The backtrace recorded while `signal` was propagating, if it is the last signal that left a function call.
+    pub fn get_backtrace(&self, signal: &GcRef) -> Option<&Backtrace> {
+        self.backtrace.as_ref().filter(|backtrace| backtrace.signal == signal.pointer)
+    }
+
+    /// Declare `kind` as a special case of `parent` in the signal kind tree.
+    /// Return false and change nothing if that would make `kind` its own ancestor.
+    pub fn define_signal_kind(&mut self, kind: &str, parent: &str) -> bool {
+        if self.is_signal_kind_of(parent, kind) {
+            return false;
+        }
+        self.signal_kinds.insert(kind.to_string(), parent.to_string());
+        true
+    }
+
+    pub fn get_signal_kind_parent(&self, kind: &str) -> Option<&str> {
+        self.signal_kinds.get(kind).map(|parent| parent.as_str())
+    }
+
+    /// Whether `kind` is `ancestor` or one of its descendants in the signal kind tree.
+    pub fn is_signal_kind_of(&self, kind: &str, ancestor: &str) -> bool {
+        let mut kind = Some(kind);
+        while let Some(k) = kind {
+            if k == ancestor {
+                return true;
+            }
+            kind = self.get_signal_kind_parent(k);
+        }
+        false
+    }
+
     pub fn get_current_module(&self) -> String {
         self.current_module.borrow().name.clone()
     }
@@ -665,6 +788,8 @@ impl Memory {
     }
 
     pub fn add_export(&mut self, name: &str) {
+        // exporting a name can make it ambiguous, which changes how it is expanded
+        self.expansion_cache.clear();
         let mut current_module = self.current_module.borrow_mut();
         if let Some(exports) = &mut current_module.exports {
             exports.insert(name.to_string());
@@ -684,13 +809,53 @@ impl Memory {
     }
 
     pub fn define_global(&mut self, name: &str, value: GcRef) {
+        if is_macro(&value) || self.is_macro_name(name) {
+            self.expansion_cache.clear();
+        }
         self.current_module.borrow_mut().definitions.insert(name.to_string(), value);
     }
 
     pub fn undefine_global(&mut self, name: &str) {
+        if self.is_macro_name(name) {
+            self.expansion_cache.clear();
+        }
         self.current_module.borrow_mut().definitions.remove(name);
     }
 
+    /// Whether `name` is defined as a macro in any module.
+    pub fn is_macro_name(&self, name: &str) -> bool {
+        self.modules.values().any(|module| module.borrow().definitions.get(name).map(is_macro).unwrap_or(false))
+    }
+
+    /// Return the result of an earlier expansion of the very same `expression` cell in the same `module`
+    /// (and current module, where the symbols are looked up during expansion), if no macro has been (re)defined since then.
+    /// The environment is not part of the key: the same code evaluated in different calls (e.g. a quoted form given to `eval`)
+    /// is expanded only once, even though it is evaluated in a new environment each time.
+    /// So code in an environment that shadows one of its macros must not be cached.
+    pub fn get_cached_expansion(&self, expression: &GcRef, module: &str) -> Option<GcRef> {
+        let cached = self.expansion_cache.get(&(expression.pointer as *const CellContent))?;
+        let hit    = cached.expression.pointer == expression.pointer &&
+                     cached.module == module &&
+                     cached.current_module == self.current_module.borrow().name;
+        hit.then(|| cached.expanded.clone())
+    }
+
+    pub fn cache_expansion(&mut self, expression: &GcRef, module: &str, expanded: &GcRef) {
+        if expression.pointer.is_null() {
+            return;
+        }
+        if self.expansion_cache.len() >= config::EXPANSION_CACHE_SIZE {
+            self.expansion_cache.clear();
+        }
+        let cached = CachedExpansion {
+            expression:     expression.clone(),
+            module:         module.to_string(),
+            current_module: self.get_current_module(),
+            expanded:       expanded.clone(),
+        };
+        self.expansion_cache.insert(expression.pointer as *const CellContent, cached);
+    }
+
     pub fn get_global(&self, name: &str, module_name: &str) -> Result<GcRef, ModulError> {
         let mut found = false;
         let mut result = GcRef::nil();
@@ -792,19 +957,58 @@ impl Memory {
         GcRef::new(ptr)
     }
 
+    /// Replace the cdr of the cons cell `cons` in place.
+    ///
+    /// Cons cells are otherwise immutable; this is only meant for tying recursive knots
+    /// when building environments (e.g. for `letrec` or named `let`).
+    pub fn set_cdr(&mut self, cons: &GcRef, cdr: GcRef) {
+        if cons.pointer.is_null() {
+            panic!("attempted to set the cdr of nil");
+        }
+
+        let mut pointer = cons.pointer;
+        if let MetaValue::Meta{value, meta: _} = unsafe {&(*pointer).metavalue} {
+            pointer = *value;
+        }
+
+        if let MetaValue::Value(PrimitiveValue::Cons(cons_cell)) = unsafe {&mut (*pointer).metavalue} {
+            cons_cell.cdr = cdr.pointer;
+        }
+        else {
+            panic!("attempted to set the cdr of a non-conscell");
+        }
+    }
+
     pub fn allocate_normal_function(&mut self, kind: FunctionKind, has_rest_params: bool, body: GcRef, params: &[GcRef], environment: GcRef, environment_module: &str) -> GcRef {
-        let mut param_ptrs = vec![];
-        for param in params {
-            if !matches!(param.get(), Some(PrimitiveValue::Symbol(_))) {
-                panic!("Function parameter is not a Symbol");
+        let (required, rest) =
+        if has_rest_params {
+            let (rest, required) = params.split_last().expect("rest parameter is missing");
+            (required.to_vec(), Some(rest.clone()))
+        }
+        else {
+            (params.to_vec(), None)
+        };
+
+        let lambda_list = LambdaList{ required, optional: vec![], rest, key: vec![] };
+        self.allocate_normal_function_with_lambda_list(kind, body, &lambda_list, environment, environment_module)
+    }
+
+    pub fn allocate_normal_function_with_lambda_list(&mut self, kind: FunctionKind, body: GcRef, lambda_list: &LambdaList, environment: GcRef, environment_module: &str) -> GcRef {
+        for param in lambda_list.required.iter().chain(lambda_list.optional.iter().map(|(p, _)| p)).chain(lambda_list.rest.iter()).chain(lambda_list.key.iter().map(|(k, _)| k)) {
+            // parameters are either symbols or destructuring patterns (lists, including `()`)
+            if !param.is_nil() && !matches!(param.get(), Some(PrimitiveValue::Symbol(_)) | Some(PrimitiveValue::Cons(_))) {
+                panic!("Function parameter is neither a Symbol nor a pattern");
             }
-            param_ptrs.push(param.pointer);
         }
 
+        let pairs = |ps: &Vec<(GcRef, GcRef)>| ps.iter().map(|(p, d)| (p.pointer, d.pointer)).collect();
+
         let f = PrimitiveValue::Function(Function::NormalFunction(NormalFunction{ kind,
-                                                                                  has_rest_params,
+                                                                                  parameters: lambda_list.required.iter().map(|p| p.pointer).collect(),
+                                                                                  optional_parameters: pairs(&lambda_list.optional),
+                                                                                  rest_parameter: lambda_list.rest.as_ref().map(|p| p.pointer),
+                                                                                  key_parameters: pairs(&lambda_list.key),
                                                                                   body: body.pointer,
-                                                                                  parameters: param_ptrs,
                                                                                   environment: environment.pointer,
                                                                                   environment_module: environment_module.to_string()}));
         let ptr = self.allocate_internal(MetaValue::Value(f));
@@ -885,8 +1089,11 @@ impl Memory {
         let mut reachable = HashSet::new();
 
         while let Some(cell) = stack.pop() {
-            reachable.insert(cell);
-            
+            // environments of recursive functions (`letrec`, named `let`) form cycles
+            if !reachable.insert(cell) {
+                continue;
+            }
+
             if cell.is_null() {
                 continue;
             }
@@ -928,7 +1135,8 @@ impl Memory {
                     if !f.environment.is_null() {
                         stack.push(f.environment);
                     }
-                    for p in f.parameters.iter() {
+                    let optional_and_key = f.optional_parameters.iter().chain(f.key_parameters.iter()).flat_map(|(p, d)| [p, d]);
+                    for p in f.parameters.iter().chain(f.rest_parameter.iter()).chain(optional_and_key) {
                         if !p.is_null() {
                             stack.push(*p);
                         }
@@ -175,7 +175,7 @@ fn mem_allocate_function() {
     let has_rest_params = false;
     let fun = mem.allocate_normal_function(FunctionKind::Lambda, has_rest_params, body, &vec![p1, p2, p3], GcRef::nil(), "default");
     assert_eq!(*fun.get().unwrap().as_function().as_normal_function().get_body().get().unwrap().as_character(), 'ß');
-    let mut params = fun.get().unwrap().as_function().as_normal_function().non_rest_params();
+    let mut params = fun.get().unwrap().as_function().as_normal_function().get_lambda_list().required.into_iter();
     assert_eq!(params.next().unwrap().get().unwrap().as_symbol(), mem.symbol_for("oak").get().unwrap().as_symbol());
     assert_eq!(params.next().unwrap().get().unwrap().as_symbol(), mem.symbol_for("pine").get().unwrap().as_symbol());
     assert_eq!(params.next().unwrap().get().unwrap().as_symbol(), mem.symbol_for("elm").get().unwrap().as_symbol());
@@ -200,7 +200,28 @@ fn gc_collect_functions() {
 
         mem.symbol_for("tulip");
 
-        assert_eq!(fun.get().unwrap().as_function().as_normal_function().non_rest_params().collect::<Vec<_>>().len(), 3);
+        assert_eq!(fun.get().unwrap().as_function().as_normal_function().get_lambda_list().required.len(), 3);
+    }
+
+    mem.collect();
+
+    assert_eq!(mem.used_count(), 0);
+}
+
+#[test]
+fn gc_collect_cycles() {
+    let mut mem = Memory::new();
+
+    {
+        let x    = mem.allocate_number(7);
+        let cons = mem.allocate_cons(x, GcRef::nil());
+        mem.set_cdr(&cons, cons.clone());
+
+        assert_eq!(*cons.get().unwrap().as_conscell().get_cdr().get().unwrap().as_conscell().get_car().get().unwrap().as_number(), 7);
+
+        mem.collect();
+
+        assert_eq!(mem.used_count(), 2);
     }
 
     mem.collect();
@@ -232,12 +253,12 @@ fn mem_allocate_meta() {
     
     {
         let loc1 = Location::Stdin { line: 23, column: 42 };
-        let md1  = Metadata{ read_name: "".to_string(), location: loc1, documentation: "".to_string() };
+        let md1  = Metadata{ read_name: "".to_string(), location: loc1, documentation: "".to_string(), span: None };
         let x1   = mem.allocate_number(1370);
         let m1   = mem.allocate_metadata(x1, md1);
 
         let loc2 = Location::File { path: PathBuf::from("~/the/input/file.lisp"), line: 41, column: 42 };
-        let md2  = Metadata{ read_name: "".to_string(), location: loc2, documentation: "Very important information".to_string() };
+        let md2  = Metadata{ read_name: "".to_string(), location: loc2, documentation: "Very important information".to_string(), span: None };
         let x2   = mem.allocate_character(' ');
         let m2   = mem.allocate_metadata(x2, md2);
 
@@ -280,3 +301,91 @@ fn mem_globals() {
 
     assert_eq!(mem.get_global("y", &mem.get_current_module()).unwrap().get().unwrap().as_symbol(), mem.symbol_for("thing").get().unwrap().as_symbol());
 }
+
+#[test]
+fn expansion_cache_invalidated_by_macro_definition() {
+    let mut mem = Memory::new();
+
+    let x        = mem.allocate_number(1);
+    let code     = mem.allocate_cons(x, GcRef::nil());
+    let expanded = mem.allocate_number(2);
+    mem.cache_expansion(&code, "default", &expanded);
+    assert!(mem.get_cached_expansion(&code, "default").is_some());
+    assert!(mem.get_cached_expansion(&code, "other").is_none());
+
+    // defining a function doesn't affect expansion
+    let function = mem.allocate_normal_function(FunctionKind::Lambda, false, GcRef::nil(), &[], GcRef::nil(), "default");
+    mem.define_global("f", function);
+    assert!(mem.get_cached_expansion(&code, "default").is_some());
+
+    // but defining a macro does
+    let function = mem.allocate_normal_function(FunctionKind::Macro, false, GcRef::nil(), &[], GcRef::nil(), "default");
+    mem.define_global("m", function);
+    assert!(mem.get_cached_expansion(&code, "default").is_none());
+
+    mem.cache_expansion(&code, "default", &expanded);
+    mem.undefine_global("m");
+    assert!(mem.get_cached_expansion(&code, "default").is_none());
+
+    // symbols are looked up in the current module during expansion
+    mem.cache_expansion(&code, "default", &expanded);
+    mem.define_module("other");
+    assert!(mem.get_cached_expansion(&code, "default").is_none());
+}
+
+#[test]
+fn backtrace_follows_one_signal() {
+    let mut mem = Memory::new();
+
+    let frame   = |name: &str| BacktraceFrame{ function: name.to_string(), call_site: None };
+    let signal1 = mem.symbol_for("first");
+    let signal2 = mem.allocate_number(2);
+    mem.record_backtrace_frame(&signal1, frame("f"));
+    mem.record_backtrace_frame(&signal1, frame("g"));
+    let names = mem.get_backtrace(&signal1).unwrap().frames.iter().map(|f| f.function.clone()).collect::<Vec<String>>();
+    assert_eq!(names, vec!["f", "g"]);
+    assert!(mem.get_backtrace(&signal2).is_none());
+
+    // another signal starts a new backtrace
+    mem.record_backtrace_frame(&signal2, frame("h"));
+    assert_eq!(mem.get_backtrace(&signal2).unwrap().frames.len(), 1);
+    assert!(mem.get_backtrace(&signal1).is_none());
+
+    for _ in 0 .. config::MAX_BACKTRACE_FRAMES {
+        mem.record_backtrace_frame(&signal2, frame("h"));
+    }
+    assert_eq!(mem.get_backtrace(&signal2).unwrap().frames.len(), config::MAX_BACKTRACE_FRAMES);
+    assert_eq!(mem.get_backtrace(&signal2).unwrap().omitted, 1);
+
+    // once the signal is handled, emitting it again starts a new backtrace
+    mem.forget_backtrace(&signal1);
+    assert!(mem.get_backtrace(&signal2).is_some());
+    mem.forget_backtrace(&signal2);
+    assert!(mem.get_backtrace(&signal2).is_none());
+    mem.record_backtrace_frame(&signal2, frame("i"));
+    let names = mem.get_backtrace(&signal2).unwrap().frames.iter().map(|f| f.function.clone()).collect::<Vec<String>>();
+    assert_eq!(names, vec!["i"]);
+}
+
+#[test]
+fn signal_kind_tree() {
+    let mut mem = Memory::new();
+
+    assert!(mem.is_signal_kind_of("divide-by-zero", "arithmetic-error"));
+    assert!(mem.is_signal_kind_of("divide-by-zero", "error"));
+    assert!(mem.is_signal_kind_of("divide-by-zero", "divide-by-zero"));
+    assert!(!mem.is_signal_kind_of("arithmetic-error", "divide-by-zero"));
+    assert!(!mem.is_signal_kind_of("undeclared", "error"));
+    assert!(mem.is_signal_kind_of("undeclared", "undeclared"));
+
+    assert!(mem.define_signal_kind("my-error", "arithmetic-error"));
+    assert!(mem.define_signal_kind("my-sub-error", "my-error"));
+    assert!(mem.is_signal_kind_of("my-sub-error", "error"));
+    assert_eq!(mem.get_signal_kind_parent("my-sub-error"), Some("my-error"));
+
+    // a kind can't become its own ancestor
+    assert!(!mem.define_signal_kind("my-error", "my-sub-error"));
+    assert!(!mem.define_signal_kind("my-error", "my-error"));
+    assert_eq!(mem.get_signal_kind_parent("my-error"), Some("arithmetic-error"));
+    assert_eq!(mem.get_signal_kind_parent("error"), None);
+}
@@ -48,6 +48,15 @@ impl Location {
         }
     }
 
+    pub fn set_position(&mut self, new_line: usize, new_column: usize) {
+        match self {
+            Self::Native                                    => {},
+            Self::Prelude{ref mut line, ref mut column}     => { *line = new_line; *column = new_column },
+            Self::Stdin  {ref mut line, ref mut column}     => { *line = new_line; *column = new_column },
+            Self::File   {ref mut line, ref mut column, ..} => { *line = new_line; *column = new_column },
+        }
+    }
+
     pub fn step_column(&mut self) {
         match self {
             Self::Native                      => {},
@@ -59,9 +68,27 @@ impl Location {
 }
 
 
+/// A character of the source: its line and column (both 1-based), and the byte offset where it begins.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+
+/// The source text an expression was read from, from its first character to its last character (inclusive).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Metadata {
     pub read_name: String,
     pub location: Location,
     pub documentation: String,
+    pub span: Option<Span>,
 }
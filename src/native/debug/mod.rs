@@ -21,8 +21,8 @@ If no debugger is attached, don't do anything."
 pub fn send(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
     validate_args!(mem, SEND.name, args, (let data: TypeLabel::List));    
 
-    let details = vec![("symbol", mem.symbol_for("data"))];
-    let invalid_plist_error = make_error(mem, "invalid-plist", SEND.name, &details);
+    let details = vec![("argument-value", args[0].clone())];
+    let invalid_plist_error = LispError::new("invalid-plist", SEND.name).details(&details).build(mem);
     let mut dm = DebugMessage::new();
     for d in data.chunks(2) {
         let key =
@@ -62,7 +62,7 @@ pub fn receive(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth:
         let msg = umb.from_high_end.recv().expect("supervisor thread disappeared");
         match msg.get("command").map(|s| s.as_str()) {
             Some("INTERRUPT") => {
-                return Err(make_error(mem, "interrupted", RECEIVE.name, &vec![]));
+                return Err(LispError::new("interrupted", RECEIVE.name).build(mem));
             },
             Some("ABORT") => {
                 return Err(GcRef::nil());
@@ -1,14 +1,22 @@
 use crate::memory::*;
 use crate::util::*;
 use crate::native::read::read;
-use crate::native::list::property;
+use crate::native::list::{property, make_plist};
+use crate::native::signal::offer_restarts;
 use crate::error_utils::*;
 use crate::config;
 use super::NativeFunctionMetaData;
+use pattern::*;
+use quasiquote::*;
+pub use pattern::TYPE_GUARDS;
 
 
+mod pattern;
+mod quasiquote;
 
-fn lookup(mem: &mut Memory, key: GcRef, environment: GcRef, environment_module: &str) -> Result<GcRef, ModulError> {
+
+
+pub(crate) fn lookup_in_environment(key: &GcRef, environment: GcRef) -> Option<GcRef> {
     let mut cursor = environment;
 
     while let Some(c) = cursor.get() {
@@ -16,18 +24,139 @@ fn lookup(mem: &mut Memory, key: GcRef, environment: GcRef, environment_module:
         let key_value = cons.get_car();
 
         if key_value.get().unwrap().as_conscell().get_car().get().unwrap().as_symbol() == key.get().unwrap().as_symbol() {
-            return Ok(key_value.get().unwrap().as_conscell().get_cdr());
+            return Some(key_value.get().unwrap().as_conscell().get_cdr());
         }
 
         cursor = cons.get_cdr();
     }
 
+    None
+}
+
+
+fn lookup(mem: &mut Memory, key: GcRef, environment: GcRef, environment_module: &str) -> Result<GcRef, ModulError> {
+    // lexical bindings shadow dynamic bindings, which in turn shadow globals
+    if let Some(value) = lookup_in_environment(&key, environment) {
+        return Ok(value);
+    }
+
+    if let Some(value) = lookup_in_environment(&key, mem.get_dynamic_environment()) {
+        return Ok(value);
+    }
+
     mem.get_global(&key.get().unwrap().as_symbol().get_name(), environment_module)
 }
 
 
-fn pair_params_and_args(mem: &mut Memory, nf: &NormalFunction, nf_name: Option<String>, args: &[GcRef]) -> Result<GcRef, GcRef> {
-    let mut new_env = nf.get_env();
+/// Split `bindings` (a flat list in the form of `name1 value1 name2 value2 ...`) into name-value pairs.
+/// If `allow_patterns` is true then the names can also be destructuring patterns.
+fn parse_bindings(mem: &mut Memory, bindings: &[GcRef], source: &str, allow_patterns: bool) -> Result<Vec<(GcRef, GcRef)>, GcRef> {
+    let mut pairs = vec![];
+
+    for binding in bindings.chunks(2) {
+        if binding[0].get_type() != TypeLabel::Symbol && !(allow_patterns && (binding[0].get_type() == TypeLabel::Cons || binding[0].is_nil())) {
+            let error_details = vec![("name", binding[0].clone())];
+            return Err(LispError::new("binding-name-is-not-symbol", source).details(&error_details).build(mem));
+        }
+
+        if allow_patterns {
+            validate_pattern(mem, &binding[0], source)?;
+        }
+
+        if let Some(value) = binding.get(1) {
+            pairs.push((binding[0].clone(), value.clone()));
+        }
+        else {
+            let error_details = vec![("name", binding[0].clone())];
+            return Err(LispError::new("incomplete-binding", source).details(&error_details).build(mem));
+        }
+    }
+
+    Ok(pairs)
+}
+
+
+#[derive(Clone, Copy)]
+enum LetKind {
+    Parallel,   // let:    values can't see any of the new bindings
+    Sequential, // let*:   values can see the bindings before them
+    Recursive,  // letrec: values can see all of the new bindings (including their own)
+}
+
+
+/// Extend `env` with `bindings` according to `kind`.
+fn bind_let(mem: &mut Memory, kind: LetKind, bindings: &[GcRef], env: GcRef, env_module: &str, source: &str, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    // the names in `letrec` all have to be bound before evaluating any of the values, so they can't be patterns
+    let allow_patterns = !matches!(kind, LetKind::Recursive);
+    let pairs          = parse_bindings(mem, bindings, source, allow_patterns)?;
+    let mut new_env    = env.clone();
+
+    match kind {
+        LetKind::Parallel | LetKind::Sequential => {
+            for (name, value) in pairs {
+                let value_env    = if let LetKind::Parallel = kind {env.clone()} else {new_env.clone()};
+                let evaled_value = eval_internal(mem, value, value_env, env_module.to_string(), recursion_depth + 1)?;
+                new_env          = bind_pattern(mem, name, evaled_value, new_env, source)?;
+            }
+        },
+        LetKind::Recursive => {
+            // first bind all names (to nil)...
+            let mut name_values = vec![];
+            for (name, _) in pairs.iter() {
+                let name_value = mem.allocate_cons(name.clone(), GcRef::nil());
+                new_env        = mem.allocate_cons(name_value.clone(), new_env);
+                name_values.push(name_value);
+            }
+
+            // ...then evaluate the values in the new environment and fill them in
+            for ((_, value), name_value) in pairs.into_iter().zip(name_values) {
+                let evaled_value = eval_internal(mem, value, new_env.clone(), env_module.to_string(), recursion_depth + 1)?;
+                mem.set_cdr(&name_value, evaled_value);
+            }
+        },
+    }
+
+    Ok(new_env)
+}
+
+
+/// Call `f` while `bindings` are pushed onto the dynamic environment.
+///
+/// The previous dynamic environment is restored afterwards,
+/// regardless of whether `f` returned normally or a signal (or an abort) is unwinding through it.
+pub fn parameterize<F>(mem: &mut Memory, bindings: &[(GcRef, GcRef)], f: F) -> Result<GcRef, GcRef>
+where F: FnOnce(&mut Memory) -> Result<GcRef, GcRef>
+{
+    let old_environment = mem.get_dynamic_environment();
+    let mut new_environment = old_environment.clone();
+
+    for (name, value) in bindings {
+        let name_value  = mem.allocate_cons(name.clone(), value.clone());
+        new_environment = mem.allocate_cons(name_value, new_environment);
+    }
+
+    mem.set_dynamic_environment(new_environment);
+    let result = f(mem);
+    mem.set_dynamic_environment(old_environment);
+
+    result
+}
+
+
+/// Evaluate the default expression of an optional or key parameter in the environment of the call
+/// (where the previous parameters are already bound).
+/// The default expressions are expanded together with the body of the function, see `macroexpand_internal`.
+fn eval_default(mem: &mut Memory, default: GcRef, env: GcRef, env_module: &str, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    eval_internal(mem, default, env, env_module.to_string(), recursion_depth + 1)
+}
+
+
+fn pair_params_and_args(mem: &mut Memory, nf: &NormalFunction, nf_name: Option<String>, args: &[GcRef], recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let mut new_env     = nf.get_env();
+    let env_module      = nf.get_env_module();
+    let lambda_list     = nf.get_lambda_list();
+    let required_count  = lambda_list.required.len();
+    let positional_count = required_count + lambda_list.optional.len();
 
     let source = if let Some(name) = nf_name {
         name
@@ -36,32 +165,65 @@ fn pair_params_and_args(mem: &mut Memory, nf: &NormalFunction, nf_name: Option<S
         "#<function>".to_string()
     };
 
-    let mut i = 0;
-    for param in nf.non_rest_params() {
-        let arg; 
-        if let Some(a) = args.get(i) {
-            arg = a.clone();
+    let max_count = if lambda_list.rest.is_some() || !lambda_list.key.is_empty() {None} else {Some(positional_count)};
+    if args.len() < required_count || max_count.is_some_and(|max| args.len() > max) {
+        let error_details =
+        if max_count == Some(required_count) {
+            vec![("expected", fit_to_number(mem, required_count)), ("actual", fit_to_number(mem, args.len()))]
         }
         else {
-            let error_details = vec![("expected", fit_to_number(mem, i + 1)), ("actual", fit_to_number(mem, args.len()))];
-            let error = make_error(mem, "wrong-number-of-arguments", &source, &error_details);
-            return Err(error);
+            let mut details = vec![("expected-min", fit_to_number(mem, required_count))];
+            if let Some(max) = max_count {
+                details.push(("expected-max", fit_to_number(mem, max)));
+            }
+            details.push(("actual", fit_to_number(mem, args.len())));
+            details
         };
-        let param_arg = mem.allocate_cons(param, arg);
-        new_env       = mem.allocate_cons(param_arg, new_env);
+        let error = LispError::new("wrong-number-of-arguments", &source).details(&error_details).build(mem);
+        return Err(error);
+    }
 
-        i += 1;
+    for (param, arg) in lambda_list.required.into_iter().zip(args.iter()) {
+        new_env = bind_pattern(mem, param, arg.clone(), new_env, &source)?;
     }
 
-    if let Some(rest_param) = nf.rest_param() {
-        let rest_args = vec_to_list(mem, &args[i..]);
-        let param_arg = mem.allocate_cons(rest_param, rest_args);
-        new_env       = mem.allocate_cons(param_arg, new_env);
+    for (i, (param, default)) in lambda_list.optional.into_iter().enumerate() {
+        let arg = match args.get(required_count + i) {
+            Some(arg) => arg.clone(),
+            None      => eval_default(mem, default, new_env.clone(), &env_module, recursion_depth)?,
+        };
+        new_env = bind_pattern(mem, param, arg, new_env, &source)?;
     }
-    else if i < args.len() {
-        let error_details = vec![("expected", fit_to_number(mem, i)), ("actual", fit_to_number(mem, args.len()))];
-        let error = make_error(mem, "wrong-number-of-arguments", &source, &error_details);
-        return Err(error);
+
+    let rest_args = &args[positional_count.min(args.len()) ..];
+
+    if let Some(rest_param) = lambda_list.rest {
+        let rest_args = vec_to_list(mem, rest_args);
+        new_env       = bind_pattern(mem, rest_param, rest_args, new_env, &source)?;
+    }
+    else if !lambda_list.key.is_empty() {
+        // the remaining arguments form a property list
+        if !rest_args.len().is_multiple_of(2) {
+            let error_details = vec![("arguments", vec_to_list(mem, rest_args))];
+            return Err(LispError::new("odd-number-of-key-arguments", &source).details(&error_details).build(mem));
+        }
+
+        // the names of the key parameters, without their type annotations
+        let names = lambda_list.key.iter().map(|(key, _)| split_type_annotation(mem, key).0).collect::<Vec<GcRef>>();
+        for key_value in rest_args.chunks(2) {
+            if !names.iter().any(|name| symbol_eq!(name, key_value[0])) {
+                let error_details = vec![("key", key_value[0].clone()), ("allowed", vec_to_list(mem, &names))];
+                return Err(LispError::new("unknown-key-argument", &source).details(&error_details).build(mem));
+            }
+        }
+
+        for ((key, default), name) in lambda_list.key.into_iter().zip(names) {
+            let arg = match rest_args.chunks(2).find(|key_value| symbol_eq!(name, key_value[0])) {
+                Some(key_value) => key_value[1].clone(),
+                None            => eval_default(mem, default, new_env.clone(), &env_module, recursion_depth)?,
+            };
+            new_env = bind_pattern(mem, key, arg, new_env, &source)?;
+        }
     }
 
     Ok(new_env)
@@ -79,7 +241,7 @@ NativeFunctionMetaData{
 
 pub fn call_native_function(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
     if recursion_depth > config::MAX_RECURSION_DEPTH {
-        return Err(make_error(mem, "stackoverflow", CALL_NATIVE_FUNCTION.name, &vec![]));
+        return Err(LispError::new("stackoverflow", CALL_NATIVE_FUNCTION.name).build(mem));
     }
     validate_args!(mem, CALL_NATIVE_FUNCTION.name, args, (let function: TypeLabel::Function), (let arguments: TypeLabel::List), (let environment: TypeLabel::Any));
 
@@ -88,7 +250,7 @@ pub fn call_native_function(mem: &mut Memory, args: &[GcRef], _env: GcRef, recur
     }
     else {
         let details = vec![("expected", mem.symbol_for("native-function")), ("actual", mem.symbol_for("normal-function"))];
-        Err(make_error(mem, "wrong-argument", CALL_NATIVE_FUNCTION.name, &details))
+        Err(LispError::new("wrong-argument", CALL_NATIVE_FUNCTION.name).details(&details).build(mem))
     }
 }
 
@@ -104,7 +266,7 @@ NativeFunctionMetaData{
 
 pub fn make_trap(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
     if recursion_depth > config::MAX_RECURSION_DEPTH {
-        return Err(make_error(mem, "stackoverflow", MAKE_TRAP.name, &vec![]));
+        return Err(LispError::new("stackoverflow", MAKE_TRAP.name).build(mem));
     }
     validate_args!(mem, MAKE_TRAP.name, args, (let normal_body: TypeLabel::Any), (let trap_body: TypeLabel::Any));
 
@@ -124,7 +286,7 @@ NativeFunctionMetaData{
 
 pub fn make_function(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
     if recursion_depth > config::MAX_RECURSION_DEPTH {
-        return Err(make_error(mem, "stackoverflow", MAKE_FUNCTION.name, &vec![]));
+        return Err(LispError::new("stackoverflow", MAKE_FUNCTION.name).build(mem));
     }
     validate_args!(mem, MAKE_FUNCTION.name, args, (let _params: TypeLabel::List), (let _body: TypeLabel::Any), (let environment: TypeLabel::Any), (let env_module: TypeLabel::Symbol), (let kind: TypeLabel::Symbol));
 
@@ -132,80 +294,105 @@ pub fn make_function(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_de
     match kind.get_name().as_str() {
         "lambda-type"         => ("lambda", FunctionKind::Lambda),
         "macro-type"          => ("macro", FunctionKind::Macro),
-        _ => return Err(make_error(mem, "wrong-arg-value", MAKE_FUNCTION.name, &vec![])),
+        _ => return Err(LispError::new("wrong-arg-value", MAKE_FUNCTION.name).build(mem)),
     };
 
     make_function_internal(mem, &args[0..2], environment, &env_module.get_name(), s, k)
 }
 
 
+pub const BIND_PATTERN: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      bind_pattern_native,
+    name:          "bind-pattern",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["pattern", "value", "environment"],
+    documentation: "Match `value` against the destructuring `pattern` (as used in parameter lists and `let`)
+and return `environment` extended with the resulting bindings.
+Signal `pattern-mismatch` if `value` does not fit `pattern`."
+};
+
+pub fn bind_pattern_native(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, BIND_PATTERN.name, args, (let pattern: TypeLabel::Any), (let value: TypeLabel::Any), (let _environment: TypeLabel::List));
+
+    validate_pattern(mem, &pattern, BIND_PATTERN.name)?;
+    bind_pattern(mem, pattern, value, args[2].clone(), BIND_PATTERN.name)
+}
+
+
+pub const BIND_ARGUMENTS: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      bind_arguments,
+    name:          "bind-arguments",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["function", "arguments"],
+    documentation: "Return the environment in which the body of `function` would be evaluated
+if it were called with `arguments` (including the defaults of the missing optional and key parameters).
+Error if `function` is a native function."
+};
+
+pub fn bind_arguments(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    if recursion_depth > config::MAX_RECURSION_DEPTH {
+        return Err(LispError::new("stackoverflow", BIND_ARGUMENTS.name).build(mem));
+    }
+    validate_args!(mem, BIND_ARGUMENTS.name, args, (let f: TypeLabel::Function), (let arguments: TypeLabel::List));
+
+    let Function::NormalFunction(nf) = f else {
+        let error_details = vec![("argument-value", args[0].clone())];
+        return Err(LispError::new("native-function", BIND_ARGUMENTS.name).details(&error_details).build(mem));
+    };
+
+    let name = args[0].get_meta().map(|md| md.read_name.clone());
+    pair_params_and_args(mem, nf, name, &arguments, recursion_depth)
+}
+
+
 fn make_function_internal(mem: &mut Memory, args: &[GcRef], env: GcRef, env_module: &str, source: &str, kind: FunctionKind) -> Result<GcRef, GcRef> {
     validate_args!(mem, source, args, (let params: TypeLabel::List), (let body: TypeLabel::Any));
     
-    let mut actual_params   = vec![];
-    let mut has_rest_params = false;
-    let rest_param_symbol   = mem.symbol_for("&");
-    let param_count         = params.len();
-    let mut i               = 0;
-
-    for param in params {
-        if let Some(PrimitiveValue::Symbol(symbol)) = param.get() {
-            if has_rest_params {
-                actual_params.push(param.clone());
-                break;
-            }
+    let lambda_list = parse_lambda_list(mem, &params, source)?;
+    let function    = mem.allocate_normal_function_with_lambda_list(kind, body, &lambda_list, env, env_module);
+    Ok(function)
+}
 
-            if symbol == rest_param_symbol.get().unwrap().as_symbol() {
-                // i == param_count - 2  (rearranged to avoid underflow when param_count == 0)
-                if i + 2 == param_count {
-                    //          ---4---
-                    //          0 1 2 3
-                    // (lambda (x y & z) ...
-                    //              ^
-                    //              4 - 2
-                    has_rest_params = true;
-                }
-                //      i > param_count - 2
-                else if i + 2 > param_count {
-                    //          ---4---
-                    //          0 1 2 3
-                    // (lambda (x y z &) ...
-                    //                ^
-                    //                3 > 4 - 2
-                    let error = make_error(mem, "missing-rest-parameter", source, &vec![]);
-                    return Err(error);
-                }
-                // i < param_count - 2
-                else {
-                    //          ---4---
-                    //          0 1 2 3
-                    // (lambda (x & y z) ...
-                    //            ^
-                    //            1 < 4 - 2
-                    let error = make_error(mem, "multiple-rest-parameters", source, &vec![]);
-                    return Err(error);
-                }
-            }
-            else {
-                actual_params.push(param.clone());
-            }
-        }
-        else {
-            let error_details = vec![("param", param)];
-            let error = make_error(mem, "param-is-not-symbol", source, &error_details);
-            return Err(error);
-        }
+/// The name of the function called by `expression` if its operator is a symbol (used as the `source` of errors).
+fn operator_name(expression: &GcRef) -> Option<String> {
+    match expression.get() {
+        Some(PrimitiveValue::Cons(cons)) if cons.get_car().get_type() == TypeLabel::Symbol => Some(cons.get_car().get().unwrap().as_symbol().get_name()),
+        _                                                                               => None,
+    }
+}
 
-        i += 1;
+/// Describe the call of `function`, whose operator in the call is `operator` (e.g. the name of the function).
+fn call_frame(function: &GcRef, operator: &GcRef) -> BacktraceFrame {
+    let name = function.get_meta().map(|md| md.read_name.clone()).filter(|name| !name.is_empty());
+    let name = name.or_else(|| operator.get().filter(|x| matches!(x, PrimitiveValue::Symbol(_))).map(|x| x.as_symbol().get_name()));
+    BacktraceFrame{
+        function:  name.unwrap_or_else(|| "<lambda>".to_string()),
+        call_site: operator.get_meta().map(|md| md.location.clone()),
     }
+}
 
-    let function = mem.allocate_normal_function(kind, has_rest_params, body, &actual_params, env, env_module);
-    Ok(function)
+
+fn eval_internal(mem: &mut Memory, expression: GcRef, env: GcRef, env_module: String, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let mut frame = None;
+    let result    = eval_calls(mem, expression, env, env_module, recursion_depth, &mut frame);
+
+    if let (Err(signal), Some(frame)) = (&result, frame) {
+        if !signal.is_nil() {
+            mem.record_backtrace_frame(signal, frame);
+        }
+    }
+
+    result
 }
 
-fn eval_internal(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut env_module: String, recursion_depth: usize) -> Result<GcRef, GcRef> {
+
+/// Evaluate `expression`, setting `frame` to the function call being evaluated
+/// (a tail call replaces its caller), so that it can be recorded in the backtrace of a signal.
+fn eval_calls(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut env_module: String, recursion_depth: usize, frame: &mut Option<BacktraceFrame>) -> Result<GcRef, GcRef> {
     if recursion_depth > config::MAX_RECURSION_DEPTH {
-        return Err(make_error(mem, "stackoverflow", EVAL.name, &vec![]));
+        return Err(LispError::new("stackoverflow", EVAL.name).build(mem));
     }
 
     // loop is only used to jump back to the beginning of the function (using `continue`); never runs until the end more than once
@@ -214,7 +401,7 @@ fn eval_internal(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut en
             if let Ok(msg) = umb.from_high_end.try_recv() {
                 match msg.get("command").map(|s| s.as_str()) {
                     Some("INTERRUPT") => {
-                        return Err(make_error(mem, "interrupted", EVAL.name, &vec![]));
+                        return Err(LispError::new("interrupted", EVAL.name).build(mem));
                     },
                     Some("ABORT") => {
                         return Err(GcRef::nil());
@@ -224,7 +411,7 @@ fn eval_internal(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut en
             }
         }
 
-        let name = expression.get_meta().map(|md| md.read_name.clone());
+        let name = operator_name(&expression);
 
         if let Some(mut list_elems) = list_to_vec(expression.clone()) {
             // `expression` is a list
@@ -259,6 +446,97 @@ fn eval_internal(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut en
                     validate_args!(mem, "trap", &list_elems[1..], (let normal_body: TypeLabel::Any), (let trap_body: TypeLabel::Any));
                     return Ok(mem.allocate_trap(normal_body, trap_body));
                 }
+                else if symbol_eq!(list_elems[0], mem.symbol_for("parameterize")) {
+                    validate_args!(mem, "parameterize", &list_elems[1..], (let bindings: TypeLabel::List), (let body: TypeLabel::Any));
+                    let mut evaled_bindings = vec![];
+                    for (name, value) in parse_bindings(mem, &bindings, "parameterize", false)? {
+                        let evaled_value = eval_internal(mem, value, env.clone(), env_module.clone(), recursion_depth + 1)?;
+                        evaled_bindings.push((name, evaled_value));
+                    }
+                    // no tail-call elimination here: the dynamic environment has to be restored after `body` returns
+                    return parameterize(mem, &evaled_bindings, |mem| eval_internal(mem, body, env, env_module, recursion_depth + 1));
+                }
+                else if symbol_eq!(list_elems[0], mem.symbol_for("unwind-protect")) {
+                    // `(unwind-protect body cleanup)`: evaluate `cleanup` after `body`, whether `body` returned normally,
+                    // emitted a signal (including `interrupted`) or was aborted (by `abort` or the debugger's ABORT command).
+                    // A signal emitted by `cleanup` replaces the result of `body`, except that an abort can't be undone.
+                    validate_args!(mem, "unwind-protect", &list_elems[1..], (let body: TypeLabel::Any), (let cleanup: TypeLabel::Any));
+                    // no tail-call elimination here: `cleanup` has to be evaluated after `body` returns
+                    let result  = eval_internal(mem, body, env.clone(), env_module.clone(), recursion_depth + 1);
+                    let cleaned = eval_internal(mem, cleanup, env, env_module, recursion_depth + 1);
+                    return match (result, cleaned) {
+                        (Err(abort), _) if abort.is_nil() => Err(abort),
+                        (_, Err(signal))                  => Err(signal),
+                        (result, Ok(_))                   => result,
+                    };
+                }
+                else if symbol_eq!(list_elems[0], mem.symbol_for("match")) {
+                    // `(match expression (pattern1 body1) (pattern2 body2) ...)`
+                    if list_elems.len() < 2 {
+                        let error_details = vec![("expected-min", mem.allocate_number(1)), ("actual", mem.allocate_number(0))];
+                        return Err(LispError::new("wrong-number-of-arguments", "match").details(&error_details).build(mem));
+                    }
+                    let value = eval_internal(mem, list_elems[1].clone(), env.clone(), env_module.clone(), recursion_depth + 1)?;
+
+                    let mut matched = None;
+                    for clause in list_elems[2..].iter() {
+                        let clause_elems = list_to_vec(clause.clone()).unwrap_or_default();
+                        let [pattern, body] = clause_elems.as_slice() else {
+                            let error_details = vec![("clause", clause.clone())];
+                            return Err(LispError::new("invalid-match-clause", "match").details(&error_details).build(mem));
+                        };
+                        if let Some(new_env) = match_pattern(mem, pattern, &value, env.clone(), &env_module, recursion_depth + 1)? {
+                            matched = Some((new_env, body.clone()));
+                            break;
+                        }
+                    }
+
+                    let Some((new_env, body)) = matched else {
+                        let mut error = LispError::new("no-match", "match").detail("value", value);
+                        if let Some(md) = list_elems[0].get_meta() {
+                            error = error.location(md.location.clone());
+                        }
+                        return Err(error.build(mem));
+                    };
+
+                    // tail-call elimination: jump back to the beginning of this instance of `eval`
+                    // instead of calling itself recursively
+                    env        = new_env;
+                    expression = body;
+                    continue;
+                }
+                else if symbol_eq!(list_elems[0], mem.symbol_for("let")) && list_elems.len() == 4 && list_elems[1].get_type() == TypeLabel::Symbol {
+                    // named let: `(let name (param1 init1 param2 init2 ...) body)`
+                    validate_args!(mem, "let", &list_elems[1..], (let name: TypeLabel::Symbol), (let bindings: TypeLabel::List), (let body: TypeLabel::Any));
+                    let mut params = vec![];
+                    let mut args   = vec![];
+                    for (param, init) in parse_bindings(mem, &bindings, "let", true)? {
+                        args.push(eval_internal(mem, init, env.clone(), env_module.clone(), recursion_depth + 1)?);
+                        params.push(param);
+                    }
+
+                    // the function has to be able to call itself, so it is bound in its own environment
+                    let name_function   = mem.allocate_cons(list_elems[1].clone(), GcRef::nil());
+                    let function_env    = mem.allocate_cons(name_function.clone(), env);
+                    let has_rest_params = false;
+                    let function        = mem.allocate_normal_function(FunctionKind::Lambda, has_rest_params, body, &params, function_env, &env_module);
+                    mem.set_cdr(&name_function, function.clone());
+
+                    let Some(PrimitiveValue::Function(Function::NormalFunction(nf))) = function.get() else { unreachable!() };
+                    // tail-call elimination: jump back to the beginning of this instance of `eval`
+                    // instead of calling itself recursively
+                    env        = pair_params_and_args(mem, nf, Some(name.get_name()), &args, recursion_depth)?;
+                    expression = nf.get_body();
+                    continue;
+                }
+                else if let Some((kind, source)) = [(LetKind::Parallel, "let"), (LetKind::Sequential, "let*"), (LetKind::Recursive, "letrec")].into_iter().find(|(_, source)| symbol_eq!(list_elems[0], mem.symbol_for(source))) {
+                    validate_args!(mem, source, &list_elems[1..], (let bindings: TypeLabel::List), (let body: TypeLabel::Any));
+                    // tail-call elimination: jump back to the beginning of this instance of `eval`
+                    // instead of calling itself recursively
+                    env        = bind_let(mem, kind, &bindings, env, &env_module, source, recursion_depth)?;
+                    expression = body;
+                    continue;
+                }
                 else {
                     // first element of `expression` is not a special operator
                     
@@ -282,13 +560,22 @@ fn eval_internal(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut en
                                     continue;
                                 }
                                 else {
-                                    return nf.call(mem, &list_elems[1..], env.clone(), recursion_depth + 1)
+                                    let result = nf.call(mem, &list_elems[1..], env.clone(), recursion_depth + 1);
+                                    // a native function doesn't replace its caller in the backtrace;
+                                    // re-emitting a signal doesn't add a call to its backtrace
+                                    if let Err(signal) = &result {
+                                        if !signal.is_nil() && !nf.is_the_same_as(crate::native::signal::signal) {
+                                            mem.record_backtrace_frame(signal, call_frame(&operator, &list_elems[0]));
+                                        }
+                                    }
+                                    return result;
                                 }
                             },
                             Function::NormalFunction(nf) => {
                                 // tail-call elimination: jump back to the beginning of this instance of `eval`
                                 // instead of calling itself recursively
-                                let new_env = pair_params_and_args(mem, &nf, name, &list_elems[1..])?;
+                                *frame      = Some(call_frame(&operator, &list_elems[0]));
+                                let new_env = pair_params_and_args(mem, &nf, name, &list_elems[1..], recursion_depth)?;
                                 expression = nf.get_body();
                                 env = new_env;
                                 env_module = nf.get_env_module();
@@ -299,8 +586,8 @@ fn eval_internal(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut en
                     else {
                         // first element of `expression` doesn't evaluate to a function
 
-                        let error_details = vec![("symbol", list_elems[0].clone())];
-                        let error = make_error(mem, "eval-bad-operator", EVAL.name, &error_details);
+                        let error_details = vec![("operator", list_elems[0].clone())];
+                        let error = LispError::new("eval-bad-operator", EVAL.name).details(&error_details).build(mem);
                         return Err(error); 
                     }
                 }
@@ -327,27 +614,41 @@ fn eval_internal(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut en
                             }
                             else {
                                 let key       = mem.symbol_for("*trapped-signal*");
-                                let param_arg = mem.allocate_cons(key, signal);
+                                let param_arg = mem.allocate_cons(key, signal.clone());
                                 let new_env   = mem.allocate_cons(param_arg, env);
-                                return eval_internal(mem, trap.get_trap_body(), new_env, env_module, recursion_depth + 1);
+                                let result    = eval_internal(mem, trap.get_trap_body(), new_env, env_module, recursion_depth + 1);
+                                // unless it is emitted again by the trap body, the signal has been handled
+                                if result.is_ok() {
+                                    mem.forget_backtrace(&signal);
+                                }
+                                return result;
                             }
                         },
                         Ok(x) => return Ok(x),
                     }
                 },
                 Some(PrimitiveValue::Symbol(_)) => {
-                    match lookup(mem, expression.clone(), env, &env_module) {
+                    match lookup(mem, expression.clone(), env.clone(), &env_module) {
                         Ok(value) => return Ok(value),
                         Err(ModulError::AmbiguousName(modules)) => {
                             let conflicting_modules = modules.iter().map(|m| mem.symbol_for(m)).collect::<Vec<GcRef>>();
                             let error_details = vec![("symbol", expression), ("conflicting-modules", vec_to_list(mem, &conflicting_modules))];
-                            let error = make_error(mem, "ambiguous-name", EVAL.name, &error_details);
+                            let error = LispError::new("ambiguous-name", EVAL.name).details(&error_details).build(mem);
                             return Err(error);
                         },
                         Err(ModulError::GlobalNonExistentOrPrivate) => {
                             let error_details = vec![("symbol", expression.clone())];
-                            let error = make_error(mem, "unbound-symbol", EVAL.name, &error_details);
-                            return Err(error);
+                            let error = LispError::new("unbound-symbol", EVAL.name).details(&error_details).build(mem);
+                            let (restart, value) = offer_restarts(mem, error, &["use-value", "define-and-retry"], recursion_depth)?;
+                            if restart == "use-value" {
+                                return Ok(value);
+                            }
+                            // define the symbol where it is looked up, then evaluate it again
+                            let old_module = mem.get_current_module();
+                            let _          = mem.set_current_module(&env_module);
+                            mem.define_global(&expression.get().unwrap().as_symbol().get_name(), value);
+                            let _          = mem.set_current_module(&old_module);
+                            continue;
                         },
                         _ => unreachable!(),
                     }
@@ -361,12 +662,60 @@ fn eval_internal(mem: &mut Memory, mut expression: GcRef, mut env: GcRef, mut en
 }
 
 
-fn macroexpand_internal(mem: &mut Memory, expression: GcRef, env: GcRef, env_module: &str, recursion_depth: usize, changed: &mut bool) -> Result<GcRef, GcRef> {
+/// State of one pass of `macroexpand_internal` over an expression.
+#[derive(Default)]
+struct ExpansionState {
+    /// whether at least one macro has been expanded during the pass
+    changed: bool,
+    /// if present, each macro call is recorded here, in the order they are expanded (see `macroexpand-trace`)
+    trace: Option<Vec<GcRef>>,
+}
+
+
+/// Call the macro `macro_function` with `args`. `form` is the macro call itself (used for tracing).
+fn call_macro(mem: &mut Memory, macro_function: &Function, name: Option<String>, form: &[GcRef], env: GcRef, recursion_depth: usize, state: &mut ExpansionState) -> Result<GcRef, GcRef> {
+    state.changed = true;
+    let expansion =
+    match macro_function {
+        Function::NativeFunction(nf) => nf.call(mem, &form[1..], env, recursion_depth + 1)?,
+        Function::NormalFunction(nf) => {
+            let new_env = pair_params_and_args(mem, nf, name, &form[1..], recursion_depth)?;
+            eval_internal(mem, nf.get_body(), new_env, nf.get_env_module(), recursion_depth + 1)?
+        },
+    };
+    record_expansion(mem, state, form[0].clone(), form, expansion)
+}
+
+
+fn record_expansion(mem: &mut Memory, state: &mut ExpansionState, macro_name: GcRef, form: &[GcRef], expansion: GcRef) -> Result<GcRef, GcRef> {
+    if state.trace.is_some() {
+        let form  = vec_to_list(mem, form);
+        let step  = make_plist(mem, &[("macro", macro_name), ("form", form), ("expansion", expansion.clone())]);
+        if let Some(trace) = state.trace.as_mut() {
+            trace.push(step);
+        }
+    }
+    Ok(expansion)
+}
+
+
+/// `elems` as a list, with the metadata of `original` (the list whose elements were expanded into `elems`),
+/// so that the expanded code keeps the location of the source.
+fn rebuild_list(mem: &mut Memory, original: &GcRef, elems: &[GcRef]) -> GcRef {
+    let list = vec_to_list(mem, elems);
+    match original.get_meta() {
+        Some(md) if !list.is_nil() => mem.allocate_metadata(list, md.clone()),
+        _                          => list,
+    }
+}
+
+
+fn macroexpand_internal(mem: &mut Memory, expression: GcRef, env: GcRef, env_module: &str, recursion_depth: usize, state: &mut ExpansionState) -> Result<GcRef, GcRef> {
     if recursion_depth > config::MAX_RECURSION_DEPTH {
-        return Err(make_error(mem, "stackoverflow", MACROEXPAND.name, &vec![]));
+        return Err(LispError::new("stackoverflow", MACROEXPAND.name).build(mem));
     }
     
-    let name = expression.get_meta().map(|md| md.read_name.clone());
+    let name = operator_name(&expression);
 
     if let Some(mut list_elems) = list_to_vec(expression.clone()) {
         // `expression` is a list
@@ -375,39 +724,92 @@ fn macroexpand_internal(mem: &mut Memory, expression: GcRef, env: GcRef, env_mod
             // `expression` is a non-empty list
             
             if symbol_eq!(list_elems[0], mem.symbol_for("macro")) {
+                // the body of the macro can use quasiquote (or other macros), so it is expanded before the macro is created
+                if let Some(params) = list_elems.get_mut(1) {
+                    *params = map_parameter_defaults(mem, params, |mem, default| macroexpand_completely(mem, default, env.clone(), env_module, recursion_depth + 1))?;
+                }
+                if let Some(body) = list_elems.get_mut(2) {
+                    *body = macroexpand_completely(mem, body.clone(), env.clone(), env_module, recursion_depth + 1)?;
+                }
                 return make_function_internal(mem, &list_elems[1..], env.clone(), env_module, "macro", FunctionKind::Macro);
             }
             else if symbol_eq!(list_elems[0], mem.symbol_for("quote")) {
                 return Ok(expression);
             }
+            else if symbol_eq!(list_elems[0], mem.symbol_for("quasiquote")) {
+                state.changed = true;
+                let expansion = expand_quasiquote(mem, expression)?;
+                record_expansion(mem, state, first, &list_elems, expansion)
+            }
+            else if symbol_eq!(list_elems[0], mem.symbol_for("unquote")) || symbol_eq!(list_elems[0], mem.symbol_for("unquote-splicing")) {
+                Err(LispError::new("unquote-outside-quasiquote", MACROEXPAND.name).detail("form", expression).build(mem))
+            }
+            else if symbol_eq!(list_elems[0], mem.symbol_for("lambda")) {
+                // the parameter list is not code, so it is left as-is, except for the default values of the parameters
+                if let Some(params) = list_elems.get_mut(1) {
+                    *params = map_parameter_defaults(mem, params, |mem, default| macroexpand_internal(mem, default, env.clone(), env_module, recursion_depth + 1, state))?;
+                }
+                for elem in list_elems.iter_mut().skip(2) {
+                    *elem = macroexpand_internal(mem, elem.clone(), env.clone(), env_module, recursion_depth + 1, state)?;
+                }
+                Ok(rebuild_list(mem, &expression, &list_elems))
+            }
+            else if symbol_eq!(list_elems[0], mem.symbol_for("match")) {
+                // only expand the matched expression, the bodies of the clauses and the predicates in the patterns
+                for (i, elem) in list_elems.iter_mut().enumerate().skip(1) {
+                    if i == 1 {
+                        *elem = macroexpand_internal(mem, elem.clone(), env.clone(), env_module, recursion_depth + 1, state)?;
+                    }
+                    else if let Some(mut clause) = list_to_vec(elem.clone()) {
+                        if let Some(pattern) = clause.get_mut(0) {
+                            *pattern = map_pattern_predicates(mem, pattern, &mut |mem, predicate| macroexpand_internal(mem, predicate, env.clone(), env_module, recursion_depth + 1, state))?;
+                        }
+                        if let Some(body) = clause.get_mut(1) {
+                            *body = macroexpand_internal(mem, body.clone(), env.clone(), env_module, recursion_depth + 1, state)?;
+                        }
+                        *elem = rebuild_list(mem, elem, &clause);
+                    }
+                }
+                Ok(rebuild_list(mem, &expression, &list_elems))
+            }
+            else if ["let", "let*", "letrec", "parameterize"].iter().any(|name| symbol_eq!(list_elems[0], mem.symbol_for(name))) {
+                // only expand the values in the binding list, not the names
+                let is_named_let = list_elems.len() == 4 && list_elems[1].get_type() == TypeLabel::Symbol;
+                let bindings_index = if is_named_let {2} else {1};
+                for (i, elem) in list_elems.iter_mut().enumerate().skip(bindings_index) {
+                    if i == bindings_index {
+                        if let Some(mut bindings) = list_to_vec(elem.clone()) {
+                            for value in bindings.iter_mut().skip(1).step_by(2) {
+                                *value = macroexpand_internal(mem, value.clone(), env.clone(), env_module, recursion_depth + 1, state)?;
+                            }
+                            *elem = rebuild_list(mem, elem, &bindings);
+                        }
+                    }
+                    else {
+                        *elem = macroexpand_internal(mem, elem.clone(), env.clone(), env_module, recursion_depth + 1, state)?;
+                    }
+                }
+                Ok(rebuild_list(mem, &expression, &list_elems))
+            }
             else {
                 // first element of `expression` is not a special operator
 
-                let operator = macroexpand_internal(mem, first, env.clone(), env_module, recursion_depth + 1, changed)?;
+                let operator = macroexpand_internal(mem, first, env.clone(), env_module, recursion_depth + 1, state)?;
 
                 // expand all elements regardless what the operator is
                 for i in 1..list_elems.len() {
-                    list_elems[i] = macroexpand_internal(mem, list_elems[i].clone(), env.clone(), env_module, recursion_depth + 1, changed)?;
+                    list_elems[i] = macroexpand_internal(mem, list_elems[i].clone(), env.clone(), env_module, recursion_depth + 1, state)?;
                 }
 
                 // if the operator is a macro then evaluate it... 
                 if let Some(PrimitiveValue::Function(f)) = operator.get() {
                     if f.get_kind() == FunctionKind::Macro {
-                        *changed = true;
-                        match f {
-                            Function::NativeFunction(nf) => {
-                                return nf.call(mem, &list_elems[1..], env.clone(), recursion_depth + 1);
-                            },
-                            Function::NormalFunction(nf) => {
-                                let new_env = pair_params_and_args(mem, &nf, name, &list_elems[1..])?;
-                                return eval_internal(mem, nf.get_body(), new_env, nf.get_env_module(), recursion_depth + 1);
-                            },
-                        }
+                        return call_macro(mem, f, name, &list_elems, env.clone(), recursion_depth, state);
                     }
                 }
 
                 // ...otherwise return the whole list as-is
-                Ok(vec_to_list(mem, &list_elems))
+                Ok(rebuild_list(mem, &expression, &list_elems))
             }
         }
         else {
@@ -420,8 +822,8 @@ fn macroexpand_internal(mem: &mut Memory, expression: GcRef, env: GcRef, env_mod
         
         match expression.get() {
             Some(PrimitiveValue::Cons(cons)) => {
-                let car = macroexpand_internal(mem, cons.get_car(), env.clone(), env_module, recursion_depth + 1, changed)?;
-                let cdr = macroexpand_internal(mem, cons.get_cdr(), env.clone(), env_module, recursion_depth + 1, changed)?;
+                let car = macroexpand_internal(mem, cons.get_car(), env.clone(), env_module, recursion_depth + 1, state)?;
+                let cdr = macroexpand_internal(mem, cons.get_cdr(), env.clone(), env_module, recursion_depth + 1, state)?;
                 Ok(mem.allocate_cons(car, cdr))
             },
             Some(PrimitiveValue::Symbol(_)) => {
@@ -429,7 +831,7 @@ fn macroexpand_internal(mem: &mut Memory, expression: GcRef, env: GcRef, env_mod
                     Ok(value) => {
                         if let Some(PrimitiveValue::Function(f)) = value.get() {
                             if f.get_kind() == FunctionKind::Macro {
-                                *changed = true;
+                                state.changed = true;
                                 return Ok(value);
                             }
                         }
@@ -437,7 +839,7 @@ fn macroexpand_internal(mem: &mut Memory, expression: GcRef, env: GcRef, env_mod
                     Err(ModulError::AmbiguousName(modules)) => {
                         let conflicting_modules = modules.iter().map(|m| mem.symbol_for(m)).collect::<Vec<GcRef>>();
                         let error_details = vec![("symbol", expression), ("conflicting-modules", vec_to_list(mem, &conflicting_modules))];
-                        let error = make_error(mem, "ambiguous-name", MACROEXPAND.name, &error_details);
+                        let error = LispError::new("ambiguous-name", MACROEXPAND.name).details(&error_details).build(mem);
                         return Err(error);
                     },
                     Err(ModulError::GlobalNonExistentOrPrivate) => {
@@ -455,12 +857,59 @@ fn macroexpand_internal(mem: &mut Memory, expression: GcRef, env: GcRef, env_mod
 }
 
 
+/// Expand all macros in `expression`.
+/// The result is memoized (see `Memory::get_cached_expansion`), so evaluating the same code again doesn't expand it again.
 fn macroexpand_completely(mem: &mut Memory, expression: GcRef, env: GcRef, env_module: &str, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let cacheable = expression.get_type() == TypeLabel::Cons && !shadows_macro(mem, &expression, &env);
+    if cacheable {
+        if let Some(expanded) = mem.get_cached_expansion(&expression, env_module) {
+            return Ok(expanded);
+        }
+    }
+
+    let expanded = macroexpand_with_state(mem, expression.clone(), env.clone(), env_module, recursion_depth, &mut ExpansionState::default())?;
+    if cacheable {
+        mem.cache_expansion(&expression, env_module, &expanded);
+    }
+    Ok(expanded)
+}
+
+
+/// Whether a lexical or dynamic binding shadows a macro whose name occurs in `expression`.
+/// The expansion of `expression` then depends on the bindings, so it can't be reused in other environments.
+fn shadows_macro(mem: &Memory, expression: &GcRef, env: &GcRef) -> bool {
+    fn occurs(name: &GcRef, expression: &GcRef) -> bool {
+        match expression.get() {
+            Some(PrimitiveValue::Cons(cons)) => occurs(name, &cons.get_car()) || occurs(name, &cons.get_cdr()),
+            Some(PrimitiveValue::Symbol(_))  => symbol_eq!(name, expression),
+            _                                => false,
+        }
+    }
+
+    for bindings in [env.clone(), mem.get_dynamic_environment()] {
+        let mut cursor = bindings;
+        while let Some(PrimitiveValue::Cons(cons)) = cursor.get() {
+            if let Some(PrimitiveValue::Cons(binding)) = cons.get_car().get() {
+                let name = binding.get_car();
+                if let Some(PrimitiveValue::Symbol(symbol)) = name.get() {
+                    if mem.is_macro_name(&symbol.get_name()) && occurs(&name, expression) {
+                        return true;
+                    }
+                }
+            }
+            cursor = cons.get_cdr();
+        }
+    }
+    false
+}
+
+
+fn macroexpand_with_state(mem: &mut Memory, expression: GcRef, env: GcRef, env_module: &str, recursion_depth: usize, state: &mut ExpansionState) -> Result<GcRef, GcRef> {
     let mut expanded = expression.clone();
     loop {
-        let mut changed  = false;
-        expanded = macroexpand_internal(mem, expanded, env.clone(), env_module, recursion_depth + 1, &mut changed)?;
-        if !changed {
+        state.changed = false;
+        expanded = macroexpand_internal(mem, expanded, env.clone(), env_module, recursion_depth + 1, state)?;
+        if !state.changed {
             break;
         }
     }
@@ -469,6 +918,30 @@ fn macroexpand_completely(mem: &mut Memory, expression: GcRef, env: GcRef, env_m
 }
 
 
+/// If `expression` is a macro call, expand that call (but neither its arguments nor the expansion itself),
+/// otherwise return `expression` unchanged.
+fn macroexpand_once(mem: &mut Memory, expression: GcRef, env: GcRef, env_module: &str, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let Some(list_elems) = list_to_vec(expression.clone()).filter(|elems| !elems.is_empty()) else {
+        return Ok(expression);
+    };
+    let mut state = ExpansionState::default();
+
+    if symbol_eq!(list_elems[0], mem.symbol_for("quasiquote")) {
+        return expand_quasiquote(mem, expression);
+    }
+
+    let name     = list_elems[0].get_meta().map(|md| md.read_name.clone()).filter(|name| !name.is_empty());
+    let operator = macroexpand_internal(mem, list_elems[0].clone(), env.clone(), env_module, recursion_depth + 1, &mut state)?;
+    if let Some(PrimitiveValue::Function(f)) = operator.get() {
+        if f.get_kind() == FunctionKind::Macro {
+            return call_macro(mem, f, name, &list_elems, env, recursion_depth, &mut state);
+        }
+    }
+
+    Ok(expression)
+}
+
+
 pub const EVAL: NativeFunctionMetaData =
 NativeFunctionMetaData{
     function:      eval,
@@ -505,6 +978,48 @@ pub fn macroexpand(mem: &mut Memory, args: &[GcRef], env: GcRef, recursion_depth
 }
 
 
+pub const MACROEXPAND_1: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      macroexpand_1,
+    name:          "macroexpand-1",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["object"],
+    documentation: "If `object` is a macro call, expand only that macro call and return the result.
+Otherwise return `object` unchanged.
+Unlike in `macroexpand`, the arguments of the macro are not expanded before the macro is called."
+};
+
+pub fn macroexpand_1(mem: &mut Memory, args: &[GcRef], env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, MACROEXPAND_1.name, args, (let x: TypeLabel::Any));
+
+    let env_module = mem.get_current_module();
+    macroexpand_once(mem, x, env, &env_module, recursion_depth + 1)
+}
+
+
+pub const MACROEXPAND_TRACE: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      macroexpand_trace,
+    name:          "macroexpand-trace",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["object"],
+    documentation: "Expand macros in `object` (like `macroexpand`), and return a list of the macro calls
+in the order they were expanded. Each element is a property list with the following keys:
+ * `macro`:     the operator of the macro call
+ * `form`:      the macro call (with its arguments already expanded)
+ * `expansion`: the form the macro call expanded into"
+};
+
+pub fn macroexpand_trace(mem: &mut Memory, args: &[GcRef], env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, MACROEXPAND_TRACE.name, args, (let x: TypeLabel::Any));
+
+    let env_module = mem.get_current_module();
+    let mut state  = ExpansionState{ changed: false, trace: Some(vec![]) };
+    macroexpand_with_state(mem, x, env, &env_module, recursion_depth + 1, &mut state)?;
+    Ok(vec_to_list(mem, &state.trace.unwrap_or_default()))
+}
+
+
 pub fn eval_external(mem: &mut Memory, tree: GcRef) -> Result<GcRef, String> {
     let empty_env = GcRef::nil();
     let recursion_depth = 0;
@@ -517,6 +1032,7 @@ pub fn eval_external(mem: &mut Memory, tree: GcRef) -> Result<GcRef, String> {
                 Err(format!("Evaluation aborted."))
             }
             else {
+                let signal = crate::native::signal::with_backtrace(mem, signal);
                 Err(list_to_string(crate::native::print::print(mem, &[signal], empty_env, recursion_depth).ok().unwrap()).unwrap())
             }
         },
@@ -536,35 +1052,49 @@ NativeFunctionMetaData{
     function:      load_all,
     name:          "load-all",
     kind:          FunctionKind::Lambda,
-    parameters:    &["string", "source"],
-    documentation: "Read, macroexpand and evaluate all expressions in `string` in sequential order.
+    parameters:    &["string", "module", "&optional", "source"],
+    documentation: "Read, macroexpand and evaluate all expressions in `string` in sequential order, in the module named `module`.
+`source` is where `string` comes from, like for `read` (by default `module`).
 Error if `string` is not a valid string."
 };
 
 pub fn load_all(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
-    validate_args!(mem, LOAD_ALL.name, args, (let _input: TypeLabel::String), (let source: TypeLabel::Any));
+    let args = fill_optional_args(mem, LOAD_ALL.name, args, 2, &[GcRef::nil()])?;
+    let args = if args[2].is_nil() {vec![args[0].clone(), args[1].clone(), args[1].clone()]} else {args};
+    validate_args!(mem, LOAD_ALL.name, &args, (let _input: TypeLabel::String), (let module: TypeLabel::Any), (let source: TypeLabel::Any));
+
+    let old_module = mem.get_current_module();
+    if let Some(s) = list_to_string(module.clone()) {
+        mem.define_module(&s);
+    }
 
+    // the current module is restored even if a signal (or an abort) interrupts the loading
+    let result = load_expressions(mem, args[0].clone(), source, recursion_depth);
+    mem.set_current_module(&old_module).unwrap();
+
+    result
+}
+
+
+fn load_expressions(mem: &mut Memory, input: GcRef, source: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
     let ok_symbol         = mem.symbol_for("ok");
     let incomplete_symbol = mem.symbol_for("incomplete");
     let error_symbol      = mem.symbol_for("error");
     let invalid_symbol    = mem.symbol_for("invalid");
     let mut line          = mem.allocate_number(1);
     let mut column        = mem.allocate_number(1);
-    let mut cursor        = args[0].clone();
-
-    let old_module = mem.get_current_module();
-    if let Some(s) = list_to_string(source.clone()) {
-        mem.define_module(&s);
-    }
+    let mut offset        = mem.allocate_number(0);
+    let mut cursor        = input;
 
     while !cursor.is_nil() {
-        let output     = read(mem, &[cursor.clone(), source.clone(), line.clone(), column.clone()], GcRef::nil(), recursion_depth + 1)?;
+        let output     = read(mem, &[cursor.clone(), source.clone(), line.clone(), column.clone(), offset.clone()], GcRef::nil(), recursion_depth + 1)?;
         let status     = property(mem, "status", output.clone()).unwrap();
         let result     = property(mem, "result", output.clone()).unwrap();
         let rest       = property(mem, "rest",   output.clone()).unwrap();
         let read_error = property(mem, "error",   output.clone()).unwrap();
         line           = property(mem, "line",   output.clone()).unwrap();
-        column         = property(mem, "column", output).unwrap();
+        column         = property(mem, "column", output.clone()).unwrap();
+        offset         = property(mem, "offset", output).unwrap();
 
         if symbol_eq!(status, ok_symbol) {
             let nr = eval(mem, &[result], GcRef::nil(), recursion_depth + 1);
@@ -573,24 +1103,26 @@ pub fn load_all(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth:
             }
         }
         else if symbol_eq!(status, incomplete_symbol) {
-            let error = make_error(mem, "input-incomplete", LOAD_ALL.name, &vec![]);
+            let error = LispError::new("input-incomplete", LOAD_ALL.name).build(mem);
             return Err(error);
         }
         else if symbol_eq!(status, error_symbol) {
-            let error_details = vec![("details", read_error)];
-            let error = make_error(mem, "read-error", LOAD_ALL.name, &error_details);
-            return Err(error);
+            let message  = property(mem, "message", read_error.clone()).and_then(list_to_string).unwrap_or_default();
+            let location = property(mem, "location", read_error.clone()).unwrap_or_else(GcRef::nil);
+            let mut error = LispError::new("read-error", LOAD_ALL.name).message(message).location_plist(location);
+            if let Some(hint) = property(mem, "hint", read_error).filter(|hint| !hint.is_nil()) {
+                error = error.detail("hint", hint);
+            }
+            return Err(error.build(mem));
         }
         else if symbol_eq!(status, invalid_symbol) {
-            let error = make_error(mem, "input-invalid-string", LOAD_ALL.name, &vec![]);
+            let error = LispError::new("input-invalid-string", LOAD_ALL.name).build(mem);
             return Err(error);
         }
 
         cursor = rest;
     }
 
-    mem.set_current_module(&old_module).unwrap();
-
     Ok(ok_symbol)
 }
 
@@ -0,0 +1,452 @@
+use crate::memory::*;
+use crate::util::*;
+use crate::native::list::property;
+use crate::native::misc::equal_internal;
+use crate::native::reflection::type_of;
+use crate::error_utils::*;
+use crate::config;
+use super::{eval_internal, macroexpand_completely};
+
+
+
+// Destructuring patterns, as accepted by `lambda` parameter lists (see `parse_lambda_list`) and `let` bindings:
+//
+//   symbol               binds the whole value
+//   ()                   matches the empty list
+//   (p1 p2 ...)          matches a list with exactly as many elements, each matched against the corresponding pattern
+//   (p1 p2 ... & rest)   same, but the list can be longer; `rest` is matched against the remaining elements
+//   (&key k1 k2 ...)     matches a property list; each `k` is bound to the value associated with it (or nil)
+//   (number-type p)      matches `p`, but first checks that the value has the given type (see `split_type_annotation`)
+
+
+/// Split the elements of a list pattern (or a parameter list) into the fixed patterns and the rest pattern (if any).
+fn split_rest(mem: &mut Memory, elems: &[GcRef], source: &str) -> Result<(Vec<GcRef>, Option<GcRef>), GcRef> {
+    let rest_param_symbol = mem.symbol_for("&");
+    let count             = elems.len();
+
+    for (i, elem) in elems.iter().enumerate() {
+        if symbol_eq!(elem, rest_param_symbol) {
+            // i == count - 2  (rearranged to avoid underflow when count == 0)
+            if i + 2 == count {
+                //          ---4---
+                //          0 1 2 3
+                // (lambda (x y & z) ...
+                //              ^
+                //              4 - 2
+                return Ok((elems[.. i].to_vec(), Some(elems[i + 1].clone())));
+            }
+            //      i > count - 2
+            else if i + 2 > count {
+                //          ---4---
+                //          0 1 2 3
+                // (lambda (x y z &) ...
+                //                ^
+                //                3 > 4 - 2
+                return Err(LispError::new("missing-rest-parameter", source).build(mem));
+            }
+            // i < count - 2
+            else {
+                //          ---4---
+                //          0 1 2 3
+                // (lambda (x & y z) ...
+                //            ^
+                //            1 < 4 - 2
+                return Err(LispError::new("multiple-rest-parameters", source).build(mem));
+            }
+        }
+    }
+
+    Ok((elems.to_vec(), None))
+}
+
+
+/// Split a type annotation `(type pattern)`, where `type` is one of the [TYPE_GUARDS], into the pattern and the type.
+/// Anything else is returned as-is, without a type.
+pub fn split_type_annotation(mem: &mut Memory, pattern: &GcRef) -> (GcRef, Option<GcRef>) {
+    if let Some([guard, inner]) = list_to_vec(pattern.clone()).as_deref() {
+        if TYPE_GUARDS.iter().any(|g| symbol_eq!(guard, mem.symbol_for(g))) {
+            return (inner.clone(), Some(guard.clone()));
+        }
+    }
+    (pattern.clone(), None)
+}
+
+
+/// Check that `value` has the type `expected` (a symbol from the [TYPE_GUARDS]), as returned by `type-of`.
+/// Nil is also accepted as a `list-type`.
+fn check_type(mem: &mut Memory, pattern: &GcRef, expected: &GcRef, value: &GcRef, source: &str) -> Result<(), GcRef> {
+    let actual = type_of(mem, std::slice::from_ref(value), GcRef::nil(), 0)?;
+    if symbol_eq!(expected, actual) || (value.is_nil() && symbol_eq!(expected, mem.symbol_for("list-type"))) {
+        return Ok(());
+    }
+
+    let error_details = vec![("argument-value", value.clone()), ("parameter", pattern.clone()), ("expected", expected.clone()), ("actual", actual)];
+    Err(LispError::new("wrong-argument-type", source).details(&error_details).build(mem))
+}
+
+
+fn is_key_pattern(mem: &mut Memory, elems: &[GcRef]) -> bool {
+    elems.first().is_some_and(|first| symbol_eq!(first, mem.symbol_for("&key")))
+}
+
+
+/// Check that `pattern` is a valid destructuring pattern.
+pub fn validate_pattern(mem: &mut Memory, pattern: &GcRef, source: &str) -> Result<(), GcRef> {
+    let (pattern, _) = split_type_annotation(mem, pattern);
+    let pattern      = &pattern;
+
+    if pattern.get_type() == TypeLabel::Symbol {
+        if symbol_eq!(pattern, mem.symbol_for("&key")) {
+            let error_details = vec![("pattern", pattern.clone())];
+            return Err(LispError::new("invalid-pattern", source).details(&error_details).build(mem));
+        }
+
+        return Ok(());
+    }
+
+    let Some(elems) = list_to_vec(pattern.clone()) else {
+        let error_details = vec![("parameter", pattern.clone())];
+        return Err(LispError::new("param-is-not-symbol", source).details(&error_details).build(mem));
+    };
+
+    if is_key_pattern(mem, &elems) {
+        for key in elems[1 ..].iter() {
+            if key.get_type() != TypeLabel::Symbol || symbol_eq!(key, mem.symbol_for("&")) || symbol_eq!(key, mem.symbol_for("&key")) {
+                let error_details = vec![("pattern", pattern.clone())];
+                return Err(LispError::new("invalid-pattern", source).details(&error_details).build(mem));
+            }
+        }
+    }
+    else {
+        let (patterns, rest) = split_rest(mem, &elems, source)?;
+        for p in patterns.iter().chain(rest.iter()) {
+            validate_pattern(mem, p, source)?;
+        }
+    }
+
+    Ok(())
+}
+
+
+#[derive(PartialEq, Eq)]
+enum Section {
+    Required,
+    Optional,
+    Key,
+}
+
+
+/// Parse the parameter list of a function: `required* [&optional spec*] [& rest | &key spec*]`,
+/// where `spec` is either `name` or `(name default)`.
+/// Required, optional and rest parameters can be destructuring patterns, key parameters have to be symbols.
+/// Any parameter can have a type annotation, e.g. `(number-type x)` or `((string-type s) "")` (see `split_type_annotation`).
+pub fn parse_lambda_list(mem: &mut Memory, params: &[GcRef], source: &str) -> Result<LambdaList, GcRef> {
+    let mut lambda_list = LambdaList{ required: vec![], optional: vec![], rest: None, key: vec![] };
+    let mut section     = Section::Required;
+
+    for (i, param) in params.iter().enumerate() {
+        if symbol_eq!(param, mem.symbol_for("&optional")) || symbol_eq!(param, mem.symbol_for("&key")) {
+            let next_section = if symbol_eq!(param, mem.symbol_for("&optional")) {Section::Optional} else {Section::Key};
+            // the sections have to come in order, and each of them can only appear once
+            if section == Section::Key || section == next_section {
+                let error_details = vec![("parameter", param.clone())];
+                return Err(LispError::new("misplaced-lambda-list-keyword", source).details(&error_details).build(mem));
+            }
+            section = next_section;
+        }
+        else if symbol_eq!(param, mem.symbol_for("&")) {
+            if section == Section::Key {
+                let error_details = vec![("parameter", param.clone())];
+                return Err(LispError::new("misplaced-lambda-list-keyword", source).details(&error_details).build(mem));
+            }
+
+            // the rest parameter has to be the last one (see `split_rest`)
+            let (_, rest) = split_rest(mem, &params[i ..], source)?;
+            let rest      = rest.unwrap();
+            validate_pattern(mem, &rest, source)?;
+            lambda_list.rest = Some(rest);
+            break;
+        }
+        else {
+            match section {
+                Section::Required => {
+                    validate_pattern(mem, param, source)?;
+                    lambda_list.required.push(param.clone());
+                },
+                Section::Optional => {
+                    let (pattern, default) = parse_parameter_spec(mem, param, source)?;
+                    validate_pattern(mem, &pattern, source)?;
+                    lambda_list.optional.push((pattern, default));
+                },
+                Section::Key => {
+                    let (key, default) = parse_parameter_spec(mem, param, source)?;
+                    if split_type_annotation(mem, &key).0.get_type() != TypeLabel::Symbol {
+                        let error_details = vec![("parameter", param.clone())];
+                        return Err(LispError::new("invalid-parameter-spec", source).details(&error_details).build(mem));
+                    }
+                    lambda_list.key.push((key, default));
+                },
+            }
+        }
+    }
+
+    Ok(lambda_list)
+}
+
+
+/// Apply `f` to the default value expressions of the optional and key parameters in `params`, leaving the rest as-is.
+pub fn map_parameter_defaults<F>(mem: &mut Memory, params: &GcRef, mut f: F) -> Result<GcRef, GcRef>
+where F: FnMut(&mut Memory, GcRef) -> Result<GcRef, GcRef> {
+    let Some(mut elems) = list_to_vec(params.clone()) else {
+        return Ok(params.clone());
+    };
+
+    let mut has_defaults = false;
+    for elem in elems.iter_mut() {
+        if symbol_eq!(elem, mem.symbol_for("&optional")) || symbol_eq!(elem, mem.symbol_for("&key")) {
+            has_defaults = true;
+        }
+        else if symbol_eq!(elem, mem.symbol_for("&")) {
+            break;
+        }
+        else if has_defaults && split_type_annotation(mem, elem).1.is_none() {
+            if let Some([name, default]) = list_to_vec(elem.clone()).as_deref() {
+                let default = f(mem, default.clone())?;
+                *elem = vec_to_list(mem, &[name.clone(), default]);
+            }
+        }
+    }
+
+    Ok(vec_to_list(mem, &elems))
+}
+
+
+/// Split `name` or `(name default)` into a name-default pair.
+fn parse_parameter_spec(mem: &mut Memory, spec: &GcRef, source: &str) -> Result<(GcRef, GcRef), GcRef> {
+    if spec.get_type() == TypeLabel::Symbol || split_type_annotation(mem, spec).1.is_some() {
+        return Ok((spec.clone(), GcRef::nil()));
+    }
+
+    match list_to_vec(spec.clone()).as_deref() {
+        Some([name, default]) => Ok((name.clone(), default.clone())),
+        _ => {
+            let error_details = vec![("parameter", spec.clone())];
+            Err(LispError::new("invalid-parameter-spec", source).details(&error_details).build(mem))
+        },
+    }
+}
+
+
+fn mismatch(mem: &mut Memory, pattern: GcRef, value: GcRef, source: &str) -> GcRef {
+    let error_details = vec![("pattern", pattern), ("value", value)];
+    LispError::new("pattern-mismatch", source).details(&error_details).build(mem)
+}
+
+
+/// Match `value` against the (already validated) `pattern`, and extend `env` with the resulting bindings.
+pub fn bind_pattern(mem: &mut Memory, pattern: GcRef, value: GcRef, env: GcRef, source: &str) -> Result<GcRef, GcRef> {
+    let (pattern, expected_type) = split_type_annotation(mem, &pattern);
+    if let Some(expected_type) = expected_type {
+        check_type(mem, &pattern, &expected_type, &value, source)?;
+    }
+
+    if pattern.get_type() == TypeLabel::Symbol {
+        let name_value = mem.allocate_cons(pattern, value);
+        return Ok(mem.allocate_cons(name_value, env));
+    }
+
+    let Some(elems) = list_to_vec(pattern.clone()) else {
+        return Err(mismatch(mem, pattern, value, source));
+    };
+
+    let mut new_env = env;
+
+    if is_key_pattern(mem, &elems) {
+        for key in elems[1 ..].iter() {
+            let Some(key_value) = property(mem, &key.get().unwrap().as_symbol().get_name(), value.clone()) else {
+                return Err(mismatch(mem, pattern, value, source));
+            };
+            let name_value = mem.allocate_cons(key.clone(), key_value);
+            new_env        = mem.allocate_cons(name_value, new_env);
+        }
+
+        return Ok(new_env);
+    }
+
+    let (patterns, rest) = split_rest(mem, &elems, source)?;
+
+    let values = match list_to_vec(value.clone()) {
+        Some(values) if values.len() == patterns.len() || (rest.is_some() && values.len() > patterns.len()) => values,
+        _ => return Err(mismatch(mem, pattern, value, source)),
+    };
+
+    for (p, v) in patterns.iter().zip(values.iter()) {
+        new_env = bind_pattern(mem, p.clone(), v.clone(), new_env, source)?;
+    }
+
+    if let Some(rest) = rest {
+        let rest_values = vec_to_list(mem, &values[patterns.len() ..]);
+        new_env         = bind_pattern(mem, rest, rest_values, new_env, source)?;
+    }
+
+    Ok(new_env)
+}
+
+
+// `match` patterns are a superset of destructuring patterns, but they can also fail to match:
+//
+//   _                    matches anything
+//   symbol               binds the whole value
+//   nil, number, char    matches an equal value
+//   (quote datum)        matches a value equal to `datum`
+//   (p1 p2 ... & rest)   list patterns, as above (also written as `(list p1 p2 ... & rest)`, so string literals match strings)
+//   (cons p1 p2)         matches a cons cell whose car matches `p1` and cdr matches `p2`
+//   (number-type p)      matches if the value has the given type (as returned by `type-of`), and it also matches `p` (if given)
+//   (? predicate p)      matches if `predicate` returns non-nil for the value, and it also matches `p` (if given)
+
+pub const TYPE_GUARDS: [&str; 9] = ["nil-type", "number-type", "character-type", "symbol-type", "cons-type", "list-type", "string-type", "function-type", "trap-type"];
+
+
+fn invalid_pattern(mem: &mut Memory, pattern: &GcRef, source: &str) -> GcRef {
+    let error_details = vec![("pattern", pattern.clone())];
+    LispError::new("invalid-pattern", source).details(&error_details).build(mem)
+}
+
+
+/// Apply `f` to the (non-symbol) predicates of the `?` sub-patterns of the `match` pattern `pattern`, leaving the rest as-is.
+pub fn map_pattern_predicates<F>(mem: &mut Memory, pattern: &GcRef, f: &mut F) -> Result<GcRef, GcRef>
+where F: FnMut(&mut Memory, GcRef) -> Result<GcRef, GcRef> {
+    let Some(mut elems) = list_to_vec(pattern.clone()).filter(|elems| !elems.is_empty()) else {
+        return Ok(pattern.clone());
+    };
+
+    if symbol_eq!(elems[0], mem.symbol_for("quote")) {
+        return Ok(pattern.clone());
+    }
+    let is_predicate = symbol_eq!(elems[0], mem.symbol_for("?"));
+    for (i, elem) in elems.iter_mut().enumerate() {
+        *elem =
+        if is_predicate && i == 1 && elem.get_type() != TypeLabel::Symbol {
+            // a symbol is left alone, if it names a macro then the call is expanded when the pattern is matched
+            f(mem, elem.clone())?
+        }
+        else {
+            map_pattern_predicates(mem, elem, f)?
+        };
+    }
+
+    Ok(vec_to_list(mem, &elems))
+}
+
+
+/// Try to match `value` against the `match` pattern `pattern`.
+/// Return `env` extended with the resulting bindings, or None if `value` does not match.
+/// Predicates are evaluated in the environment extended with the bindings of the previous sub-patterns.
+pub fn match_pattern(mem: &mut Memory, pattern: &GcRef, value: &GcRef, env: GcRef, env_module: &str, recursion_depth: usize) -> Result<Option<GcRef>, GcRef> {
+    if recursion_depth > config::MAX_RECURSION_DEPTH {
+        return Err(LispError::new("stackoverflow", "match").build(mem));
+    }
+
+    match pattern.get_type() {
+        TypeLabel::Symbol => {
+            if symbol_eq!(pattern, mem.symbol_for("_")) {
+                return Ok(Some(env));
+            }
+            let name_value = mem.allocate_cons(pattern.clone(), value.clone());
+            return Ok(Some(mem.allocate_cons(name_value, env)));
+        },
+        TypeLabel::Cons => {},
+        _ => {
+            return Ok(if equal_internal(pattern.clone(), value.clone()) {Some(env)} else {None});
+        },
+    }
+
+    let Some(elems) = list_to_vec(pattern.clone()) else {
+        return Err(invalid_pattern(mem, pattern, "match"));
+    };
+
+    let head = elems[0].clone();
+
+    if symbol_eq!(head, mem.symbol_for("quote")) {
+        let [_, datum] = elems.as_slice() else {
+            return Err(invalid_pattern(mem, pattern, "match"));
+        };
+        Ok(if equal_internal(datum.clone(), value.clone()) {Some(env)} else {None})
+    }
+    else if symbol_eq!(head, mem.symbol_for("?")) {
+        if elems.len() != 2 && elems.len() != 3 {
+            return Err(invalid_pattern(mem, pattern, "match"));
+        }
+        let quote  = mem.symbol_for("quote");
+        let quoted = vec_to_list(mem, &[quote, value.clone()]);
+        let call   = vec_to_list(mem, &[elems[1].clone(), quoted]);
+        // the predicate has already been expanded with the rest of the `match` form,
+        // unless it is the name of a macro, in which case the call has to be expanded
+        let call   = if elems[1].get_type() == TypeLabel::Symbol {macroexpand_completely(mem, call, env.clone(), env_module, recursion_depth + 1)?} else {call};
+        if eval_internal(mem, call, env.clone(), env_module.to_string(), recursion_depth + 1)?.is_nil() {
+            return Ok(None);
+        }
+        match elems.get(2) {
+            Some(p) => match_pattern(mem, p, value, env, env_module, recursion_depth + 1),
+            None    => Ok(Some(env)),
+        }
+    }
+    else if TYPE_GUARDS.iter().any(|guard| symbol_eq!(head, mem.symbol_for(guard))) {
+        if elems.len() != 1 && elems.len() != 2 {
+            return Err(invalid_pattern(mem, pattern, "match"));
+        }
+        let value_type = type_of(mem, std::slice::from_ref(value), GcRef::nil(), recursion_depth + 1)?;
+        if !symbol_eq!(head, value_type) {
+            return Ok(None);
+        }
+        match elems.get(1) {
+            Some(p) => match_pattern(mem, p, value, env, env_module, recursion_depth + 1),
+            None    => Ok(Some(env)),
+        }
+    }
+    else if symbol_eq!(head, mem.symbol_for("cons")) {
+        let [_, car_pattern, cdr_pattern] = elems.as_slice() else {
+            return Err(invalid_pattern(mem, pattern, "match"));
+        };
+        let Some(PrimitiveValue::Cons(cons)) = value.get() else {
+            return Ok(None);
+        };
+        let (car, cdr) = (cons.get_car(), cons.get_cdr());
+        match match_pattern(mem, car_pattern, &car, env, env_module, recursion_depth + 1)? {
+            Some(env) => match_pattern(mem, cdr_pattern, &cdr, env, env_module, recursion_depth + 1),
+            None      => Ok(None),
+        }
+    }
+    else if symbol_eq!(head, mem.symbol_for("list")) {
+        match_list_pattern(mem, &elems[1 ..], value, env, env_module, recursion_depth)
+    }
+    else {
+        match_list_pattern(mem, &elems, value, env, env_module, recursion_depth)
+    }
+}
+
+
+fn match_list_pattern(mem: &mut Memory, elems: &[GcRef], value: &GcRef, env: GcRef, env_module: &str, recursion_depth: usize) -> Result<Option<GcRef>, GcRef> {
+    let (patterns, rest) = split_rest(mem, elems, "match")?;
+
+    let values = match list_to_vec(value.clone()) {
+        Some(values) if values.len() == patterns.len() || (rest.is_some() && values.len() > patterns.len()) => values,
+        _ => return Ok(None),
+    };
+
+    let mut new_env = env;
+
+    for (p, v) in patterns.iter().zip(values.iter()) {
+        match match_pattern(mem, p, v, new_env, env_module, recursion_depth + 1)? {
+            Some(env) => new_env = env,
+            None      => return Ok(None),
+        }
+    }
+
+    if let Some(rest) = rest {
+        let rest_values = vec_to_list(mem, &values[patterns.len() ..]);
+        return match_pattern(mem, &rest, &rest_values, new_env, env_module, recursion_depth + 1);
+    }
+
+    Ok(Some(new_env))
+}
@@ -0,0 +1,191 @@
+use crate::memory::*;
+use crate::util::*;
+use crate::error_utils::*;
+use super::MACROEXPAND;
+
+
+
+// `(quasiquote x)` is expanded into code that builds `x`, where:
+//
+//   (unquote e)            is replaced by the value of `e`
+//   (unquote-splicing e)   is replaced by the elements of the value of `e` (only allowed inside a list)
+//
+// Each nested `quasiquote` increases the nesting level by one, each `unquote` (or `unquote-splicing`) decreases it;
+// only the forms at level zero are evaluated, the rest is rebuilt as data.
+//
+// Parts of `x` that don't contain anything to evaluate are emitted as a single `(quote ...)`, and runs of elements
+// without splicing are built by one call to `list`. The functions are referenced directly (not by their names),
+// so local bindings called `list` or `cons` don't interfere with the expansion.
+
+
+enum Expansion {
+    Constant(GcRef),
+    Code(GcRef),
+}
+
+enum Element {
+    Single(Expansion),
+    Splice(GcRef),
+}
+
+
+/// Return the operand of `(name operand)`, if `form` has this shape.
+fn unary_form(mem: &mut Memory, form: &GcRef, name: &str) -> Result<Option<GcRef>, GcRef> {
+    if let Some(elems) = list_to_vec(form.clone()) {
+        if elems.first().map(|x| symbol_eq!(x, mem.symbol_for(name))).unwrap_or(false) {
+            if elems.len() != 2 {
+                return Err(LispError::new("invalid-quasiquote", MACROEXPAND.name).detail("form", form.clone()).build(mem));
+            }
+            return Ok(Some(elems[1].clone()));
+        }
+    }
+    Ok(None)
+}
+
+
+fn native(mem: &mut Memory, name: &str) -> GcRef {
+    match mem.get_global_from_module(name, "native") {
+        Ok(function) => function,
+        Err(_)       => panic!("native function `{name}` is not loaded"),
+    }
+}
+
+
+fn to_code(mem: &mut Memory, expansion: Expansion) -> GcRef {
+    match expansion {
+        Expansion::Code(code)      => code,
+        Expansion::Constant(datum) => {
+            let quote = mem.symbol_for("quote");
+            vec_to_list(mem, &[quote, datum])
+        },
+    }
+}
+
+
+/// Rebuild `(name operand)` around the expansion of `operand`.
+fn wrap(mem: &mut Memory, name: &str, operand: Expansion) -> Expansion {
+    let symbol = mem.symbol_for(name);
+    match operand {
+        Expansion::Constant(datum) => Expansion::Constant(vec_to_list(mem, &[symbol, datum])),
+        code                       => {
+            let list   = native(mem, "list");
+            let quoted = to_code(mem, Expansion::Constant(symbol));
+            let code   = to_code(mem, code);
+            Expansion::Code(vec_to_list(mem, &[list, quoted, code]))
+        },
+    }
+}
+
+
+fn expand(mem: &mut Memory, x: GcRef, depth: usize) -> Result<Expansion, GcRef> {
+    if let Some(operand) = unary_form(mem, &x, "unquote")? {
+        return if depth == 1 {
+            Ok(Expansion::Code(operand))
+        }
+        else {
+            let inner = expand(mem, operand, depth - 1)?;
+            Ok(wrap(mem, "unquote", inner))
+        };
+    }
+    if unary_form(mem, &x, "unquote-splicing")?.is_some() && depth == 1 {
+        return Err(LispError::new("unquote-splicing-outside-list", MACROEXPAND.name).detail("form", x).build(mem));
+    }
+    if let Some(operand) = unary_form(mem, &x, "quasiquote")? {
+        let inner = expand(mem, operand, depth + 1)?;
+        return Ok(wrap(mem, "quasiquote", inner));
+    }
+
+    if let Some(elems) = list_to_vec(x.clone()) {
+        let mut expanded = Vec::with_capacity(elems.len());
+        for elem in elems {
+            match unary_form(mem, &elem, "unquote-splicing")? {
+                Some(operand) if depth == 1 => expanded.push(Element::Splice(operand)),
+                Some(operand)               => {
+                    let inner = expand(mem, operand, depth - 1)?;
+                    expanded.push(Element::Single(wrap(mem, "unquote-splicing", inner)));
+                },
+                None                        => expanded.push(Element::Single(expand(mem, elem, depth)?)),
+            }
+        }
+        Ok(build_list(mem, expanded))
+    }
+    else if let Some(PrimitiveValue::Cons(cons)) = x.get() {
+        let car = expand(mem, cons.get_car(), depth)?;
+        let cdr = expand(mem, cons.get_cdr(), depth)?;
+        match (car, cdr) {
+            (Expansion::Constant(_), Expansion::Constant(_)) => Ok(Expansion::Constant(x)),
+            (car, cdr)                                       => {
+                let cons = native(mem, "cons");
+                let car  = to_code(mem, car);
+                let cdr  = to_code(mem, cdr);
+                Ok(Expansion::Code(vec_to_list(mem, &[cons, car, cdr])))
+            },
+        }
+    }
+    else {
+        Ok(Expansion::Constant(x))
+    }
+}
+
+
+/// Build a list out of the expanded elements, working backwards from the end of the list.
+fn build_list(mem: &mut Memory, elems: Vec<Element>) -> Expansion {
+    let mut tail  = Expansion::Constant(GcRef::nil());
+    let mut elems = elems.into_iter().rev().peekable();
+
+    while let Some(elem) = elems.next() {
+        match elem {
+            Element::Splice(operand) => {
+                let append = native(mem, "append");
+                let rest   = to_code(mem, tail);
+                tail = Expansion::Code(vec_to_list(mem, &[append, operand, rest]));
+            },
+            Element::Single(first) => {
+                // collect the whole run of non-spliced elements (in reverse order)
+                let mut run = vec![first];
+                while let Some(Element::Single(_)) = elems.peek() {
+                    if let Some(Element::Single(x)) = elems.next() {
+                        run.push(x);
+                    }
+                }
+                run.reverse();
+
+                let all_constant = matches!(tail, Expansion::Constant(_)) && run.iter().all(|x| matches!(x, Expansion::Constant(_)));
+                if all_constant {
+                    let mut datum = if let Expansion::Constant(d) = tail {d} else {unreachable!()};
+                    for x in run.into_iter().rev() {
+                        if let Expansion::Constant(d) = x {
+                            datum = mem.allocate_cons(d, datum);
+                        }
+                    }
+                    tail = Expansion::Constant(datum);
+                }
+                else {
+                    let list      = native(mem, "list");
+                    let mut call  = vec![list];
+                    call.extend(run.into_iter().map(|x| to_code(mem, x)));
+                    let list_code = vec_to_list(mem, &call);
+                    tail =
+                    match tail {
+                        Expansion::Constant(d) if d.is_nil() => Expansion::Code(list_code),
+                        rest                                 => {
+                            let append = native(mem, "append");
+                            let rest   = to_code(mem, rest);
+                            Expansion::Code(vec_to_list(mem, &[append, list_code, rest]))
+                        },
+                    };
+                }
+            },
+        }
+    }
+
+    tail
+}
+
+
+/// Expand `(quasiquote template)` into code that builds `template`.
+pub fn expand_quasiquote(mem: &mut Memory, form: GcRef) -> Result<GcRef, GcRef> {
+    let template  = unary_form(mem, &form, "quasiquote")?.expect("not a quasiquote form");
+    let expansion = expand(mem, template, 1)?;
+    Ok(to_code(mem, expansion))
+}
@@ -97,7 +97,7 @@ fn make_lambda() {
     let value = eval_external(&mut mem, tree);
     assert_eq!(value.clone().unwrap().get().unwrap().as_function().as_normal_function().get_kind(), FunctionKind::Lambda);
     assert_eq_symbol!(value.clone().unwrap().get().unwrap().as_function().as_normal_function().get_body(), mem.symbol_for("y"));
-    let p = value.clone().unwrap().get().unwrap().as_function().as_normal_function().non_rest_params().collect::<Vec<GcRef>>();
+    let p = value.clone().unwrap().get().unwrap().as_function().as_normal_function().get_lambda_list().required;
     assert_eq_symbol!(p[0], mem.symbol_for("x"));
     assert_eq_symbol!(p[1], mem.symbol_for("y"));
 }
@@ -111,7 +111,7 @@ fn make_lambda_bad_param_list() {
     let tree   = vec_to_list(&mut mem, &vec);
 
     let value = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind wrong-argument-type source lambda argument-value x expected list-type actual symbol-type)");
+    assert_eq!(value.err().unwrap(), "(kind wrong-argument-type source lambda message \"expected list-type, got symbol-type\" details (argument-value x expected list-type actual symbol-type) location ())");
 }
 
 #[test]
@@ -124,7 +124,7 @@ fn make_lambda_bad_param() {
     let tree   = vec_to_list(&mut mem, &vec);
 
     let value = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind param-is-not-symbol source lambda param 10)");
+    assert_eq!(value.err().unwrap(), "(kind param-is-not-symbol source lambda message \"the parameter 10 is not a symbol or a pattern\" details (parameter 10) location ())");
 }
 
 #[test]
@@ -137,7 +137,7 @@ fn make_lambda_not_enough_args() {
     let tree   = vec_to_list(&mut mem, &vec);
 
     let value = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source lambda expected 2 actual 1)");
+    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source lambda message \"wrong number of arguments: expected 2, got 1\" details (expected 2 actual 1) location ())");
 }
 
 #[test]
@@ -150,7 +150,7 @@ fn make_lambda_too_many_args() {
     let tree   = vec_to_list(&mut mem, &vec);
 
     let value = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source lambda expected 2 actual 3)");
+    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source lambda message \"wrong number of arguments: expected 2, got 3\" details (expected 2 actual 3) location ())");
 }
 
 #[test]
@@ -182,7 +182,7 @@ fn eval_not_lambda() {
     let tree       = vec_to_list(&mut mem, &vec2);
 
     let value = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind unbound-symbol source eval symbol mu)");
+    assert_eq!(value.err().unwrap(), "(kind unbound-symbol source eval message \"the symbol `mu` is unbound\" details (symbol mu) location ())");
 }
 
 #[test]
@@ -218,7 +218,7 @@ fn eval_unbound_symbol() {
 
     let tree  = mem.symbol_for("apple-tree");
     let value = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind unbound-symbol source eval symbol apple-tree)");
+    assert_eq!(value.err().unwrap(), "(kind unbound-symbol source eval message \"the symbol `apple-tree` is unbound\" details (symbol apple-tree) location ())");
 }
 
 #[test]
@@ -265,7 +265,7 @@ fn eval_list_bad_operator() {
     let vec   = vec![mem.allocate_number(0), mem.allocate_number(-10), mem.allocate_number(-20), mem.allocate_number(-30)];
     let tree  = vec_to_list(&mut mem, &vec);
     let value = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind eval-bad-operator source eval symbol 0)");
+    assert_eq!(value.err().unwrap(), "(kind eval-bad-operator source eval message \"the operator 0 is not a function\" details (operator 0) location ())");
 }
 
 #[test]
@@ -300,7 +300,7 @@ fn eval_call_lambda_unbound_params() {
     let tree    = vec_to_list(&mut mem, &vec);
 
     let value = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind unbound-symbol source eval symbol no-value)");
+    assert_eq!(value.err().unwrap(), "(kind unbound-symbol source eval message \"the symbol `no-value` is unbound\" details (symbol no-value) location ())");
 }
 
 #[test]
@@ -343,7 +343,7 @@ fn test_native_function(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursio
         Ok(args[1].clone())
     }
     else {
-        let error = make_error(mem, "wrong-number-of-arguments", "test_native_function", &vec![]);
+        let error = LispError::new("wrong-number-of-arguments", "test_native_function").build(mem);
         Err(error)
     }
 }
@@ -358,7 +358,7 @@ fn eval_native_function_not_enough_args() {
     let tree = vec_to_list(&mut mem, &vec);
 
     let value     = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source test_native_function)");
+    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source test_native_function message \"wrong number of arguments\" details () location () backtrace ((function \"<lambda>\")))");
 }
 
 #[test]
@@ -371,7 +371,7 @@ fn eval_native_function_too_many_args() {
     let tree = vec_to_list(&mut mem, &vec);
 
     let value     = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source test_native_function)");
+    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source test_native_function message \"wrong number of arguments\" details () location () backtrace ((function \"<lambda>\")))");
 }
 
 #[test]
@@ -414,7 +414,7 @@ fn eval_not_enough_args() {
     let tree    = vec_to_list(&mut mem, &vec);
 
     let value     = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source #<function> expected 2 actual 1)");
+    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source #<function> message \"wrong number of arguments: expected 2, got 1\" details (expected 2 actual 1) location () backtrace ((function \"<lambda>\")))");
 }
 
 #[test]
@@ -431,5 +431,438 @@ fn eval_too_many_args() {
     let tree    = vec_to_list(&mut mem, &vec);
 
     let value     = eval_external(&mut mem, tree);
-    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source #<function> expected 2 actual 3)");
+    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source #<function> message \"wrong number of arguments: expected 2, got 3\" details (expected 2 actual 3) location () backtrace ((function \"<lambda>\")))");
+}
+
+#[test]
+fn eval_parameterize() {
+    let mut mem = Memory::new();
+
+    // a lambda that returns the value of `x`, which is not bound lexically
+    let body            = mem.symbol_for("x");
+    let has_rest_params = false;
+    let lambda          = mem.allocate_normal_function(FunctionKind::Lambda, has_rest_params, body, &[], GcRef::nil(), "default");
+    mem.define_global("f", lambda);
+
+    // (parameterize (x 5) (f))
+    let bindings = vec![mem.symbol_for("x"), mem.allocate_number(5)];
+    let call     = vec![mem.symbol_for("f")];
+    let vec      = vec![mem.symbol_for("parameterize"), vec_to_list(&mut mem, &bindings), vec_to_list(&mut mem, &call)];
+    let tree     = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_number(), 5);
+    assert!(mem.get_dynamic_environment().is_nil());
+}
+
+#[test]
+fn eval_parameterize_restore_after_signal() {
+    let mut mem = Memory::new();
+
+    // (parameterize (x 1) y)
+    let bindings = vec![mem.symbol_for("x"), mem.allocate_number(1)];
+    let vec      = vec![mem.symbol_for("parameterize"), vec_to_list(&mut mem, &bindings), mem.symbol_for("y")];
+    let tree     = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.err().unwrap(), "(kind unbound-symbol source eval message \"the symbol `y` is unbound\" details (symbol y) location ())");
+    assert!(mem.get_dynamic_environment().is_nil());
+}
+
+fn mark_cleaned_up(mem: &mut Memory, _args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let t = mem.symbol_for("t");
+    mem.define_global("cleaned-up", t);
+    Ok(GcRef::nil())
+}
+
+#[test]
+fn eval_unwind_protect() {
+    let mut mem = Memory::new();
+
+    // (unwind-protect y (cleanup))
+    let cleanup = mem.allocate_native_function(FunctionKind::Lambda, vec![], mark_cleaned_up);
+    let call    = vec_to_list(&mut mem, &[cleanup]);
+    let vec     = vec![mem.symbol_for("unwind-protect"), mem.symbol_for("y"), call.clone()];
+    let tree    = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert!(value.err().unwrap().starts_with("(kind unbound-symbol source eval message \"the symbol `y` is unbound\" details (symbol y)"));
+    assert!(mem.get_global("cleaned-up", "default").is_ok());
+
+    // (unwind-protect 5 (cleanup))
+    mem.undefine_global("cleaned-up");
+    let vec  = vec![mem.symbol_for("unwind-protect"), mem.allocate_number(5), call];
+    let tree = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_number(), 5);
+    assert!(mem.get_global("cleaned-up", "default").is_ok());
+}
+
+#[test]
+fn eval_parameterize_incomplete_binding() {
+    let mut mem = Memory::new();
+
+    // (parameterize (x) x)
+    let bindings = vec![mem.symbol_for("x")];
+    let vec      = vec![mem.symbol_for("parameterize"), vec_to_list(&mut mem, &bindings), mem.symbol_for("x")];
+    let tree     = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.err().unwrap(), "(kind incomplete-binding source parameterize message \"the binding of `x` has no value\" details (name x) location ())");
+}
+
+#[test]
+fn eval_let_star() {
+    let mut mem = Memory::new();
+
+    // (let* (x 1, y x) y)
+    let bindings = vec![mem.symbol_for("x"), mem.allocate_number(1), mem.symbol_for("y"), mem.symbol_for("x")];
+    let vec      = vec![mem.symbol_for("let*"), vec_to_list(&mut mem, &bindings), mem.symbol_for("y")];
+    let tree     = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_number(), 1);
+}
+
+#[test]
+fn eval_let_is_parallel() {
+    let mut mem = Memory::new();
+
+    // (let (x 1, y x) y)
+    let bindings = vec![mem.symbol_for("x"), mem.allocate_number(1), mem.symbol_for("y"), mem.symbol_for("x")];
+    let vec      = vec![mem.symbol_for("let"), vec_to_list(&mut mem, &bindings), mem.symbol_for("y")];
+    let tree     = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.err().unwrap(), "(kind unbound-symbol source eval message \"the symbol `x` is unbound\" details (symbol x) location ())");
+}
+
+#[test]
+fn eval_letrec() {
+    let mut mem = Memory::new();
+
+    // (letrec (f (lambda () f)) (f))
+    let lambda_vec = vec![mem.symbol_for("lambda"), GcRef::nil(), mem.symbol_for("f")];
+    let lambda     = vec_to_list(&mut mem, &lambda_vec);
+    let bindings   = vec![mem.symbol_for("f"), lambda];
+    let call       = vec![mem.symbol_for("f")];
+    let vec        = vec![mem.symbol_for("letrec"), vec_to_list(&mut mem, &bindings), vec_to_list(&mut mem, &call)];
+    let tree       = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree).unwrap();
+    assert!(value.get_type() == TypeLabel::Function);
+}
+
+#[test]
+fn eval_named_let() {
+    let mut mem = Memory::new();
+
+    // (let loop (x nil) (if x x (loop 'done)))
+    let bindings = vec![mem.symbol_for("x"), GcRef::nil()];
+    let quoted   = vec![mem.symbol_for("quote"), mem.symbol_for("done")];
+    let quoted   = vec_to_list(&mut mem, &quoted);
+    let call     = vec![mem.symbol_for("loop"), quoted];
+    let call     = vec_to_list(&mut mem, &call);
+    let if_vec   = vec![mem.symbol_for("if"), mem.symbol_for("x"), mem.symbol_for("x"), call];
+    let if_expr  = vec_to_list(&mut mem, &if_vec);
+    let vec      = vec![mem.symbol_for("let"), mem.symbol_for("loop"), vec_to_list(&mut mem, &bindings), if_expr];
+    let tree     = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.unwrap().get().unwrap().as_symbol().get_name(), "done");
+}
+
+#[test]
+fn eval_call_lambda_destructuring() {
+    let mut mem = Memory::new();
+
+    // a lambda that returns the second element of its (only) parameter
+    let pattern = vec![mem.symbol_for("x"), mem.symbol_for("y")];
+    let params  = vec![vec_to_list(&mut mem, &pattern)];
+    let body    = mem.symbol_for("y");
+    let has_rest_params = false;
+    let lambda  = mem.allocate_normal_function(FunctionKind::Lambda, has_rest_params, body, &params, GcRef::nil(), "default");
+
+    let arg     = vec![mem.allocate_character('A'), mem.allocate_character('B')];
+    let arg     = vec_to_list(&mut mem, &arg);
+    let quoted  = vec![mem.symbol_for("quote"), arg];
+    let vec     = vec![lambda, vec_to_list(&mut mem, &quoted)];
+    let tree    = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_character(), 'B');
+}
+
+#[test]
+fn eval_call_lambda_pattern_mismatch() {
+    let mut mem = Memory::new();
+
+    let pattern = vec![mem.symbol_for("x"), mem.symbol_for("y")];
+    let params  = vec![vec_to_list(&mut mem, &pattern)];
+    let body    = mem.symbol_for("y");
+    let has_rest_params = false;
+    let lambda  = mem.allocate_normal_function(FunctionKind::Lambda, has_rest_params, body, &params, GcRef::nil(), "default");
+
+    let vec     = vec![lambda, mem.allocate_number(1)];
+    let tree    = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.err().unwrap(), "(kind pattern-mismatch source #<function> message \"the value 1 does not match the pattern (x y)\" details (pattern (x y) value 1) location () backtrace ((function \"<lambda>\")))");
+}
+
+#[test]
+fn eval_call_lambda_type_annotation() {
+    let mut mem = Memory::new();
+
+    let annotation = vec![mem.symbol_for("number-type"), mem.symbol_for("x")];
+    let params  = vec![vec_to_list(&mut mem, &annotation)];
+    let body    = mem.symbol_for("x");
+    let has_rest_params = false;
+    let lambda  = mem.allocate_normal_function(FunctionKind::Lambda, has_rest_params, body, &params, GcRef::nil(), "default");
+
+    let vec     = vec![lambda.clone(), mem.allocate_number(1)];
+    let tree    = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_number(), 1);
+
+    let quote   = mem.symbol_for("quote");
+    let symbol  = mem.symbol_for("a");
+    let quoted  = vec_to_list(&mut mem, &[quote, symbol]);
+    let vec     = vec![lambda, quoted];
+    let tree    = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.err().unwrap(), "(kind wrong-argument-type source #<function> message \"expected number-type for argument `x`, got symbol-type\" details (argument-value a parameter x expected number-type actual symbol-type) location () backtrace ((function \"<lambda>\")))");
+}
+
+#[test]
+fn bind_key_pattern() {
+    let mut mem = Memory::new();
+
+    // (&key b c) against (a 1 b 2)
+    let pattern = vec![mem.symbol_for("&key"), mem.symbol_for("b"), mem.symbol_for("c")];
+    let pattern = vec_to_list(&mut mem, &pattern);
+    let plist   = vec![mem.symbol_for("a"), mem.allocate_number(1), mem.symbol_for("b"), mem.allocate_number(2)];
+    let plist   = vec_to_list(&mut mem, &plist);
+
+    let env = bind_pattern(&mut mem, pattern, plist, GcRef::nil(), "test").ok().unwrap();
+    let current_module = mem.get_current_module();
+    let b = mem.symbol_for("b");
+    let c = mem.symbol_for("c");
+    assert_eq!(*lookup(&mut mem, b, env.clone(), &current_module).unwrap().get().unwrap().as_number(), 2);
+    assert!(lookup(&mut mem, c, env, &current_module).unwrap().is_nil());
+}
+
+#[test]
+fn bind_rest_pattern() {
+    let mut mem = Memory::new();
+
+    // (x & (y)) against (1 2)
+    let inner   = vec![mem.symbol_for("y")];
+    let pattern = vec![mem.symbol_for("x"), mem.symbol_for("&"), vec_to_list(&mut mem, &inner)];
+    let pattern = vec_to_list(&mut mem, &pattern);
+    let value   = vec![mem.allocate_number(1), mem.allocate_number(2)];
+    let value   = vec_to_list(&mut mem, &value);
+
+    let env = bind_pattern(&mut mem, pattern, value, GcRef::nil(), "test").ok().unwrap();
+    let current_module = mem.get_current_module();
+    let y = mem.symbol_for("y");
+    assert_eq!(*lookup(&mut mem, y, env, &current_module).unwrap().get().unwrap().as_number(), 2);
+}
+
+#[test]
+fn validate_bad_key_pattern() {
+    let mut mem = Memory::new();
+
+    // (&key 1)
+    let pattern = vec![mem.symbol_for("&key"), mem.allocate_number(1)];
+    let pattern = vec_to_list(&mut mem, &pattern);
+
+    let error = validate_pattern(&mut mem, &pattern, "test").err().unwrap();
+    assert_eq!(list_to_string(print(&mut mem, &[error], GcRef::nil(), 0).ok().unwrap()).unwrap(), "(kind invalid-pattern source test message \"invalid pattern (&key 1)\" details (pattern (&key 1)) location ())");
+}
+
+#[test]
+fn eval_call_lambda_optional_default() {
+    let mut mem = Memory::new();
+
+    // (lambda (x &optional (y x)) y)
+    let spec    = vec![mem.symbol_for("y"), mem.symbol_for("x")];
+    let params  = vec![mem.symbol_for("x"), mem.symbol_for("&optional"), vec_to_list(&mut mem, &spec)];
+    let vec     = vec![mem.symbol_for("lambda"), vec_to_list(&mut mem, &params), mem.symbol_for("y")];
+    let lambda  = vec_to_list(&mut mem, &vec);
+
+    let call    = vec![lambda.clone(), mem.allocate_number(1)];
+    let tree    = vec_to_list(&mut mem, &call);
+    let value   = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_number(), 1);
+
+    let call    = vec![lambda, mem.allocate_number(1), mem.allocate_number(2)];
+    let tree    = vec_to_list(&mut mem, &call);
+    let value   = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_number(), 2);
+}
+
+#[test]
+fn eval_call_lambda_optional_too_many_args() {
+    let mut mem = Memory::new();
+
+    // ((lambda (&optional x) x) 1 2)
+    let params  = vec![mem.symbol_for("&optional"), mem.symbol_for("x")];
+    let vec     = vec![mem.symbol_for("lambda"), vec_to_list(&mut mem, &params), mem.symbol_for("x")];
+    let lambda  = vec_to_list(&mut mem, &vec);
+    let call    = vec![lambda, mem.allocate_number(1), mem.allocate_number(2)];
+    let tree    = vec_to_list(&mut mem, &call);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.err().unwrap(), "(kind wrong-number-of-arguments source #<function> message \"wrong number of arguments: expected 0 to 1, got 2\" details (expected-min 0 expected-max 1 actual 2) location () backtrace ((function \"<lambda>\")))");
+}
+
+#[test]
+fn eval_call_lambda_key() {
+    let mut mem = Memory::new();
+
+    // ((lambda (&key x (y 5)) y) 'x 1)
+    let spec    = vec![mem.symbol_for("y"), mem.allocate_number(5)];
+    let params  = vec![mem.symbol_for("&key"), mem.symbol_for("x"), vec_to_list(&mut mem, &spec)];
+    let vec     = vec![mem.symbol_for("lambda"), vec_to_list(&mut mem, &params), mem.symbol_for("y")];
+    let lambda  = vec_to_list(&mut mem, &vec);
+    let quoted  = vec![mem.symbol_for("quote"), mem.symbol_for("x")];
+    let call    = vec![lambda, vec_to_list(&mut mem, &quoted), mem.allocate_number(1)];
+    let tree    = vec_to_list(&mut mem, &call);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_number(), 5);
+}
+
+#[test]
+fn make_lambda_misplaced_lambda_list_keyword() {
+    let mut mem = Memory::new();
+
+    // (lambda (&key x &optional y) x)
+    let params = vec![mem.symbol_for("&key"), mem.symbol_for("x"), mem.symbol_for("&optional"), mem.symbol_for("y")];
+    let vec    = vec![mem.symbol_for("lambda"), vec_to_list(&mut mem, &params), mem.symbol_for("x")];
+    let tree   = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.err().unwrap(), "(kind misplaced-lambda-list-keyword source lambda message \"misplaced lambda list keyword `&optional`\" details (parameter &optional) location ())");
+}
+
+#[test]
+fn eval_match() {
+    let mut mem = Memory::new();
+
+    // (match 2 (1 'one) ((number-type n) n))
+    let quoted  = vec![mem.symbol_for("quote"), mem.symbol_for("one")];
+    let clause1 = vec![mem.allocate_number(1), vec_to_list(&mut mem, &quoted)];
+    let guard   = vec![mem.symbol_for("number-type"), mem.symbol_for("n")];
+    let clause2 = vec![vec_to_list(&mut mem, &guard), mem.symbol_for("n")];
+    let vec     = vec![mem.symbol_for("match"), mem.allocate_number(2), vec_to_list(&mut mem, &clause1), vec_to_list(&mut mem, &clause2)];
+    let tree    = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(*value.unwrap().get().unwrap().as_number(), 2);
+}
+
+#[test]
+fn eval_match_no_match() {
+    let mut mem = Memory::new();
+
+    // (match 2 (1 1))
+    let clause = vec![mem.allocate_number(1), mem.allocate_number(1)];
+    let vec    = vec![mem.symbol_for("match"), mem.allocate_number(2), vec_to_list(&mut mem, &clause)];
+    let tree   = vec_to_list(&mut mem, &vec);
+
+    let value = eval_external(&mut mem, tree);
+    assert_eq!(value.err().unwrap(), "(kind no-match source match message \"no pattern matches the value 2\" details (value 2) location ())");
+}
+
+#[test]
+fn match_cons_pattern() {
+    let mut mem = Memory::new();
+
+    // (cons _ (symbol-type)) against (1 . x)
+    let guard   = vec![mem.symbol_for("symbol-type")];
+    let pattern = vec![mem.symbol_for("cons"), mem.symbol_for("_"), vec_to_list(&mut mem, &guard)];
+    let pattern = vec_to_list(&mut mem, &pattern);
+    let one     = mem.allocate_number(1);
+    let x       = mem.symbol_for("x");
+    let value   = mem.allocate_cons(one.clone(), x);
+    let env     = GcRef::nil();
+
+    assert!(match_pattern(&mut mem, &pattern, &value, env.clone(), "default", 0).ok().unwrap().is_some());
+    assert!(match_pattern(&mut mem, &pattern, &one, env, "default", 0).ok().unwrap().is_none());
+}
+
+#[test]
+fn expand_constant_quasiquote() {
+    let mut mem = Memory::new();
+
+    // (quasiquote (a (b))) => (quote (a (b)))
+    let inner    = vec![mem.symbol_for("b")];
+    let template = vec![mem.symbol_for("a"), vec_to_list(&mut mem, &inner)];
+    let vec      = vec![mem.symbol_for("quasiquote"), vec_to_list(&mut mem, &template)];
+    let form     = vec_to_list(&mut mem, &vec);
+
+    let expanded = list_to_vec(expand_quasiquote(&mut mem, form).ok().unwrap()).unwrap();
+    assert_eq!(expanded.len(), 2);
+    assert_eq_symbol!(expanded[0], mem.symbol_for("quote"));
+    let datum = list_to_vec(expanded[1].clone()).unwrap();
+    assert_eq!(datum.len(), 2);
+    assert_eq_symbol!(datum[0], mem.symbol_for("a"));
+}
+
+#[test]
+fn expand_quasiquote_unquote_splicing_outside_list() {
+    let mut mem = Memory::new();
+
+    // (quasiquote (unquote-splicing x))
+    let splice = vec![mem.symbol_for("unquote-splicing"), mem.symbol_for("x")];
+    let vec    = vec![mem.symbol_for("quasiquote"), vec_to_list(&mut mem, &splice)];
+    let form   = vec_to_list(&mut mem, &vec);
+
+    assert!(expand_quasiquote(&mut mem, form).is_err());
+}
+
+#[test]
+fn macroexpand_trace_records_each_call() {
+    let mut mem = Memory::new();
+
+    // a macro that expands to its argument
+    let params          = vec![mem.symbol_for("x")];
+    let body            = mem.symbol_for("x");
+    let has_rest_params = false;
+    let id              = mem.allocate_normal_function(FunctionKind::Macro, has_rest_params, body, &params, GcRef::nil(), "default");
+    mem.define_global("id", id);
+
+    // (id (id 5))
+    let inner = vec![mem.symbol_for("id"), mem.allocate_number(5)];
+    let vec   = vec![mem.symbol_for("id"), vec_to_list(&mut mem, &inner)];
+    let tree  = vec_to_list(&mut mem, &vec);
+
+    let trace = list_to_vec(macroexpand_trace(&mut mem, &[tree], GcRef::nil(), 0).ok().unwrap()).unwrap();
+    assert_eq!(trace.len(), 2);
+    let expansion = crate::native::list::property(&mut mem, "expansion", trace[1].clone()).unwrap();
+    assert_eq!(*expansion.get().unwrap().as_number(), 5);
+}
+
+#[test]
+fn macroexpand_once_ignores_non_macro_calls() {
+    let mut mem = Memory::new();
+
+    let params          = vec![mem.symbol_for("x")];
+    let body            = mem.symbol_for("x");
+    let has_rest_params = false;
+    let id              = mem.allocate_normal_function(FunctionKind::Macro, has_rest_params, body, &params, GcRef::nil(), "default");
+    mem.define_global("id", id);
+
+    // (f (id 5)) is not a macro call, so it stays as it is
+    let inner = vec![mem.symbol_for("id"), mem.allocate_number(5)];
+    let vec   = vec![mem.symbol_for("f"), vec_to_list(&mut mem, &inner)];
+    let tree  = vec_to_list(&mut mem, &vec);
+
+    let result = list_to_vec(macroexpand_1(&mut mem, &[tree], GcRef::nil(), 0).ok().unwrap()).unwrap();
+    assert_eq!(list_to_vec(result[1].clone()).unwrap().len(), 2);
 }
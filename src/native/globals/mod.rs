@@ -21,7 +21,7 @@ pub fn with_current_module(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recur
 
     mem.get_global(&name.get_name(), &module.get_name()).map_err(|_| {
         let details = vec![("symbol", args[0].clone())];
-        make_error(mem, "unbound-symbol", WITH_CURRENT_MODULE.name, &details)
+        LispError::new("unbound-symbol", WITH_CURRENT_MODULE.name).details(&details).build(mem)
     })
 }
 
@@ -42,11 +42,11 @@ pub fn from_module(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_dep
         Ok(x) => Ok(x),
         Err(ModulError::GlobalNonExistentOrPrivate) => {
             let details = vec![("symbol", args[0].clone())];
-            Err(make_error(mem, "unbound-symbol", FROM_MODULE.name, &details))
+            Err(LispError::new("unbound-symbol", FROM_MODULE.name).details(&details).build(mem))
         },
         Err(ModulError::ModuleNonExistent) => {
             let details = vec![("module", args[1].clone())];
-            Err(make_error(mem, "no-such-module", FROM_MODULE.name, &details))
+            Err(LispError::new("no-such-module", FROM_MODULE.name).details(&details).build(mem))
         },
         _ => unreachable!(),
     }
@@ -86,10 +86,10 @@ pub fn export(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: u
             mem.add_export(&s.get_name());
         }
         else {
-            let details = vec![("expected", mem.symbol_for("symbol-type")),
-                               ("actual",   mem.symbol_for(name.get_type().to_string())),
-                               ("symbol",   name)];
-            return Err(make_error(mem, "wrong-argument-type", EXPORT.name, &details));
+            let details = vec![("argument-value", name.clone()),
+                               ("expected",       mem.symbol_for("symbol-type")),
+                               ("actual",         mem.symbol_for(name.get_type().to_string()))];
+            return Err(LispError::new("wrong-argument-type", EXPORT.name).details(&details).build(mem));
         }
     }
 
@@ -129,7 +129,7 @@ pub fn define(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: us
     validate_args!(mem, DEFINE.name, args, (let name: TypeLabel::Symbol), (let value: TypeLabel::Any), (let documentation: TypeLabel::String));    
 
     if mem.is_global_defined(&name.get_name()) {
-        return Err(mem.symbol_for("already-defined"));
+        return Err(LispError::new("already-defined", DEFINE.name).detail("symbol", args[0].clone()).build(mem));
     }
 
     if let Some(meta) = args[0].get_meta() {
@@ -1,6 +1,7 @@
 use crate::{memory::*, util::string_to_list};
 use crate::util::*;
 use crate::error_utils::*;
+use crate::native::eval::{eval, parameterize};
 use super::NativeFunctionMetaData;
 use std::io::BufReader;
 use std::io::prelude::*;
@@ -31,7 +32,7 @@ pub fn input_file(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_dept
                             if let Ok(msg) = umb.from_high_end.try_recv() {
                                 match msg.get("command").map(|s| s.as_str()) {
                                     Some("INTERRUPT") => {
-                                        return Err(make_error(mem, "interrupted", INPUT_FILE.name, &vec![]));
+                                        return Err(LispError::new("interrupted", INPUT_FILE.name).build(mem));
                                     },
                                     Some("ABORT") => {
                                         return Err(GcRef::nil());
@@ -43,43 +44,81 @@ pub fn input_file(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_dept
                         continue;
                     }
                     else {
-                        let details = vec![("details", string_to_list(mem, &err.kind().to_string()))];
-                        return Err(make_error(mem, "cannot-read-file", INPUT_FILE.name, &details));
+                        let details = vec![("reason", string_to_list(mem, &err.kind().to_string()))];
+                        return Err(LispError::new("cannot-read-file", INPUT_FILE.name).details(&details).build(mem));
                     }
                 },
-                Ok(0) => return Err(make_error(mem, "eof", INPUT_FILE.name, &vec![])),
+                Ok(0) => return Err(LispError::new("eof", INPUT_FILE.name).build(mem)),
                 Ok(_) => return Ok(string_to_list(mem, &line)),
             }
         }
     }
     else {
         let Some(path) = list_to_string(input_source.clone()) else {
-            let error_details = vec![("expected", mem.symbol_for("string-type")), ("actual", mem.symbol_for(input_source.get_type().to_string()))];
-            return Err(make_error(mem, "wrong-argument-type", INPUT_FILE.name, &error_details));
+            let error_details = vec![("argument-value", input_source.clone()), ("expected", mem.symbol_for("string-type")), ("actual", mem.symbol_for(input_source.get_type().to_string()))];
+            return Err(LispError::new("wrong-argument-type", INPUT_FILE.name).details(&error_details).build(mem));
         };
         match std::fs::read_to_string(path) {
             Ok(string) => Ok(string_to_list(mem, &string)),
             Err(err)   => {
                 let details = string_to_list(mem, &err.kind().to_string());
-                Err(make_error(mem, "cannot-read-file", INPUT_FILE.name, &vec![("details", details)]))
+                Err(LispError::new("cannot-read-file", INPUT_FILE.name).detail("reason", details).build(mem))
             },
         }
     }
 }
 
 
+pub const ABSOLUTE_PATH: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      absolute_path,
+    name:          "absolute-path",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["path"],
+    documentation: "Return the absolute form of `path`, with all symbolic links resolved.
+Error if there is no file at `path`."
+};
+
+pub fn absolute_path(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, ABSOLUTE_PATH.name, args, (let path: TypeLabel::String));
+
+    match std::fs::canonicalize(&path) {
+        Ok(absolute) => Ok(string_to_list(mem, &absolute.to_string_lossy())),
+        Err(err)     => {
+            let details = string_to_list(mem, &err.kind().to_string());
+            Err(LispError::new("cannot-read-file", ABSOLUTE_PATH.name).detail("reason", details).build(mem))
+        },
+    }
+}
+
+
 pub const OUTPUT_FILE: NativeFunctionMetaData =
 NativeFunctionMetaData{
     function:      output_file,
     name:          "output-file",
     kind:          FunctionKind::Lambda,
     parameters:    &["path", "string"],
-    documentation: "Append `string` to the file at `path`."
+    documentation: "Append `string` to the file at `path`.
+`path` can also be `*stdout*` or a string buffer created by `capture-output`."
 };
 
 pub fn output_file(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
     validate_args!(mem, OUTPUT_FILE.name, args, (let output_source: TypeLabel::Any), (let string: TypeLabel::String));
 
+    // the symbol `*stdout*` itself always refers to the current (possibly dynamically rebound) standard output
+    let output_source =
+    if symbol_eq!(output_source, mem.symbol_for("*stdout*")) {
+        mem.get_dynamic("*stdout*").unwrap_or(output_source)
+    }
+    else {
+        output_source
+    };
+
+    if let Some(buffer) = mem.get_string_output(&output_source) {
+        buffer.push_str(&string);
+        return Ok(mem.symbol_for("ok"));
+    }
+
     if symbol_eq!(output_source, mem.symbol_for("*stdout*")) {
         let status = 
         write!(mem.stdout, "{string}").and_then(|_| {
@@ -90,15 +129,15 @@ pub fn output_file(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_dep
                 Ok(mem.symbol_for("ok"))
             },
             Err(err) => {
-                let vec = vec![("details", string_to_list(mem, &err.kind().to_string()))];
-                Err(make_error(mem, "cannot-write-file", OUTPUT_FILE.name, &vec))
+                let vec = vec![("reason", string_to_list(mem, &err.kind().to_string()))];
+                Err(LispError::new("cannot-write-file", OUTPUT_FILE.name).details(&vec).build(mem))
             },
         }
     }
     else { 
         let Some(path) = list_to_string(output_source.clone()) else {
-            let error_details = vec![("expected", mem.symbol_for("string-type")), ("actual", mem.symbol_for(output_source.get_type().to_string()))];
-            return Err(make_error(mem, "wrong-argument-type", OUTPUT_FILE.name, &error_details));
+            let error_details = vec![("argument-value", output_source.clone()), ("expected", mem.symbol_for("string-type")), ("actual", mem.symbol_for(output_source.get_type().to_string()))];
+            return Err(LispError::new("wrong-argument-type", OUTPUT_FILE.name).details(&error_details).build(mem));
         };
         match std::fs::OpenOptions::new().append(true).open(path) {
             Ok(mut file)   => {
@@ -106,14 +145,38 @@ pub fn output_file(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_dep
                     Ok(_)    => Ok(mem.symbol_for("ok")),
                     Err(err) => {
                         let details = string_to_list(mem, &err.kind().to_string());
-                        Err(make_error(mem, "cannot-write-file", OUTPUT_FILE.name, &vec![("details", details)]))
+                        Err(LispError::new("cannot-write-file", OUTPUT_FILE.name).detail("reason", details).build(mem))
                     },
                 }
             },
             Err(err)   => {
                 let details = string_to_list(mem, &err.kind().to_string());
-                Err(make_error(mem, "cannot-write-file", OUTPUT_FILE.name, &vec![("details", details)]))
+                Err(LispError::new("cannot-write-file", OUTPUT_FILE.name).detail("reason", details).build(mem))
             },
         }
     }
 }
+
+
+pub const CAPTURE_OUTPUT: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      capture_output,
+    name:          "capture-output",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["function"],
+    documentation: "Call `function` without arguments while `*stdout*` is dynamically bound to a fresh string buffer.
+Return everything written to the buffer as a string.
+If a signal is emitted by `function` then the buffer is discarded and the signal is forwarded."
+};
+
+pub fn capture_output(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, CAPTURE_OUTPUT.name, args, (let _function: TypeLabel::Function));
+
+    let port     = mem.open_string_output();
+    let stdout   = mem.symbol_for("*stdout*");
+    let call     = vec_to_list(mem, &[args[0].clone()]);
+    let result   = parameterize(mem, &[(stdout, port)], |mem| eval(mem, &[call], GcRef::nil(), recursion_depth + 1));
+    let captured = mem.close_string_output();
+
+    result.map(|_| string_to_list(mem, &captured))
+}
@@ -114,7 +114,7 @@ pub fn get_property(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_de
         Ok(result)
     }
     else {
-        Err(make_error(mem, "wrong-plist-format", GET_PROPERTY.name, &vec![]))
+        Err(LispError::new("wrong-plist-format", GET_PROPERTY.name).build(mem))
     }
 }
 
@@ -154,19 +154,26 @@ NativeFunctionMetaData{
     kind:          FunctionKind::Lambda,
     parameters:    &["f"],
     documentation: "Transform `f` so that its last parameter is a normal list and not a rest-parameter.
-If `f` doesn't have rest-paramteres then it will remain unchanged.",
+If `f` doesn't have rest-paramteres (or it also has optional or key parameters) then it will remain unchanged.",
 };
 
 pub fn unrest(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
     validate_args!(mem, UNREST.name, args, (let f: TypeLabel::Function));
 
-    if let Function::NormalFunction(nf) = f {
-        let has_rest_params = false;
-        let new_nf = mem.allocate_normal_function(nf.get_kind(), has_rest_params, nf.get_body(), &nf.get_params(), nf.get_env(), &nf.get_env_module());
-        Ok(new_nf)
-    }
-    else {
-        Ok(args[0].clone())
+    let Function::NormalFunction(nf) = f else {
+        return Ok(args[0].clone());
+    };
+
+    let lambda_list = nf.get_lambda_list();
+    match lambda_list.rest {
+        Some(rest) if lambda_list.optional.is_empty() && lambda_list.key.is_empty() => {
+            let mut params = lambda_list.required;
+            params.push(rest);
+            let has_rest_params = false;
+            let new_nf = mem.allocate_normal_function(nf.get_kind(), has_rest_params, nf.get_body(), &params, nf.get_env(), &nf.get_env_module());
+            Ok(new_nf)
+        },
+        _ => Ok(args[0].clone()),
     }
 }
 
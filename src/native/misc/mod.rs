@@ -38,7 +38,7 @@ pub fn equal(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: us
     Ok(if equal_internal(x, y) {mem.symbol_for("t")} else {GcRef::nil()})
 }
 
-fn equal_internal(a: GcRef, b: GcRef) -> bool {
+pub fn equal_internal(a: GcRef, b: GcRef) -> bool {
     if let (Some(x), Some(y)) = (a.get(), b.get()) {
         match x {
             PrimitiveValue::Number(n1) => {
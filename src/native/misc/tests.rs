@@ -146,11 +146,11 @@ fn equal_through_metadata() {
     let mut mem = Memory::new();
     
     let x1   = mem.allocate_number(1);
-    let md1 = Metadata{read_name: "x1".to_string(), location: Location::Native, documentation: "cat".to_string()};
+    let md1 = Metadata{read_name: "x1".to_string(), location: Location::Native, documentation: "cat".to_string(), span: None};
     let y1  = mem.allocate_metadata(x1, md1);
 
     let x2   = mem.allocate_number(1);
-    let md2 = Metadata{read_name: "x2".to_string(), location: Location::Native, documentation: "dog".to_string()};
+    let md2 = Metadata{read_name: "x2".to_string(), location: Location::Native, documentation: "dog".to_string(), span: None};
     let y2  = mem.allocate_metadata(x2, md2);
 
     let e = equal(&mut mem, &[y1, y2], GcRef::nil(), 0).ok().unwrap();
@@ -163,11 +163,11 @@ fn equal_symbol_through_metadata() {
     let mut mem = Memory::new();
     
     let x1   = mem.symbol_for("kitten");
-    let md1 = Metadata{read_name: "x1".to_string(), location: Location::Native, documentation: "cat".to_string()};
+    let md1 = Metadata{read_name: "x1".to_string(), location: Location::Native, documentation: "cat".to_string(), span: None};
     let y1  = mem.allocate_metadata(x1, md1);
 
     let x2   = mem.symbol_for("kitten");
-    let md2 = Metadata{read_name: "x2".to_string(), location: Location::Native, documentation: "lion".to_string()};
+    let md2 = Metadata{read_name: "x2".to_string(), location: Location::Native, documentation: "lion".to_string(), span: None};
     let y2  = mem.allocate_metadata(x2, md2);
 
     let e = equal(&mut mem, &[y1, y2], GcRef::nil(), 0).ok().unwrap();
@@ -16,13 +16,32 @@ pub fn load_native_functions(mem: &mut Memory) {
     load_native_function(mem, list::UNREST);
     load_native_function(mem, signal::ABORT);
     load_native_function(mem, signal::SIGNAL);
+    load_native_function(mem, signal::MAKE_ERROR);
+    load_native_function(mem, signal::MAKE_ASSERTION_ERROR);
+    load_native_function(mem, signal::ATTACH_BACKTRACE);
+    load_native_function(mem, signal::DEFINE_SIGNAL_KIND);
+    load_native_function(mem, signal::SIGNAL_KIND_PARENT);
+    load_native_function(mem, signal::IS_SIGNAL_KIND_OF);
+    load_native_function(mem, signal::INVOKE_RESTART);
+    load_native_function(mem, signal::OFFER_RESTARTS);
     load_native_function(mem, read::READ);
+    load_native_function(mem, read::MAKE_READER);
+    load_native_function(mem, read::READER_FEED);
+    load_native_function(mem, read::CHECK_SYNTAX);
+    load_native_function(mem, read::SET_READER_MACRO);
+    load_native_function(mem, read::GET_READER_MACRO);
+    load_native_function(mem, read::REMOVE_READER_MACRO);
     load_native_function(mem, eval::MAKE_TRAP);
     load_native_function(mem, eval::MAKE_FUNCTION);
+    load_native_function(mem, eval::BIND_PATTERN);
+    load_native_function(mem, eval::BIND_ARGUMENTS);
     load_native_function(mem, eval::CALL_NATIVE_FUNCTION);
     load_native_function(mem, eval::MACROEXPAND);
+    load_native_function(mem, eval::MACROEXPAND_1);
+    load_native_function(mem, eval::MACROEXPAND_TRACE);
     load_native_function(mem, eval::EVAL);
     load_native_function(mem, eval::LOAD_ALL);
+    load_native_function(mem, syntax::EXPAND_SYNTAX_RULES);
     load_native_function(mem, print::PRINT);
     load_native_function(mem, numbers::ADD);
     load_native_function(mem, numbers::SUBSTRACT);
@@ -41,10 +60,13 @@ pub fn load_native_functions(mem: &mut Memory) {
     load_native_function(mem, reflection::DESTRUCTURE_FUNCTION);
     load_native_function(mem, reflection::TYPE_OF);
     load_native_function(mem, reflection::GET_METADATA);
+    load_native_function(mem, reflection::SOURCE_EXCERPT);
     load_native_function(mem, debug::SEND);
     load_native_function(mem, debug::RECEIVE);
     load_native_function(mem, io::INPUT_FILE);
+    load_native_function(mem, io::ABSOLUTE_PATH);
     load_native_function(mem, io::OUTPUT_FILE);
+    load_native_function(mem, io::CAPTURE_OUTPUT);
     load_native_function(mem, misc::GENSYM);
     load_native_function(mem, misc::EQUAL);
 
@@ -57,6 +79,7 @@ fn load_native_function(mem: &mut Memory, nfmd: NativeFunctionMetaData) {
         read_name:     nfmd.name.to_string(),
         location:      Location::Native,
         documentation: nfmd.documentation.to_string(),
+        span:          None,
     };
     let nf = mem.allocate_native_function(nfmd.kind, nfmd.parameters.iter().map(|s| s.to_string()).collect(), nfmd.function);
     let x  = mem.allocate_metadata(nf, md);
@@ -97,3 +120,4 @@ pub mod io;
 pub mod reflection;
 pub mod debug;
 pub mod misc;
+pub mod syntax;
@@ -1,4 +1,5 @@
 use crate::memory::*;
+use crate::util::vec_to_list;
 use crate::error_utils::*;
 use super::NativeFunctionMetaData;
 
@@ -21,7 +22,8 @@ pub fn add(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usiz
         Ok(mem.allocate_number(z))
     }
     else {
-        Err(make_error(mem, "arithmetic-overflow", ADD.name, &vec![]))
+        let arguments = vec_to_list(mem, args);
+        Err(LispError::new("arithmetic-overflow", ADD.name).detail("arguments", arguments).build(mem))
     }
 }
 
@@ -43,7 +45,8 @@ pub fn substract(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth
         Ok(mem.allocate_number(z))
     }
     else {
-        Err(make_error(mem, "arithmetic-overflow", SUBSTRACT.name, &vec![]))
+        let arguments = vec_to_list(mem, args);
+        Err(LispError::new("arithmetic-overflow", SUBSTRACT.name).detail("arguments", arguments).build(mem))
     }
 }
 
@@ -65,7 +68,8 @@ pub fn multiply(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth:
         Ok(mem.allocate_number(z))
     }
     else {
-        Err(make_error(mem, "arithmetic-overflow", MULTIPLY.name, &vec![]))
+        let arguments = vec_to_list(mem, args);
+        Err(LispError::new("arithmetic-overflow", MULTIPLY.name).detail("arguments", arguments).build(mem))
     }
 }
 
@@ -84,7 +88,8 @@ pub fn divide(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: u
     validate_args!(mem, DIVIDE.name, args, (let x: TypeLabel::Number), (let y: TypeLabel::Number));
 
     if *y == 0 {
-        Err(make_error(mem, "divide-by-zero", DIVIDE.name, &vec![]))
+        let arguments = vec_to_list(mem, args);
+        Err(LispError::new("divide-by-zero", DIVIDE.name).detail("arguments", arguments).build(mem))
     }
     else {
         Ok(mem.allocate_number(*x / *y))
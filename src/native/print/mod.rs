@@ -3,6 +3,7 @@ use crate::memory::*;
 use crate::error_utils::*;
 use crate::config;
 use super::NativeFunctionMetaData;
+use super::read::CHARACTER_NAMES;
 
 
 fn print_atom(mem: &mut Memory, atom: GcRef) -> GcRef {
@@ -21,7 +22,17 @@ fn print_atom(mem: &mut Memory, atom: GcRef) -> GcRef {
                 '\r' => "\\r".to_string(),
                 ' '  => "\\s".to_string(),
                 '\\' => "\\\\".to_string(),
-                _    => format!("{x}"),
+                c    => {
+                    if let Some((name, _)) = CHARACTER_NAMES.iter().find(|(_, y)| y == c) {
+                        format!("\\{name}")
+                    }
+                    else if c.is_control() || c.is_whitespace() {
+                        unicode_escape(*c)
+                    }
+                    else {
+                        format!("{c}")
+                    }
+                },
             };
             string_to_list(mem, &format!("%{y}"))
         },
@@ -36,15 +47,29 @@ fn print_atom(mem: &mut Memory, atom: GcRef) -> GcRef {
     }
 }
 
+/// The `\u{XXXX}` escape of `c`, understood by `read` in both character and string literals.
+fn unicode_escape(c: char) -> String {
+    format!("\\u{{{:X}}}", c as u32)
+}
+
 fn print_string(mem: &mut Memory, string: String) -> GcRef {
-    let backslash = mem.allocate_character('\\');
     let mut result = string_to_list(mem, "\"");
     for c in string.chars().rev() {
-        let character = mem.allocate_character(c);
-        result = mem.allocate_cons(character, result);
-        if c == '"' {
-            result = mem.allocate_cons(backslash.clone(), result);
-        }
+        // newlines and tabs are printed as they are, `read` accepts them in string literals
+        let escaped =
+        match c {
+            '"'                                       => "\\\"".to_string(),
+            '\\'                                      => "\\\\".to_string(),
+            '\r'                                      => "\\r".to_string(),
+            c if c.is_control() && c != '\n' && c != '\t' => unicode_escape(c),
+            c                                         => {
+                let character = mem.allocate_character(c);
+                result = mem.allocate_cons(character, result);
+                continue;
+            },
+        };
+        let escaped = string_to_list(mem, &escaped);
+        result = append_lists(mem, escaped, result).unwrap();
     }
 
     let quote = mem.allocate_character('"');
@@ -69,7 +94,7 @@ fn print_list(mem: &mut Memory, list: Vec<GcRef>) -> GcRef {
 
 fn print_internal(mem: &mut Memory, expression: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
     if recursion_depth > config::MAX_RECURSION_DEPTH {
-        return Err(make_error(mem, "stackoverflow", PRINT.name, &vec![]));
+        return Err(LispError::new("stackoverflow", PRINT.name).build(mem));
     }
 
     if expression.is_nil() {
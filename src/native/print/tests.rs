@@ -32,6 +32,28 @@ fn print_character() {
     assert_eq!(s, "%A");
 }
 
+#[test]
+fn print_escaped_character() {
+    let mut mem = Memory::new();
+
+    for (c, printed) in [('\0', "%\\nul"), ('\x07', "%\\u{7}"), ('\u{A0}', "%\\u{A0}"), ('\x7f', "%\\delete")] {
+        let x = mem.allocate_character(c);
+        let p = print(&mut mem, &[x], GcRef::nil(), 0);
+        let s = list_to_string(p.ok().unwrap()).unwrap();
+        assert_eq!(s, printed);
+    }
+}
+
+#[test]
+fn print_escaped_string() {
+    let mut mem = Memory::new();
+
+    let x = string_to_list(&mut mem, "a\\b\"c\r\x01\n");
+    let p = print(&mut mem, &[x], GcRef::nil(), 0);
+    let s = list_to_string(p.ok().unwrap()).unwrap();
+    assert_eq!(s, "\"a\\\\b\\\"c\\r\\u{1}\n\"");
+}
+
 #[test]
 fn print_symbol() {
     let mut mem = Memory::new();
@@ -1,12 +1,15 @@
 use crate::memory::*;
 use crate::metadata::*;
 use crate::util::*;
-use crate::native::list::make_plist;
+use crate::native::list::{make_plist, property};
+use crate::native::eval::eval;
+use crate::native::reflection::location_details;
 use crate::error_utils::*;
 use crate::config;
 use super::NativeFunctionMetaData;
 use unicode_segmentation::UnicodeSegmentation;
 use std::path::PathBuf;
+use std::collections::HashSet;
 use std::iter::Peekable;
 
 
@@ -19,12 +22,18 @@ enum TokenValue {
     Symbol(String),
     String(String),
     Quote,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+    ReaderMacro(char),
+    DatumComment,
 }
 
 
 struct Token {
     value: TokenValue,
     location: Location,
+    span: Span,
 }
 
 
@@ -33,11 +42,12 @@ struct StringWithPosition {
     string: GcRef,
     line: usize,
     column: usize,
+    offset: usize, // in bytes, from the beginning of the input of `read`
 }
 
 impl StringWithPosition {
-    fn new(string: GcRef, line: usize, column: usize) -> Self {
-        Self{ string, line, column }
+    fn new(string: GcRef, line: usize, column: usize, offset: usize) -> Self {
+        Self{ string, line, column, offset }
     }
 }
 
@@ -48,9 +58,9 @@ struct TokenAndRest {
 }
 
 impl TokenAndRest {
-    fn new(value: TokenValue, location: Location, rest: StringWithPosition) -> Self {
+    fn new(value: TokenValue, location: Location, span: Span, rest: StringWithPosition) -> Self {
         Self {
-            token: Token { value, location },
+            token: Token { value, location, span },
             rest,
         }
     }
@@ -61,8 +71,10 @@ impl TokenAndRest {
 enum TokenIteratorStatus {
     WhiteSpace,
     Comment,
+    BlockComment,
     Character,
     Number,
+    CharacterCode,
     Symbol,
     SymbolOrNumber,
     StringNormal,
@@ -74,18 +86,71 @@ enum ReadError {
     InvalidString,
     Incomplete,
     Nothing,
-    Error{ msg: String, location: Location, rest: StringWithPosition },
+    Error{ msg: String, location: Location, rest: StringWithPosition, hint: Option<String> },
+    Reported(GcRef), // the error returned by a reader macro, already formatted like the result of `read`
+    Signal(GcRef),
 }
 
 
 struct TokenIterator {
     input: Peekable<StringIterator>,
     location: Location,
+    offset: usize, // the byte offset of the next character
+    reader_macros: HashSet<char>,
+    open_at_end: bool, // whether more input could change how the end of the input is read (e.g. `ab` + `c`)
 }
 
 impl TokenIterator {
-    fn new(input: GcRef, location: Location) -> Self {
-        Self{ input: StringIterator::new(input).peekable(), location }
+    fn new(input: &StringWithPosition, location: Location, reader_macros: HashSet<char>) -> Self {
+        Self{ input: StringIterator::new(input.string.clone()).peekable(), location, offset: input.offset, reader_macros, open_at_end: false }
+    }
+
+    /// Move past `ch`, the character just taken from `input`.
+    fn step(&mut self, ch: char) {
+        if ch == '\n' {
+            self.location.step_line();
+        }
+        else {
+            self.location.step_column();
+        }
+        self.offset += ch.len_utf8();
+    }
+
+    /// The position of `ch`, the character stepped over last.
+    fn position_of(&self, ch: char) -> Position {
+        Position{ line: self.location.get_line().unwrap(), column: self.location.get_column().unwrap(), offset: self.offset - ch.len_utf8() }
+    }
+
+    /// The input following the character stepped over last, `r`.
+    fn rest(&self, r: GcRef) -> StringWithPosition {
+        StringWithPosition::new(r, self.location.get_line().unwrap(), self.location.get_column().unwrap() + 1, self.offset)
+    }
+
+    /// Read the `{XXXX}` part of a `\u{XXXX}` escape in a string literal (`\u` is already consumed).
+    fn unicode_escape(&mut self) -> Result<char, ReadError> {
+        let mut digits = String::new();
+        let mut opened = false;
+
+        loop {
+            let (ch, r) = match self.input.next() {
+                None          => return Err(ReadError::Incomplete),
+                Some(None)    => return Err(ReadError::InvalidString),
+                Some(Some(x)) => x,
+            };
+            self.step(ch);
+            let rest = self.rest(r);
+
+            match ch {
+                '{' if !opened                          => opened = true,
+                c if opened && c.is_ascii_hexdigit()    => digits.push(c),
+                '}' if opened                           => {
+                    return unicode_character(&digits).ok_or_else(|| ReadError::Error{ msg: format!("invalid unicode escape: '\\u{{{digits}}}'"), location: self.location.clone(), rest, hint: None });
+                },
+                _                                       => {
+                    return Err(ReadError::Error{ msg: format!("invalid unicode escape, expected '\\u{{XXXX}}' where XXXX are hexadecimal digits, found: '{ch}'"), location: self.location.clone(), rest, hint: None });
+                },
+            }
+        }
     }
 }
 
@@ -95,21 +160,20 @@ impl Iterator for TokenIterator {
     fn next(&mut self) -> Option<Self::Item> {
         use TokenIteratorStatus::*;
 
-        let mut status = WhiteSpace;
-        let mut buffer = vec![];
+        let mut status        = WhiteSpace;
+        let mut buffer        = vec![];
+        let mut comment_depth = 0;
+        let mut code_start    = 0; // in a character code literal, where the character begins in `buffer`
         let mut beginning_location = self.location.clone();
+        let mut beginning_position = Position::default();
 
         while let Some(maybe_char_and_rest) = self.input.next() {
             let (ch, r) = if let Some(x) = maybe_char_and_rest {x} else {return Some(Err(ReadError::InvalidString));};
 
-            if ch == '\n' {
-                self.location.step_line();
-            }
-            else {
-                self.location.step_column();
-            }
+            self.step(ch);
 
-            let rest = StringWithPosition::new(r, self.location.get_line().unwrap(), self.location.get_column().unwrap() + 1);
+            let rest     = self.rest(r);
+            let position = self.position_of(ch);
 
 
             if status == Comment {
@@ -118,6 +182,26 @@ impl Iterator for TokenIterator {
                 }
                 continue;
             }
+            if status == BlockComment {
+                // block comments nest: `#|` opens one more level, `|#` closes one
+                match (ch, self.input.peek()) {
+                    ('#', Some(Some(('|', _)))) => {
+                        self.input.next();
+                        self.step('|');
+                        comment_depth += 1;
+                    },
+                    ('|', Some(Some(('#', _)))) => {
+                        self.input.next();
+                        self.step('#');
+                        comment_depth -= 1;
+                        if comment_depth == 0 {
+                            status = WhiteSpace;
+                        }
+                    },
+                    _ => {},
+                }
+                continue;
+            }
             if status == StringNormal {
                 if ch != '"' && ch != '\\' {
                     buffer.push(ch);
@@ -131,36 +215,92 @@ impl Iterator for TokenIterator {
                     'r'  => buffer.push('\r'),
                     't'  => buffer.push('\t'),
                     '\\' => buffer.push('\\'),
-                    _    => return Some(Err(ReadError::Error{ msg: format!("'{ch}' is not a valid escape character in a string literal"), location: self.location.clone(), rest })),
+                    'u'  => match self.unicode_escape() {
+                        Ok(c)    => buffer.push(c),
+                        Err(err) => return Some(Err(err)),
+                    },
+                    _    => return Some(Err(ReadError::Error{ msg: format!("'{ch}' is not a valid escape character in a string literal"), location: self.location.clone(), rest, hint: None })),
                 }
                 status = StringNormal;
                 continue;
             }
 
             match ch {
-                c if c.is_whitespace() || c == ',' => {
+                '`' if status == Character && buffer.is_empty() => {
+                    // a backquote ends an atom, but `%`` is still the backquote character
+                    buffer.push(ch);
+                },
+                c if status == CharacterCode && buffer.len() == code_start && !c.is_whitespace() && c != '\\' => {
+                    // any character can follow `0c`, even the ones that would otherwise end an atom
+                    buffer.push(c);
+                },
+                '#' if status == WhiteSpace && matches!(self.input.peek(), Some(Some(('|', _)))) => {
+                    self.input.next();
+                    self.step('|');
+                    status        = BlockComment;
+                    comment_depth = 1;
+                },
+                '#' if status == WhiteSpace && matches!(self.input.peek(), Some(Some((';', _)))) => {
+                    let location = self.location.clone();
+                    let (_, r)   = self.input.next().unwrap().unwrap();
+                    self.step(';');
+                    let span     = Span{ start: position, end: self.position_of(';') };
+                    return Some(Ok(TokenAndRest::new(TokenValue::DatumComment, location, span, self.rest(r))));
+                },
+                c if status == WhiteSpace && self.reader_macros.contains(&c) => {
+                    return Some(Ok(TokenAndRest::new(TokenValue::ReaderMacro(c), self.location.clone(), Span{ start: position, end: position }, rest)));
+                },
+                c if c.is_whitespace() => {
                     status = WhiteSpace;
                 },
+                ',' => {
+                    // For backwards compatibility a comma that isn't followed by an expression is still whitespace,
+                    // so `(let (a 1, b 2) ...)` keeps working; `,x` and `,@x` are unquotes.
+                    match self.input.peek() {
+                        Some(Some(('@', _))) => {
+                            let location = self.location.clone();
+                            let (_, r)   = self.input.next().unwrap().unwrap();
+                            self.step('@');
+                            let span     = Span{ start: position, end: self.position_of('@') };
+                            return Some(Ok(TokenAndRest::new(TokenValue::UnquoteSplicing, location, span, self.rest(r))));
+                        },
+                        Some(Some((c, _))) if !c.is_whitespace() && !matches!(c, ')' | ',' | ';') => {
+                            return Some(Ok(TokenAndRest::new(TokenValue::Unquote, self.location.clone(), Span{ start: position, end: position }, rest)));
+                        },
+                        Some(None) => {
+                            return Some(Err(ReadError::InvalidString));
+                        },
+                        next => {
+                            self.open_at_end = next.is_none();
+                            status           = WhiteSpace;
+                        },
+                    }
+                },
+                '`' => {
+                    return Some(Ok(TokenAndRest::new(TokenValue::Quasiquote, self.location.clone(), Span{ start: position, end: position }, rest)));
+                },
                 ';' => {
                     status = Comment;
                 },
                 '\'' => {
-                    return Some(Ok(TokenAndRest::new(TokenValue::Quote, self.location.clone(), rest)));
+                    return Some(Ok(TokenAndRest::new(TokenValue::Quote, self.location.clone(), Span{ start: position, end: position }, rest)));
                 },
                 '(' => {
-                    return Some(Ok(TokenAndRest::new(TokenValue::OpenParen, self.location.clone(), rest)));
+                    return Some(Ok(TokenAndRest::new(TokenValue::OpenParen, self.location.clone(), Span{ start: position, end: position }, rest)));
                 },
                 ')' => {
-                    return Some(Ok(TokenAndRest::new(TokenValue::CloseParen, self.location.clone(), rest)));
+                    return Some(Ok(TokenAndRest::new(TokenValue::CloseParen, self.location.clone(), Span{ start: position, end: position }, rest)));
                 },
                 '"' => {
                     match status {
                         StringNormal => {
-                            return Some(Ok(TokenAndRest::new(TokenValue::String(buffer.iter().collect()), beginning_location, rest)));
+                            let span = Span{ start: beginning_position, end: position };
+                            return Some(Ok(TokenAndRest::new(TokenValue::String(buffer.iter().collect()), beginning_location, span, rest)));
                         }
                         _ => {
                             status = StringNormal;
                             beginning_location = self.location.clone();
+                            beginning_position = position;
                         }
                     }
                 },
@@ -169,11 +309,11 @@ impl Iterator for TokenIterator {
                         StringNormal => {
                             status = StringEscape;
                         },
-                        Character => {
+                        Character | CharacterCode => {
                             buffer.push(ch);
                         },
                         _ => {
-                            return Some(Err(ReadError::Error{ msg: format!("unexpected character: '\\'"), location: self.location.clone(), rest }));
+                            return Some(Err(ReadError::Error{ msg: format!("unexpected character: '\\'"), location: self.location.clone(), rest, hint: None }));
                         }
                     }
                 },
@@ -181,6 +321,7 @@ impl Iterator for TokenIterator {
                     if status == WhiteSpace {
                         status = Character;
                         beginning_location = self.location.clone();
+                        beginning_position = position;
                     }
                     else {
                         buffer.push(ch);
@@ -190,6 +331,7 @@ impl Iterator for TokenIterator {
                     if status == WhiteSpace {
                         status = SymbolOrNumber;
                         beginning_location = self.location.clone();
+                        beginning_position = position;
                     }
                     buffer.push(ch);
                 },
@@ -201,6 +343,7 @@ impl Iterator for TokenIterator {
                         WhiteSpace => {
                             status = Number;
                             beginning_location = self.location.clone();
+                            beginning_position = position;
                         },
                         _ => {},
                     }
@@ -211,12 +354,18 @@ impl Iterator for TokenIterator {
                         WhiteSpace => {
                             status = Symbol;
                             beginning_location = self.location.clone();
+                            beginning_position = position;
                         },
                         SymbolOrNumber => {
                             status = Symbol;
                         },
-                        Number => {
-                            return Some(Err(ReadError::Error{ msg: format!("unexpected character in number literal: '{ch}'"), location: self.location.clone(), rest }));
+                        Number if ch == 'c' && matches!(buffer.as_slice(), ['0'] | ['+', '0'] | ['-', '0']) => {
+                            status     = CharacterCode;
+                            code_start = buffer.len() + 1;
+                        },
+                        Number if !ch.is_ascii_alphanumeric() && ch != '_' => {
+                            // letters are validated by `build_number`, they can be digits (e.g. `0xff`)
+                            return Some(Err(ReadError::Error{ msg: format!("unexpected character in number literal: '{ch}'"), location: self.location.clone(), rest, hint: None }));
                         },
                         _ => {},
                     }
@@ -226,11 +375,15 @@ impl Iterator for TokenIterator {
 
             if buffer.len() > 0 {
                 let atom_ending = if let Some(x) = is_atom_ending(&self.input.peek()) {x} else {return Some(Err(ReadError::InvalidString));};
-                if atom_ending {
+                let code_ending = status != CharacterCode || buffer.len() > code_start || matches!(self.input.peek(), None | Some(Some((' ' | '\t' | '\n' | '\r', _))));
+                if atom_ending && code_ending {
+                    self.open_at_end = self.input.peek().is_none() && !matches!(status, StringNormal | StringEscape);
+                    let span         = Span{ start: beginning_position, end: position };
                     match status {
-                        Character                   => return Some(build_character(&buffer, self.location.clone(), rest.clone()).map(|x| TokenAndRest::new(TokenValue::Character(x), beginning_location, rest))),
-                        Number                      => return Some(build_number(   &buffer, self.location.clone(), rest.clone()).map(|x| TokenAndRest::new(TokenValue::Number(x),    beginning_location, rest))),
-                        Symbol | SymbolOrNumber     => return Some(build_symbol(   &buffer, self.location.clone(), rest.clone()).map(|x| TokenAndRest::new(TokenValue::Symbol(x),    beginning_location, rest))),
+                        Character                   => return Some(build_character(&buffer, self.location.clone(), rest.clone()).map(|x| TokenAndRest::new(TokenValue::Character(x), beginning_location, span, rest))),
+                        Number                      => return Some(build_number(   &buffer, self.location.clone(), rest.clone()).map(|x| TokenAndRest::new(TokenValue::Number(x),    beginning_location, span, rest))),
+                        CharacterCode               => return Some(build_character_code(&buffer, code_start, self.location.clone(), rest.clone()).map(|x| TokenAndRest::new(TokenValue::Number(x), beginning_location, span, rest))),
+                        Symbol | SymbolOrNumber     => return Some(build_symbol(   &buffer, self.location.clone(), rest.clone()).map(|x| TokenAndRest::new(TokenValue::Symbol(x),    beginning_location, span, rest))),
                         StringNormal | StringEscape => { /* don't do anything */ },
                         _                           => unreachable!(),
                     }
@@ -238,10 +391,11 @@ impl Iterator for TokenIterator {
             }
         }
         
-        if buffer.len() > 0 {
+        if buffer.len() > 0 || !matches!(status, WhiteSpace | Comment) {
             Some(Err(ReadError::Incomplete))
         }
         else {
+            self.open_at_end = self.open_at_end || status == Comment;
             None
         }
     }
@@ -253,7 +407,7 @@ fn is_atom_ending(ch: &Option<&Option<(char, GcRef)>>) -> Option<bool> { // None
         None => Some(true),
         Some(Some((c, _))) => {
             match c {
-                ';' | '(' | ')' | '"' | '\'' | ',' => Some(true),
+                ';' | '(' | ')' | '"' | '\'' | ',' | '`' => Some(true),
                 k if k.is_whitespace()             => Some(true),
                 _                                  => Some(false),
             } 
@@ -263,16 +417,44 @@ fn is_atom_ending(ch: &Option<&Option<(char, GcRef)>>) -> Option<bool> { // None
 }
 
 
+/// Names that can be used in character literals, e.g. `%\\newline`.
+pub const CHARACTER_NAMES: &[(&str, char)] = &[
+    ("nul",       '\0'),
+    ("backspace", '\x08'),
+    ("tab",       '\t'),
+    ("newline",   '\n'),
+    ("return",    '\r'),
+    ("escape",    '\x1b'),
+    ("space",     ' '),
+    ("delete",    '\x7f'),
+];
+
+
+/// The character whose code is `digits` in hexadecimal.
+fn unicode_character(digits: &str) -> Option<char> {
+    if digits.is_empty() || digits.len() > 6 {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok().and_then(char::from_u32)
+}
+
+
 fn build_character(chars: &[char], location: Location, rest: StringWithPosition) -> Result<char, ReadError> {
     match chars.iter().collect::<String>().as_str() {
-        ""     => Err(ReadError::Error{ msg: format!("invalid character: '%' (empty literal)"), location, rest }),
+        ""     => Err(ReadError::Error{ msg: format!("invalid character: '%' (empty literal)"), location, rest, hint: None }),
         "\\n"  => Ok('\n'),
         "\\t"  => Ok('\t'),
         "\\s"  => Ok(' '),
         "\\r"  => Ok('\r'),
         "\\\\" => Ok('\\'),
+        c if c.starts_with("\\u{") && c.ends_with('}') => {
+            unicode_character(&c[3..c.len() - 1]).ok_or(ReadError::Error{ msg: format!("invalid character: '%{c}' (not a valid unicode escape)"), location, rest, hint: None })
+        },
+        c if c.starts_with('\\') && CHARACTER_NAMES.iter().any(|(name, _)| *name == &c[1..]) => {
+            Ok(CHARACTER_NAMES.iter().find(|(name, _)| *name == &c[1..]).unwrap().1)
+        },
         c if c.graphemes(true).count() == 1 => Ok(c.chars().next().unwrap()),
-        c      => Err(ReadError::Error{ msg: format!("invalid character: '%{c}'"), location, rest }),
+        c      => Err(ReadError::Error{ msg: format!("invalid character: '%{c}'"), location, rest, hint: None }),
     }
 }
 
@@ -282,13 +464,49 @@ fn build_symbol(chars: &[char], _location: Location, _rest: StringWithPosition)
 }
 
 
+/// Parse an integer literal: an optional sign, an optional radix prefix (`0x`, `0o` or `0b`),
+/// then digits, which can be separated by single `_`s (e.g. `1_000_000`).
 fn build_number(chars: &[char], location: Location, rest: StringWithPosition) -> Result<i64, ReadError> {
-    chars.iter().collect::<String>().parse::<i64>().map_err(|err| ReadError::Error{ msg: format!("invalid number: '{err}'"), location, rest })
+    let literal          = chars.iter().collect::<String>();
+    let error            = |msg: String| ReadError::Error{ msg, location: location.clone(), rest: rest.clone(), hint: None };
+    let (sign, unsigned) = match chars.first() {
+        Some('-') => ("-", &chars[1..]),
+        Some('+') => ("",  &chars[1..]),
+        _         => ("",  chars),
+    };
+    let (radix, digits)  = match unsigned {
+        ['0', 'x' | 'X', d @ ..] => (16, d),
+        ['0', 'o' | 'O', d @ ..] => (8,  d),
+        ['0', 'b' | 'B', d @ ..] => (2,  d),
+        d                        => (10, d),
+    };
+
+    if digits.is_empty() {
+        return Err(error(format!("missing digits in number literal: '{literal}'")));
+    }
+    if digits.first() == Some(&'_') || digits.last() == Some(&'_') || digits.windows(2).any(|w| w == ['_', '_']) {
+        return Err(error(format!("misplaced digit separator in number literal: '{literal}'")));
+    }
+    if let Some(c) = digits.iter().find(|c| **c != '_' && !c.is_digit(radix)) {
+        return Err(error(format!("unexpected character in number literal: '{c}'")));
+    }
+
+    let digits = digits.iter().filter(|c| **c != '_').collect::<String>();
+    i64::from_str_radix(&format!("{sign}{digits}"), radix).map_err(|_| error(format!("number literal out of range: '{literal}' (numbers must be between {} and {})", i64::MIN, i64::MAX)))
+}
+
+
+/// Parse a character code literal, i.e. `0c` followed by a character (as in character literals), e.g. `0ca` is 97.
+fn build_character_code(chars: &[char], code_start: usize, location: Location, rest: StringWithPosition) -> Result<i64, ReadError> {
+    let code = build_character(&chars[code_start..], location.clone(), rest.clone()).map_err(|_| {
+        ReadError::Error{ msg: format!("invalid character code literal: '{}'", chars.iter().collect::<String>()), location, rest, hint: None }
+    })? as i64;
+    Ok(if chars[0] == '-' {-code} else {code})
 }
 
 
 
-fn format_error(mem: &mut Memory, location: Location, msg: String, rest: StringWithPosition) -> GcRef {
+fn format_error(mem: &mut Memory, location: Location, msg: String, hint: Option<String>, rest: StringWithPosition) -> GcRef {
     let error_sym = mem.symbol_for("error");
     let error_msg = string_to_list(mem, &msg);
     let file;
@@ -307,171 +525,330 @@ fn format_error(mem: &mut Memory, location: Location, msg: String, rest: StringW
             column = mem.allocate_number(cn as i64);
         },
         Location::File{ path, line: ln, column: cn } => {
-            file = string_to_proper_list(mem, &path.into_os_string().into_string().unwrap());
+            file = string_to_list(mem, &path.into_os_string().into_string().unwrap());
             line = mem.allocate_number(ln as i64);
             column = mem.allocate_number(cn as i64);
         },
     }
     let error_loc = make_plist(mem, &vec![("file", file), ("line", line), ("column", column)]);
-    let error     = make_plist(mem, &vec![("location", error_loc), ("message", error_msg)]);
+    let mut error = vec![("location", error_loc), ("message", error_msg)];
+    if let Some(hint) = hint {
+        error.push(("hint", string_to_list(mem, &hint)));
+    }
+    let error     = make_plist(mem, &error);
     let ln        = mem.allocate_number(rest.line as i64);
     let cn        = mem.allocate_number(rest.column as i64);
-    make_plist(mem, &vec![("status", error_sym), ("error", error), ("rest", rest.string), ("line", ln), ("column", cn)])
+    let on        = mem.allocate_number(rest.offset as i64);
+    make_plist(mem, &[("status", error_sym), ("error", error), ("rest", rest.string), ("line", ln), ("column", cn), ("offset", on)])
+}
+
+
+enum Prefix {
+    Wrap(&'static str), // `'`, `` ` ``, `,` and `,@`
+    DatumComment,       // `#;`
+}
+
+
+/// Wrap `x` in the forms of the prefix tokens that preceded it, innermost last, consuming them.
+///
+/// Return `None` when a datum comment swallows `x`; the prefixes before the datum comment are left in
+/// `prefixes`, so they apply to the next expression instead.
+fn wrap_in_prefixes(mem: &mut Memory, x: GcRef, prefixes: &mut Vec<Prefix>) -> Option<GcRef> {
+    let mut x = x;
+    while let Some(prefix) = prefixes.pop() {
+        match prefix {
+            Prefix::Wrap(name)   => {
+                let vec = vec![mem.symbol_for(name), x];
+                x = vec_to_list(mem, &vec);
+            },
+            Prefix::DatumComment => return None,
+        }
+    }
+    Some(x)
+}
+
+
+/// The byte offset of `tail`, which is expected to be a part of `input`; the offset of `input` if it isn't.
+fn offset_of(input: &StringWithPosition, tail: &GcRef) -> usize {
+    let mut offset = input.offset;
+    let mut cursor = input.string.clone();
+
+    while let Some(PrimitiveValue::Cons(cons)) = cursor.get() {
+        if cursor.is_same_object(tail) {
+            return offset;
+        }
+        let car = cons.get_car();
+        if let Some(PrimitiveValue::Character(c)) = car.get() {
+            offset += c.len_utf8();
+        }
+        cursor = cons.get_cdr();
+    }
+
+    if tail.is_nil() {offset} else {input.offset}
+}
+
+
+enum ReaderMacroResult {
+    Expression(GcRef, StringWithPosition),
+    Skip(StringWithPosition),
+}
+
+
+/// Call the reader function registered for `character` on the input following it.
+///
+/// The function receives the rest of the input, the source, and the line and column where the rest begins,
+/// so it can hand the input back to `read` and get correct locations.
+/// It returns a property list like the one returned by `read`; `nothing` means that the input it consumed
+/// (up to `rest`, or all of it) doesn't contain an expression, e.g. because it was a comment.
+fn call_reader_macro(mem: &mut Memory, character: char, location: &Location, rest: StringWithPosition, recursion_depth: usize) -> Result<ReaderMacroResult, ReadError> {
+    let function = mem.get_reader_macro(character).expect("reader macro character without a function");
+    let quote    = mem.symbol_for("quote");
+    let input    = vec_to_list(mem, &[quote.clone(), rest.string.clone()]);
+    let source   = location_details(mem, location)[0].1.clone();
+    let source   = vec_to_list(mem, &[quote, source]);
+    let line     = mem.allocate_number(rest.line as i64);
+    let column   = mem.allocate_number(rest.column as i64);
+    let call     = vec_to_list(mem, &[function, input, source, line, column]);
+    let result   = eval(mem, &[call], GcRef::nil(), recursion_depth + 1).map_err(ReadError::Signal)?;
+
+    let invalid_result = |mem: &mut Memory, result: GcRef| {
+        let details = vec![("character", mem.allocate_character(character)), ("result", result)];
+        ReadError::Signal(LispError::new("invalid-reader-macro-result", READ.name).details(&details).build(mem))
+    };
+
+    let status   = if let Some(x) = property(mem, "status", result.clone()) {x} else {return Err(invalid_result(mem, result))};
+    let new_rest =
+    match (property(mem, "rest", result.clone()), property(mem, "line", result.clone()), property(mem, "column", result.clone())) {
+        (Some(string), Some(line), Some(column)) if !line.is_nil() || !column.is_nil() => {
+            match (line.get(), column.get()) {
+                (Some(PrimitiveValue::Number(l)), Some(PrimitiveValue::Number(c))) if *l > 0 && *c > 0 => {
+                    let offset = offset_of(&rest, &string);
+                    Some(StringWithPosition::new(string, *l as usize, *c as usize, offset))
+                },
+                _                                                                                      => return Err(invalid_result(mem, result)),
+            }
+        },
+        _ => None,
+    };
+
+    if symbol_eq!(status, mem.symbol_for("ok")) {
+        match (property(mem, "result", result.clone()), new_rest) {
+            (Some(x), Some(new_rest)) => Ok(ReaderMacroResult::Expression(x, new_rest)),
+            _                         => Err(invalid_result(mem, result)),
+        }
+    }
+    else if symbol_eq!(status, mem.symbol_for("nothing")) {
+        Ok(ReaderMacroResult::Skip(new_rest.unwrap_or(StringWithPosition{ string: GcRef::nil(), ..rest })))
+    }
+    else if symbol_eq!(status, mem.symbol_for("incomplete")) {
+        Err(ReadError::Incomplete)
+    }
+    else if symbol_eq!(status, mem.symbol_for("invalid")) {
+        Err(ReadError::InvalidString)
+    }
+    else if symbol_eq!(status, mem.symbol_for("error")) {
+        Err(ReadError::Reported(result))
+    }
+    else {
+        Err(invalid_result(mem, result))
+    }
 }
 
 
-fn read_internal(mem: &mut Memory, input: GcRef, location: Location) -> Result<(GcRef, StringWithPosition), ReadError> {
-    let mut stack = vec![];
-    let mut quoted = false;
+struct OpenList {
+    reversed: GcRef,       // the elements read so far, in reverse order
+    prefixes: Vec<Prefix>, // the prefixes before the `(`
+    location: Location,    // the location of the `(`
+    start: Position,       // the position of the `(`, where the span of the list begins
+    top_level_paren: Option<Location>, // the first `(` at the beginning of a line directly inside the list
+}
+
 
-    for maybe_token_and_rest in TokenIterator::new(input, location) {
-        let TokenAndRest{token, rest} = maybe_token_and_rest?;
+/// The state of the reader between two tokens, so that reading can continue when more input arrives.
+struct Parser {
+    stack: Vec<OpenList>,
+    prefixes: Vec<Prefix>,
+    resume: StringWithPosition, // the input following the last token processed
+    end_of_input: bool,         // when false, tokens that may continue in the next input are left unread
+    top_level_parens: bool,     // when true, a `(` at the beginning of a line inside an expression is remembered for the hint of an unclosed paren
+}
 
-        let result;
+impl Parser {
+    fn new(input: StringWithPosition, end_of_input: bool) -> Self {
+        Self{ stack: vec![], prefixes: vec![], resume: input, end_of_input, top_level_parens: false }
+    }
+}
+
+
+fn read_internal(mem: &mut Memory, input: GcRef, location: Location, offset: usize, recursion_depth: usize) -> Result<(GcRef, StringWithPosition), ReadError> {
+    let start      = StringWithPosition::new(input, location.get_line().unwrap(), location.get_column().unwrap() + 1, offset);
+    let mut parser = Parser::new(start, true);
+    parse(mem, &mut parser, &location, recursion_depth)
+}
+
+
+/// Read the next expression from `parser.resume`.
+///
+/// On `Incomplete`, `parser` holds everything needed to continue once more input is appended to `parser.resume`.
+/// `location` is only used for its source, positions come from `parser.resume`.
+fn parse(mem: &mut Memory, parser: &mut Parser, location: &Location, recursion_depth: usize) -> Result<(GcRef, StringWithPosition), ReadError> {
+    let mut start_location = location.clone();
+    start_location.set_position(parser.resume.line, parser.resume.column - 1);
+    let mut tokens = TokenIterator::new(&parser.resume, start_location, mem.get_reader_macro_characters());
+
+    while let Some(maybe_token_and_rest) = tokens.next() {
+        if !parser.end_of_input && tokens.open_at_end {
+            return Err(ReadError::Incomplete);
+        }
+        let TokenAndRest{token, mut rest} = maybe_token_and_rest?;
+
+        let x =
         match token.value {
-            TokenValue::Quote => {
-                quoted = true;
-                continue;
+            TokenValue::Quote | TokenValue::Quasiquote | TokenValue::Unquote | TokenValue::UnquoteSplicing => {
+                parser.prefixes.push(Prefix::Wrap(match token.value {
+                    TokenValue::Quote      => "quote",
+                    TokenValue::Quasiquote => "quasiquote",
+                    TokenValue::Unquote    => "unquote",
+                    _                      => "unquote-splicing",
+                }));
+                None
+            },
+            TokenValue::DatumComment => {
+                parser.prefixes.push(Prefix::DatumComment);
+                None
             },
             TokenValue::OpenParen => {
-                stack.push((vec![], quoted));
-                quoted = false;
-                continue;
+                if parser.top_level_parens && token.location.get_column() == Some(1) {
+                    if let Some(open) = parser.stack.last_mut() {
+                        open.top_level_paren.get_or_insert(token.location.clone());
+                    }
+                }
+                let prefixes = std::mem::take(&mut parser.prefixes);
+                parser.stack.push(OpenList{ reversed: GcRef::nil(), prefixes, location: token.location, start: token.span.start, top_level_paren: None });
+                None
             },
             TokenValue::CloseParen => {
-                if let Some((vec, q)) = stack.pop() {
-                    let list = vec_to_list(mem, &vec);
-                    let qlist =
-                    if q {
-                        let vec = vec![mem.symbol_for("quote"), list];
-                        quoted = false;
-                        vec_to_list(mem, &vec)
-                    }
-                    else {
-                        list
-                    };
-                    if let Some((lower_vec, _)) = stack.last_mut() {
-                        lower_vec.push(qlist);
-                        continue;
+                if !parser.prefixes.is_empty() {
+                    return Err(ReadError::Error{ msg: "expected an expression before ')'".to_string(), location: token.location, rest, hint: None });
+                }
+                if let Some(open) = parser.stack.pop() {
+                    parser.prefixes = open.prefixes;
+                    let list        = reverse_list(mem, open.reversed);
+                    if list.is_nil() {
+                        Some(list)
                     }
                     else {
-                        result = qlist;
+                        let md = Metadata{ read_name: String::new(), location: open.location, documentation: String::new(), span: Some(Span{ start: open.start, end: token.span.end }) };
+                        Some(mem.allocate_metadata(list, md))
                     }
                 }
                 else {
-                    return Err(ReadError::Error{ msg: format!("too many closing parentheses"), location: token.location, rest });
+                    return Err(ReadError::Error{ msg: format!("too many closing parentheses"), location: token.location, rest, hint: None });
                 }
             },
             TokenValue::Character(c) => {
-                let md = Metadata{ read_name: format!("{c}"), location: token.location, documentation: String::new() };
+                let md = Metadata{ read_name: format!("{c}"), location: token.location, documentation: String::new(), span: Some(token.span) };
                 let v  = mem.allocate_character(c);
-                let x  = mem.allocate_metadata(v, md);
-                let y  =
-                if quoted {
-                    let vec = vec![mem.symbol_for("quote"), x];
-                    quoted = false;
-                    vec_to_list(mem, &vec)
-                }
-                else {
-                    x
-                };
-                if let Some((vec, _)) = stack.last_mut() {
-                    vec.push(y);
-                    continue;
-                }
-                else {
-                    result = y;
-                }
+                Some(mem.allocate_metadata(v, md))
             },
             TokenValue::Number(n) => {
-                let md = Metadata{ read_name: format!("{n}"), location: token.location, documentation: String::new() };
+                let md = Metadata{ read_name: format!("{n}"), location: token.location, documentation: String::new(), span: Some(token.span) };
                 let v  = mem.allocate_number(n);
-                let x  = mem.allocate_metadata(v, md);
-                let y  =
-                if quoted {
-                    let vec = vec![mem.symbol_for("quote"), x];
-                    quoted = false;
-                    vec_to_list(mem, &vec)
-                }
-                else {
-                    x
-                };
-                if let Some((vec, _)) = stack.last_mut() {
-                    vec.push(y);
-                    continue;
-                }
-                else {
-                    result = y;
-                }
+                Some(mem.allocate_metadata(v, md))
             },
             TokenValue::Symbol(s) => {
-                let md = Metadata{ read_name: format!("{s}"), location: token.location, documentation: String::new() };
+                let md = Metadata{ read_name: format!("{s}"), location: token.location, documentation: String::new(), span: Some(token.span) };
                 let v  = mem.symbol_for(s.as_str());
-                let x  = mem.allocate_metadata(v, md);
-                let y  =
-                if quoted {
-                    let vec = vec![mem.symbol_for("quote"), x];
-                    quoted = false;
-                    vec_to_list(mem, &vec)
-                }
-                else {
-                    x
-                };
-                if let Some((vec, _)) = stack.last_mut() {
-                    vec.push(y);
-                    continue;
-                }
-                else {
-                    result = y;
-                }
+                Some(mem.allocate_metadata(v, md))
             },
             TokenValue::String(s) => {
-                let md = Metadata{ read_name: s.clone(), location: token.location, documentation: String::new() };
+                let md = Metadata{ read_name: s.clone(), location: token.location, documentation: String::new(), span: Some(token.span) };
                 let v  = string_to_proper_list(mem, s.as_str());
-                let x  = mem.allocate_metadata(v, md);
-                let y  =
-                if quoted {
-                    let vec = vec![mem.symbol_for("quote"), x];
-                    quoted = false;
-                    vec_to_list(mem, &vec)
-                }
-                else {
-                    x
+                Some(mem.allocate_metadata(v, md))
+            },
+            TokenValue::ReaderMacro(c) => {
+                let result = call_reader_macro(mem, c, &token.location, rest, recursion_depth)?;
+
+                // continue tokenizing where the reader function stopped
+                rest = match &result {
+                    ReaderMacroResult::Expression(_, r) => r.clone(),
+                    ReaderMacroResult::Skip(r)          => r.clone(),
                 };
-                if let Some((vec, _)) = stack.last_mut() {
-                    vec.push(y);
-                    continue;
-                }
-                else {
-                    result = y;
+                let mut rest_location = location.clone();
+                rest_location.set_position(rest.line, rest.column - 1);
+                tokens = TokenIterator::new(&rest, rest_location, mem.get_reader_macro_characters());
+
+                match result {
+                    ReaderMacroResult::Expression(x, _) => Some(x),
+                    ReaderMacroResult::Skip(_)          => None,
                 }
             },
-        }
+        };
+
+        parser.resume = rest.clone();
+
+        let Some(x) = x else {continue};
+        let Some(x) = wrap_in_prefixes(mem, x, &mut parser.prefixes) else {continue};
 
-        if quoted {
-            let vec = vec![mem.symbol_for("quote"), result];
-            return Ok((vec_to_list(mem, &vec), rest));
+        if let Some(open) = parser.stack.last_mut() {
+            open.reversed = mem.allocate_cons(x, open.reversed.clone());
         }
         else {
-            return Ok((result, rest));
+            return Ok((x, rest));
         }
     }
 
-    if stack.len() > 0 {
+    if !parser.stack.is_empty() || !parser.prefixes.is_empty() || (!parser.end_of_input && tokens.open_at_end) {
         Err(ReadError::Incomplete)
     }
     else {
+        // only whitespace and comments are left
+        parser.resume = StringWithPosition::new(GcRef::nil(), tokens.location.get_line().unwrap(), tokens.location.get_column().unwrap() + 1, tokens.offset);
         Err(ReadError::Nothing)
     }
 }
 
 
+fn reverse_list(mem: &mut Memory, list: GcRef) -> GcRef {
+    let mut result = GcRef::nil();
+    let mut cursor = list;
+    while let Some(PrimitiveValue::Cons(cons)) = cursor.get() {
+        result = mem.allocate_cons(cons.get_car(), result);
+        cursor = cons.get_cdr();
+    }
+    result
+}
+
+
+/// Convert the `source`, `start-line` and `start-column` arguments of `read` into a location.
+fn make_location(mem: &mut Memory, function_name: &str, source: GcRef, start_line: i64, start_column: i64) -> Result<Location, GcRef> {
+    let sl = start_line         as usize;
+    let sc = (start_column - 1) as usize;
+
+    if let Some(path) = list_to_string(source.clone()) {
+        Ok(Location::File { path: PathBuf::from(path), line: sl, column: sc})
+    }
+    else if symbol_eq!(source, mem.symbol_for("prelude")) {
+        Ok(Location::Prelude { line: sl, column: sc })
+    }
+    else if symbol_eq!(source, mem.symbol_for("stdin")) {
+        Ok(Location::Stdin { line: sl, column: sc })
+    }
+    else {
+        let error_details = vec![("argument-value", source)];
+        Err(LispError::new("unknown-read-source", function_name).details(&error_details).build(mem))
+    }
+}
+
+
+
+
 pub const READ: NativeFunctionMetaData =
 NativeFunctionMetaData{
     function:      read,
     name:          "read",
     kind:          FunctionKind::Lambda,
-    parameters:    &["input", "source", "start-line", "start-column"],
+    parameters:    &["input", "&optional", "source", "start-line", "start-column", "start-offset"],
     documentation: 
 r"Converts a Lisp-style string to an AST.
 
@@ -485,44 +862,32 @@ The `status` key can have one of the following values:
  * `error`:      The input is not a valid AST, not even the beginning of one. The `error` key contains the error details.
  * `invalid`:    The input is not a valid string.
 
-Whenever there is a `rest` key, the `line` and `column` keys are also present,
-whose values are respectively the first line and column of the rest of the input.
+Whenever there is a `rest` key, the `line`, `column` and `offset` keys are also present,
+whose values are respectively the first line, column and byte offset of the rest of the input.
 
-`source`, `start-line` and `start-column` describe where we are reading from.
+`source`, `start-line`, `start-column` and `start-offset` describe where we are reading from
+(by default `stdin`, line 1, column 1, byte offset 0).
 Possible values of `source`:
  * prelude
  * stdin
- * a string representing a file-path.",
+ * a string representing a file-path.
+
+The metadata of the atoms read records where they begin and end (see `get-metadata`).",
 };
 
 pub fn read(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
     if recursion_depth > config::MAX_RECURSION_DEPTH {
-        return Err(make_error(mem, "stackoverflow", READ.name, &vec![]));
+        return Err(LispError::new("stackoverflow", READ.name).build(mem));
     }
-    validate_args!(mem, READ.name, args, (let input: TypeLabel::Any), (let source: TypeLabel::Any), (let start_line: TypeLabel::Number), (let start_column: TypeLabel::Number));
+    let defaults = vec![mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1), mem.allocate_number(0)];
+    let args     = fill_optional_args(mem, READ.name, args, 1, &defaults)?;
+    validate_args!(mem, READ.name, &args, (let input: TypeLabel::Any), (let source: TypeLabel::Any), (let start_line: TypeLabel::Number), (let start_column: TypeLabel::Number), (let start_offset: TypeLabel::Number));
 
-    let sl = *start_line         as usize;
-    let sc = (*start_column - 1) as usize;
+    let location = make_location(mem, READ.name, source.clone(), *start_line, *start_column)?;
 
-    let location =
-    if let Some(path) = list_to_string(source.clone()) {
-        Location::File { path: PathBuf::from(path), line: sl, column: sc}
-    }
-    else if symbol_eq!(args[1], mem.symbol_for("prelude")) {
-        Location::Prelude { line: sl, column: sc }
-    }
-    else if symbol_eq!(args[1], mem.symbol_for("stdin")) {
-        Location::Stdin { line: sl, column: sc }
-    }
-    else {
-        let error_details = vec![("the-unknown-source", source)];
-        let error = make_error(mem, "unknown-read-source", READ.name, &error_details);
-        return Err(error);
-    };
-
-    match read_internal(mem, input, location) {
+    match read_internal(mem, input, location, (*start_offset).max(0) as usize, recursion_depth) {
         Ok((result, rest)) => {
-            let kv = vec![("status", mem.symbol_for("ok")), ("result", result), ("rest", rest.string), ("line", mem.allocate_number(rest.line as i64)), ("column", mem.allocate_number(rest.column as i64))];
+            let kv = vec![("status", mem.symbol_for("ok")), ("result", result), ("rest", rest.string), ("line", mem.allocate_number(rest.line as i64)), ("column", mem.allocate_number(rest.column as i64)), ("offset", mem.allocate_number(rest.offset as i64))];
             Ok(make_plist(mem, &kv))
         },
         Err(ReadError::Nothing) => {
@@ -537,12 +902,443 @@ pub fn read(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usiz
             let kv = vec![("status", mem.symbol_for("invalid"))];
             Ok(make_plist(mem, &kv))
         },
-        Err(ReadError::Error{ msg, location, rest }) => {
-            Ok(format_error(mem, location, msg, rest))
+        Err(ReadError::Error{ msg, location, rest, hint }) => {
+            Ok(format_error(mem, location, msg, hint, rest))
+        },
+        Err(ReadError::Reported(error)) => {
+            Ok(error)
         },
+        Err(ReadError::Signal(signal)) => {
+            Err(signal)
+        },
+    }
+}
+
+
+
+/// The first line starting with `(` in `input`, where reading can continue after a syntax error.
+fn next_top_level_expression(input: &StringWithPosition) -> Option<StringWithPosition> {
+    let mut cursor = input.string.clone();
+    let (mut line, mut column, mut offset) = (input.line, input.column, input.offset);
+
+    while let Some(PrimitiveValue::Cons(cons)) = cursor.get() {
+        let car = cons.get_car();
+        let Some(PrimitiveValue::Character(c)) = car.get() else {
+            return None;
+        };
+        if *c == '(' && column == 1 {
+            return Some(StringWithPosition::new(cursor.clone(), line, column, offset));
+        }
+        if *c == '\n' {
+            line  += 1;
+            column = 1;
+        }
+        else {
+            column += 1;
+        }
+        offset += c.len_utf8();
+        cursor  = cons.get_cdr();
+    }
+
+    None
+}
+
+
+/// Explain why the input ended in the middle of an expression. Also return the position where the error is reported.
+fn end_of_input_hint(parser: &Parser) -> (usize, usize, Option<String>) {
+    // the input ended inside a token
+    let (mut line, mut column) = (parser.resume.line, parser.resume.column);
+    let mut chars = StringIterator::new(parser.resume.string.clone()).map_while(|x| x.map(|(c, _)| c)).peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"'                               => return (line, column, Some("unterminated string literal".to_string())),
+            '#' if chars.peek() == Some(&'|') => return (line, column, Some("unterminated block comment".to_string())),
+            '\n'                              => { line += 1; column = 1; },
+            c if c.is_whitespace()            => column += 1,
+            _                                 => break,
+        }
+    }
+
+    let hint =
+    if let Some(open) = parser.stack.last() {
+        let unclosed = format!("unclosed paren opened at {}:{}", open.location.get_line().unwrap(), open.location.get_column().unwrap());
+        // valid code can have a `(` at the beginning of a line inside an expression, so this is only a guess
+        match parser.stack.iter().find_map(|open| open.top_level_paren.as_ref()) {
+            Some(paren) => Some(format!("{unclosed}, a ')' may be missing before the '(' at {}:{}", paren.get_line().unwrap(), paren.get_column().unwrap())),
+            None        => Some(unclosed),
+        }
+    }
+    else if !parser.prefixes.is_empty() {
+        Some("a quote or a datum comment is not followed by an expression".to_string())
+    }
+    else {
+        None
+    };
+    (parser.resume.line, parser.resume.column, hint)
+}
+
+
+/// Read all of `input` and return the details of every syntax error in it (like the `error` key of the result of `read`).
+///
+/// After an error, reading continues at the next line starting with `(`. When the input ends inside an expression,
+/// the hint points at the first `(` at the beginning of a line inside it, which probably starts a top-level expression.
+fn check_syntax_internal(mem: &mut Memory, input: GcRef, location: Location, recursion_depth: usize) -> Result<Vec<GcRef>, GcRef> {
+    let mut errors = vec![];
+    let mut start  = StringWithPosition::new(input, location.get_line().unwrap(), location.get_column().unwrap() + 1, 0);
+
+    loop {
+        let mut parser = Parser::new(start, true);
+        parser.top_level_parens = true;
+
+        let rest =
+        match parse(mem, &mut parser, &location, recursion_depth + 1) {
+            Ok((_, rest))                  => {
+                start = rest;
+                continue;
+            },
+            Err(ReadError::Nothing)        => break,
+            Err(ReadError::Signal(signal)) => return Err(signal),
+            Err(ReadError::InvalidString)  => {
+                let error = LispError::new("input-invalid-string", CHECK_SYNTAX.name).build(mem);
+                return Err(error);
+            },
+            Err(ReadError::Incomplete)     => {
+                let (line, column, hint) = end_of_input_hint(&parser);
+                let mut end = location.clone();
+                end.set_position(line, column);
+                let error = format_error(mem, end, "unexpected end of input".to_string(), hint, parser.resume.clone());
+                errors.push(property(mem, "error", error).unwrap());
+                break;
+            },
+            Err(ReadError::Error{ msg, location, rest, hint }) => {
+                let error = format_error(mem, location, msg, hint, rest.clone());
+                errors.push(property(mem, "error", error).unwrap());
+                rest
+            },
+            Err(ReadError::Reported(error)) => {
+                errors.push(property(mem, "error", error.clone()).unwrap());
+                match (property(mem, "rest", error.clone()), number_property(mem, "line", error.clone()), number_property(mem, "column", error)) {
+                    (Some(rest), Some(line), Some(column)) => {
+                        let offset = offset_of(&parser.resume, &rest);
+                        StringWithPosition::new(rest, line as usize, column as usize, offset)
+                    },
+                    _                                      => break,
+                }
+            },
+        };
+
+        match next_top_level_expression(&rest) {
+            Some(next) => start = next,
+            None       => break,
+        }
+    }
+
+    Ok(errors)
+}
+
+
+pub const CHECK_SYNTAX: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      check_syntax,
+    name:          "check-syntax",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["input", "&optional", "source", "start-line", "start-column"],
+    documentation: 
+r"Return the list of all syntax errors in `input`, without evaluating anything.
+
+Each error is a property list with the keys `location`, `message` and possibly `hint`
+(like the `error` key of the result of `read`).
+After an error, checking continues at the next line starting with `(`.
+When the input ends inside an expression, the hint also mentions the first `(` at the beginning
+of a line inside it, because a `)` is probably missing before it.
+
+`source`, `start-line` and `start-column` have the same meaning as for `read`.",
+};
+
+pub fn check_syntax(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    if recursion_depth > config::MAX_RECURSION_DEPTH {
+        return Err(LispError::new("stackoverflow", CHECK_SYNTAX.name).build(mem));
+    }
+    let defaults = vec![mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let args     = fill_optional_args(mem, CHECK_SYNTAX.name, args, 1, &defaults)?;
+    validate_args!(mem, CHECK_SYNTAX.name, &args, (let input: TypeLabel::Any), (let source: TypeLabel::Any), (let start_line: TypeLabel::Number), (let start_column: TypeLabel::Number));
+
+    let location = make_location(mem, CHECK_SYNTAX.name, source.clone(), *start_line, *start_column)?;
+    let errors   = check_syntax_internal(mem, input.clone(), location, recursion_depth)?;
+    Ok(vec_to_list(mem, &errors))
+}
+
+
+// A reader (see `make-reader`) is a property list holding the state of `Parser`:
+//
+//   (pending <input not read yet> source <source> line <line> column <column> offset <offset>
+//    stack ((<elements in reverse order> <prefixes> <line of the paren> <column of the paren> <offset of the paren>) ...) prefixes <prefixes>)
+//
+// where `line`, `column` and `offset` are the position of `pending`, and prefixes are symbols (`quote`, ..., `datum-comment`).
+
+
+pub fn number_property(mem: &mut Memory, key: &str, plist: GcRef) -> Option<i64> {
+    match property(mem, key, plist).as_ref().and_then(|x| x.get()) {
+        Some(PrimitiveValue::Number(n)) => Some(*n),
+        _                               => None,
+    }
+}
+
+
+fn prefix_to_symbol(mem: &mut Memory, prefix: &Prefix) -> GcRef {
+    match prefix {
+        Prefix::Wrap(name)   => mem.symbol_for(name),
+        Prefix::DatumComment => mem.symbol_for("datum-comment"),
+    }
+}
+
+
+fn symbol_to_prefix(symbol: &GcRef) -> Option<Prefix> {
+    let Some(PrimitiveValue::Symbol(s)) = symbol.get() else {
+        return None;
+    };
+    match s.get_name().as_str() {
+        "quote"            => Some(Prefix::Wrap("quote")),
+        "quasiquote"       => Some(Prefix::Wrap("quasiquote")),
+        "unquote"          => Some(Prefix::Wrap("unquote")),
+        "unquote-splicing" => Some(Prefix::Wrap("unquote-splicing")),
+        "datum-comment"    => Some(Prefix::DatumComment),
+        _                  => None,
     }
 }
 
 
+fn encode_reader(mem: &mut Memory, parser: &Parser, location: &Location) -> GcRef {
+    let mut frames = vec![];
+    for open in parser.stack.iter() {
+        let prefixes = open.prefixes.iter().map(|p| prefix_to_symbol(mem, p)).collect::<Vec<GcRef>>();
+        let prefixes = vec_to_list(mem, &prefixes);
+        let line     = mem.allocate_number(open.location.get_line().unwrap() as i64);
+        let column   = mem.allocate_number(open.location.get_column().unwrap() as i64);
+        let offset   = mem.allocate_number(open.start.offset as i64);
+        frames.push(vec_to_list(mem, &[open.reversed.clone(), prefixes, line, column, offset]));
+    }
+    let prefixes = parser.prefixes.iter().map(|p| prefix_to_symbol(mem, p)).collect::<Vec<GcRef>>();
+
+    let kv = vec![("pending",  parser.resume.string.clone()),
+                  ("source",   location_details(mem, location)[0].1.clone()),
+                  ("line",     mem.allocate_number(parser.resume.line as i64)),
+                  ("column",   mem.allocate_number(parser.resume.column as i64)),
+                  ("offset",   mem.allocate_number(parser.resume.offset as i64)),
+                  ("stack",    vec_to_list(mem, &frames)),
+                  ("prefixes", vec_to_list(mem, &prefixes))];
+    make_plist(mem, &kv)
+}
+
+
+fn decode_reader(mem: &mut Memory, function_name: &str, reader: GcRef) -> Result<(Parser, Location), GcRef> {
+    let invalid_reader = |mem: &mut Memory| LispError::new("invalid-reader", function_name).detail("reader", reader.clone()).build(mem);
+
+    let pending = property(mem, "pending", reader.clone()).unwrap_or(GcRef::nil());
+    let source  = property(mem, "source",  reader.clone()).unwrap_or(GcRef::nil());
+    let (line, column, offset) =
+    match (number_property(mem, "line", reader.clone()), number_property(mem, "column", reader.clone()), number_property(mem, "offset", reader.clone())) {
+        (Some(l), Some(c), Some(o)) if l > 0 && c > 0 && o >= 0 => (l, c, o),
+        _                                                       => return Err(invalid_reader(mem)),
+    };
+    let location = make_location(mem, function_name, source, line, column)?;
+
+    let mut parser = Parser::new(StringWithPosition::new(pending, line as usize, column as usize, offset as usize), false);
+    let frames     = property(mem, "stack", reader.clone()).and_then(list_to_vec).ok_or_else(|| invalid_reader(mem))?;
+    for frame in frames {
+        let frame    = list_to_vec(frame).filter(|f| f.len() == 5).ok_or_else(|| invalid_reader(mem))?;
+        let prefixes = list_to_vec(frame[1].clone()).and_then(|v| v.iter().map(symbol_to_prefix).collect::<Option<Vec<Prefix>>>()).ok_or_else(|| invalid_reader(mem))?;
+        let (Some(PrimitiveValue::Number(l)), Some(PrimitiveValue::Number(c)), Some(PrimitiveValue::Number(o))) = (frame[2].get(), frame[3].get(), frame[4].get()) else {
+            return Err(invalid_reader(mem));
+        };
+        let mut open_location = location.clone();
+        open_location.set_position(*l as usize, *c as usize);
+        let start = Position{ line: *l as usize, column: *c as usize, offset: *o as usize };
+        parser.stack.push(OpenList{ reversed: frame[0].clone(), prefixes, location: open_location, start, top_level_paren: None });
+    }
+    parser.prefixes = property(mem, "prefixes", reader.clone()).and_then(list_to_vec)
+                                                               .and_then(|v| v.iter().map(symbol_to_prefix).collect::<Option<Vec<Prefix>>>())
+                                                               .ok_or_else(|| invalid_reader(mem))?;
+    Ok((parser, location))
+}
+
+
+pub const MAKE_READER: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      make_reader,
+    name:          "make-reader",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["&optional", "source", "start-line", "start-column"],
+    documentation: 
+r"Create a reader that can be fed the input in chunks (see `reader-feed`).
+
+`source`, `start-line` and `start-column` have the same meaning as for `read`.",
+};
+
+pub fn make_reader(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let defaults = vec![mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let args     = fill_optional_args(mem, MAKE_READER.name, args, 0, &defaults)?;
+    validate_args!(mem, MAKE_READER.name, &args, (let source: TypeLabel::Any), (let start_line: TypeLabel::Number), (let start_column: TypeLabel::Number));
+
+    let location = make_location(mem, MAKE_READER.name, source.clone(), *start_line, *start_column)?;
+    let start    = StringWithPosition::new(GcRef::nil(), *start_line as usize, *start_column as usize, 0);
+    Ok(encode_reader(mem, &Parser::new(start, false), &location))
+}
+
+
+pub const READER_FEED: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      reader_feed,
+    name:          "reader-feed",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["reader", "input", "&optional", "end-of-input"],
+    documentation: 
+r"Continue reading with `reader` where it stopped, as if `input` was appended to everything fed to it before.
+
+The input already read is not read again, so feeding a long expression line by line takes linear time.
+An expression is not finished until the input following it arrives (e.g. `ab` could continue as `abc`),
+unless `end-of-input` is true.
+
+Returns a property list with the keys:
+ * `status`:   `ok`, `error` (the `error` key contains the error details like for `read`)
+               or `incomplete` (only if `end-of-input` is true and the input ended in the middle of an expression).
+ * `forms`:    the list of expressions completed by `input`.
+ * `complete`: whether the reader is between two expressions.
+ * `reader`:   the reader to feed the next input to.
+               After an error the rest of `input` is dropped and this is a reader starting after the error.",
+};
+
+pub fn reader_feed(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    if recursion_depth > config::MAX_RECURSION_DEPTH {
+        return Err(LispError::new("stackoverflow", READER_FEED.name).build(mem));
+    }
+    let args = fill_optional_args(mem, READER_FEED.name, args, 2, &[GcRef::nil()])?;
+    validate_args!(mem, READER_FEED.name, &args, (let reader: TypeLabel::Any), (let _input: TypeLabel::String), (let end_of_input: TypeLabel::Any));
+
+    let (mut parser, location) = decode_reader(mem, READER_FEED.name, reader.clone())?;
+    parser.end_of_input   = !end_of_input.is_nil();
+    parser.resume.string  = append_lists(mem, parser.resume.string.clone(), args[1].clone()).unwrap();
+
+    let mut forms = vec![];
+    let status =
+    loop {
+        match parse(mem, &mut parser, &location, recursion_depth + 1) {
+            Ok((x, rest)) => {
+                forms.push(x);
+                parser = Parser::new(rest, parser.end_of_input);
+            },
+            Err(ReadError::Nothing) => {
+                break mem.symbol_for("ok");
+            },
+            Err(ReadError::Incomplete) => {
+                break mem.symbol_for(if parser.end_of_input {"incomplete"} else {"ok"});
+            },
+            Err(ReadError::Signal(signal)) => {
+                return Err(signal);
+            },
+            Err(error) => {
+                let error = match error {
+                    ReadError::Error{ msg, location, rest, hint } => format_error(mem, location, msg, hint, rest),
+                    ReadError::Reported(error)              => error,
+                    _                                       => {
+                        let invalid = mem.symbol_for("invalid");
+                        make_plist(mem, &[("status", invalid)])
+                    },
+                };
+                // continue after the error, dropping the rest of the input
+                let line   = number_property(mem, "line",   error.clone());
+                let column = number_property(mem, "column", error.clone());
+                let offset = number_property(mem, "offset", error.clone()).unwrap_or(parser.resume.offset as i64);
+                if let (Some(l), Some(c)) = (line, column) {
+                    parser = Parser::new(StringWithPosition::new(GcRef::nil(), l as usize, c as usize, offset as usize), false);
+                }
+                else {
+                    parser = Parser::new(StringWithPosition{ string: GcRef::nil(), ..parser.resume }, false);
+                }
+                let details = property(mem, "error", error.clone()).filter(|x| !x.is_nil()).unwrap_or_else(|| mem.symbol_for("invalid-string"));
+                let forms   = vec_to_list(mem, &forms);
+                let reader  = encode_reader(mem, &parser, &location);
+                let kv      = vec![("status", mem.symbol_for("error")), ("error", details), ("forms", forms), ("complete", mem.symbol_for("t")), ("reader", reader)];
+                return Ok(make_plist(mem, &kv));
+            },
+        }
+    };
+
+    let complete = parser.stack.is_empty() && parser.prefixes.is_empty() && list_to_string(parser.resume.string.clone()).map(|s| s.trim().is_empty()).unwrap_or(false);
+    let complete = if complete {mem.symbol_for("t")} else {GcRef::nil()};
+    let forms    = vec_to_list(mem, &forms);
+    let reader   = encode_reader(mem, &parser, &location);
+    let kv       = vec![("status", status), ("forms", forms), ("complete", complete), ("reader", reader)];
+    Ok(make_plist(mem, &kv))
+}
+
+
+pub const SET_READER_MACRO: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      set_reader_macro,
+    name:          "set-reader-macro",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["character", "function"],
+    documentation: 
+r"Make `read` call `function` whenever an expression starts with `character`.
+
+`function` is called with the input following `character`, the source,
+and the line and column where that input begins (the same arguments as `read`).
+It must return a property list like the one returned by `read`:
+ * `ok`:         the key `result` is the expression read, `rest`, `line` and `column` describe the input left.
+ * `nothing`:    the input up to `rest` (or all of it, if there is no `rest`) contains no expression.
+ * `incomplete`, `error` and `invalid` are returned by `read` as they are.
+
+Replaces the previous function associated with `character`, if any.",
+};
+
+pub fn set_reader_macro(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, SET_READER_MACRO.name, args, (let character: TypeLabel::Character), (let _function: TypeLabel::Function));
+
+    if character.is_whitespace() {
+        let error = LispError::new("invalid-reader-macro-character", SET_READER_MACRO.name).detail("character", args[0].clone()).build(mem);
+        return Err(error);
+    }
+
+    mem.set_reader_macro(*character, args[1].clone());
+
+    Ok(mem.symbol_for("ok"))
+}
+
+
+pub const GET_READER_MACRO: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      get_reader_macro,
+    name:          "get-reader-macro",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["character"],
+    documentation: "Return the function `read` calls when an expression starts with `character`, or nil if there is none."
+};
+
+pub fn get_reader_macro(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, GET_READER_MACRO.name, args, (let character: TypeLabel::Character));
+
+    Ok(mem.get_reader_macro(*character).unwrap_or(GcRef::nil()))
+}
+
+
+pub const REMOVE_READER_MACRO: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      remove_reader_macro,
+    name:          "remove-reader-macro",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["character"],
+    documentation: "Delete the function associated with `character` by `set-reader-macro`, if any."
+};
+
+pub fn remove_reader_macro(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, REMOVE_READER_MACRO.name, args, (let character: TypeLabel::Character));
+
+    mem.remove_reader_macro(*character);
+
+    Ok(mem.symbol_for("ok"))
+}
+
+
 #[cfg(test)]
 mod tests;
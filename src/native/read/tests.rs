@@ -35,6 +35,30 @@ fn read_number() {
     assert_eq!(list_to_string(rest).unwrap(), "");
 }
 
+#[test]
+fn read_radix_number() {
+    let mut mem = Memory::new();
+
+    for (literal, value) in [("0x7f", 127), ("-0b1_01", -5), ("0o777", 511), ("0c\\s", 32)] {
+        let input  = string_to_list(&mut mem, literal);
+        let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+        let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+        let result = property(&mut mem, "result", r).unwrap();
+        assert_eq!(*result.get().unwrap().as_number(), value, "{literal}");
+    }
+}
+
+#[test]
+fn read_number_overflow() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "0x8000_0000_0000_0000");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("error"));
+}
+
 #[test]
 fn read_character() {
     let mut mem = Memory::new();
@@ -645,3 +669,323 @@ fn read_quoted_nested_list() {
     assert_eq_symbol!(vec3[1], mem.symbol_for("b"));
     assert_eq!(list_to_string(rest).unwrap(), " ");
 }
+
+#[test]
+fn read_nested_quotes() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "`',x");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r.clone()).unwrap();
+    let result = property(&mut mem, "result", r.clone()).unwrap();
+
+    assert_eq_symbol!(status, mem.symbol_for("ok"));
+    let vec = list_to_vec(result).unwrap();
+    assert_eq!(vec.len(), 2);
+    assert_eq_symbol!(vec[0], mem.symbol_for("quasiquote"));
+    let vec2 = list_to_vec(vec[1].clone()).unwrap();
+    assert_eq!(vec2.len(), 2);
+    assert_eq_symbol!(vec2[0], mem.symbol_for("quote"));
+    let vec3 = list_to_vec(vec2[1].clone()).unwrap();
+    assert_eq!(vec3.len(), 2);
+    assert_eq_symbol!(vec3[0], mem.symbol_for("unquote"));
+    assert_eq_symbol!(vec3[1], mem.symbol_for("x"));
+}
+
+#[test]
+fn read_unquote_splicing() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "(a ,@b)");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r.clone()).unwrap();
+    let result = property(&mut mem, "result", r.clone()).unwrap();
+
+    assert_eq_symbol!(status, mem.symbol_for("ok"));
+    let vec = list_to_vec(result).unwrap();
+    assert_eq!(vec.len(), 2);
+    let vec2 = list_to_vec(vec[1].clone()).unwrap();
+    assert_eq!(vec2.len(), 2);
+    assert_eq_symbol!(vec2[0], mem.symbol_for("unquote-splicing"));
+    assert_eq_symbol!(vec2[1], mem.symbol_for("b"));
+}
+
+#[test]
+fn read_comma_as_separator() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "(a 1, b 2,)");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r.clone()).unwrap();
+    let result = property(&mut mem, "result", r.clone()).unwrap();
+
+    assert_eq_symbol!(status, mem.symbol_for("ok"));
+    let vec = list_to_vec(result).unwrap();
+    assert_eq!(vec.len(), 4);
+    assert_eq_symbol!(vec[2], mem.symbol_for("b"));
+}
+
+#[test]
+fn read_dangling_quote() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "(a ')");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r.clone()).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("error"));
+
+    let input  = string_to_list(&mut mem, "`");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r.clone()).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("incomplete"));
+}
+
+#[test]
+fn read_reader_macro() {
+    let mut mem = Memory::new();
+    crate::native::load_native_functions(&mut mem);
+
+    // `#` hands the input back to `read`, so `#2` reads as `2`
+    let function = mem.get_global_from_module("read", "native").ok().unwrap();
+    mem.set_reader_macro('#', function);
+
+    let input  = string_to_list(&mut mem, "(1 #2 a#b)");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r.clone()).unwrap();
+    let result = property(&mut mem, "result", r.clone()).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("ok"));
+    let elems = list_to_vec(result).unwrap();
+    assert_eq!(*elems[1].get().unwrap().as_number(), 2);
+    assert_eq!(elems[1].get_meta().unwrap().location.get_column(), Some(5));
+    assert_eq_symbol!(elems[2], mem.symbol_for("a#b"));
+
+    let input  = string_to_list(&mut mem, "(1 #");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("incomplete"));
+}
+
+#[test]
+fn read_span() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "(ab ,@é\n \"c\nd\" 12)");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1), mem.allocate_number(100)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let result = property(&mut mem, "result", r.clone()).unwrap();
+    let elems  = list_to_vec(result.clone()).unwrap();
+    let span   = |x: &GcRef| x.get_meta().unwrap().span.unwrap();
+
+    assert_eq!(span(&result),   Span{ start: Position{ line: 1, column: 1, offset: 100 }, end: Position{ line: 3, column: 6, offset: 118 } });
+    assert_eq!(span(&elems[0]), Span{ start: Position{ line: 1, column: 2, offset: 101 }, end: Position{ line: 1, column: 3, offset: 102 } });
+    let e      = list_to_vec(elems[1].clone()).unwrap()[1].clone();
+    assert_eq!(span(&e),        Span{ start: Position{ line: 1, column: 7, offset: 106 }, end: Position{ line: 1, column: 7, offset: 106 } });
+    assert_eq!(span(&elems[2]), Span{ start: Position{ line: 2, column: 2, offset: 110 }, end: Position{ line: 3, column: 2, offset: 114 } });
+    assert_eq!(span(&elems[3]), Span{ start: Position{ line: 3, column: 4, offset: 116 }, end: Position{ line: 3, column: 5, offset: 117 } });
+    assert_eq!(number_property(&mut mem, "offset", r), Some(119));
+}
+
+#[test]
+fn read_span_after_reader_macro() {
+    let mut mem = Memory::new();
+    crate::native::load_native_functions(&mut mem);
+
+    let function = mem.get_global_from_module("read", "native").ok().unwrap();
+    mem.set_reader_macro('#', function);
+
+    let input  = string_to_list(&mut mem, "(#2 abc)");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let result = property(&mut mem, "result", r).unwrap();
+    let elems  = list_to_vec(result).unwrap();
+    assert_eq!(elems[1].get_meta().unwrap().span.unwrap().start.offset, 4);
+}
+
+#[test]
+fn read_block_comment() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "#| a #| nested |#\n b |# (x)");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r.clone()).unwrap();
+    let result = property(&mut mem, "result", r.clone()).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("ok"));
+    let elems = list_to_vec(result).unwrap();
+    assert_eq!(elems[0].get_meta().unwrap().location.get_line(), Some(2));
+    assert_eq!(elems[0].get_meta().unwrap().location.get_column(), Some(8));
+
+    let input  = string_to_list(&mut mem, "#| a #| b |# c");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("incomplete"));
+}
+
+#[test]
+fn read_datum_comment() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "(a #;(b\n c) '#;d e)");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let result = property(&mut mem, "result", r).unwrap();
+    let elems  = list_to_vec(result).unwrap();
+    assert_eq!(elems.len(), 2);
+    assert_eq_symbol!(elems[0], mem.symbol_for("a"));
+    let quoted = list_to_vec(elems[1].clone()).unwrap();
+    assert_eq_symbol!(quoted[0], mem.symbol_for("quote"));
+    assert_eq_symbol!(quoted[1], mem.symbol_for("e"));
+    assert_eq!(quoted[1].get_meta().unwrap().location.get_line(), Some(2));
+    assert_eq!(quoted[1].get_meta().unwrap().location.get_column(), Some(10));
+}
+
+#[test]
+fn read_unicode_escapes() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "\"a\\u{1F600}\\u{41}\"");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let result = property(&mut mem, "result", r).unwrap();
+    assert_eq!(list_to_string(result).unwrap(), "a\u{1F600}A");
+
+    for (literal, c) in [("%\\u{1F600}", '\u{1F600}'), ("%\\nul", '\0'), ("%\\newline", '\n'), ("%\\u{20}", ' ')] {
+        let input  = string_to_list(&mut mem, literal);
+        let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+        let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+        let result = property(&mut mem, "result", r).unwrap();
+        assert_eq!(*result.get().unwrap().as_character(), c, "{literal}");
+    }
+}
+
+#[test]
+fn read_invalid_unicode_escape() {
+    let mut mem = Memory::new();
+
+    for literal in ["\"\\u{D800}\"", "\"\\u41\"", "%\\u{110000}"] {
+        let input  = string_to_list(&mut mem, literal);
+        let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+        let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+        let status = property(&mut mem, "status", r).unwrap();
+        assert_eq_symbol!(status, mem.symbol_for("error"));
+    }
+
+    let input  = string_to_list(&mut mem, "\"\\u{41");
+    let args   = vec![input, mem.symbol_for("stdin"), mem.allocate_number(1), mem.allocate_number(1)];
+    let r      = read(&mut mem, &args, GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("incomplete"));
+}
+
+#[test]
+fn reader_feed_in_chunks() {
+    let mut mem = Memory::new();
+
+    let reader = make_reader(&mut mem, &[], GcRef::nil(), 0).ok().unwrap();
+    let mut forms = vec![];
+    let mut reader = reader;
+    for chunk in ["(a \"b", "c\" d", "e)\n", "12", "3 ; x", "\n#| y", "|# z\n"] {
+        let input  = string_to_list(&mut mem, chunk);
+        let r      = reader_feed(&mut mem, &[reader, input], GcRef::nil(), 0).ok().unwrap();
+        let status = property(&mut mem, "status", r.clone()).unwrap();
+        assert_eq_symbol!(status, mem.symbol_for("ok"));
+        forms.extend(list_to_vec(property(&mut mem, "forms", r.clone()).unwrap()).unwrap());
+        reader = property(&mut mem, "reader", r).unwrap();
+    }
+
+    assert_eq!(forms.len(), 3);
+    let list = list_to_vec(forms[0].clone()).unwrap();
+    assert_eq!(list_to_string(list[1].clone()).unwrap(), "bc");
+    assert_eq_symbol!(list[2], mem.symbol_for("de"));
+    assert_eq!(*forms[1].get().unwrap().as_number(), 123);
+    assert_eq_symbol!(forms[2], mem.symbol_for("z"));
+    assert_eq!(forms[2].get_meta().unwrap().location.get_line(), Some(3));
+    assert_eq!(forms[2].get_meta().unwrap().location.get_column(), Some(8));
+}
+
+#[test]
+fn reader_feed_end_of_input() {
+    let mut mem = Memory::new();
+
+    let reader = make_reader(&mut mem, &[], GcRef::nil(), 0).ok().unwrap();
+    let input  = string_to_list(&mut mem, "ab");
+    let r      = reader_feed(&mut mem, &[reader.clone(), input.clone()], GcRef::nil(), 0).ok().unwrap();
+    let forms  = property(&mut mem, "forms", r).unwrap();
+    assert!(forms.is_nil());
+
+    let t      = mem.symbol_for("t");
+    let r      = reader_feed(&mut mem, &[reader.clone(), input, t.clone()], GcRef::nil(), 0).ok().unwrap();
+    let forms  = list_to_vec(property(&mut mem, "forms", r).unwrap()).unwrap();
+    assert_eq_symbol!(forms[0], mem.symbol_for("ab"));
+
+    let input  = string_to_list(&mut mem, "(a");
+    let r      = reader_feed(&mut mem, &[reader, input, t], GcRef::nil(), 0).ok().unwrap();
+    let status = property(&mut mem, "status", r).unwrap();
+    assert_eq_symbol!(status, mem.symbol_for("incomplete"));
+}
+
+
+#[test]
+fn check_syntax_reports_all_errors() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "(defun f (x)\n  (g x)\n\n(h \"\\q\")\n(k 0b12)\n(m");
+    let errors = list_to_vec(check_syntax(&mut mem, &[input], GcRef::nil(), 0).ok().unwrap()).unwrap();
+    assert_eq!(errors.len(), 3);
+
+    let expected = [
+        (4, 6, "'q' is not a valid escape character in a string literal", None),
+        (5, 7, "unexpected character in number literal: '2'", None),
+        (6, 3, "unexpected end of input", Some("unclosed paren opened at 6:1")),
+    ];
+    for (error, (line, column, message, hint)) in errors.iter().zip(expected) {
+        let location = property(&mut mem, "location", error.clone()).unwrap();
+        assert_eq!(number_property(&mut mem, "line", location.clone()), Some(line));
+        assert_eq!(number_property(&mut mem, "column", location), Some(column));
+        assert_eq!(list_to_string(property(&mut mem, "message", error.clone()).unwrap()).unwrap(), message);
+        let h = property(&mut mem, "hint", error.clone()).unwrap();
+        assert_eq!(list_to_string(h.clone()).filter(|_| !h.is_nil()).as_deref(), hint);
+    }
+}
+
+
+#[test]
+fn check_syntax_hints_at_top_level_paren() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "(list\n(a))\n(defun f (x)\n  (g x\n\n(h 1)\n");
+    let errors = list_to_vec(check_syntax(&mut mem, &[input], GcRef::nil(), 0).ok().unwrap()).unwrap();
+    assert_eq!(errors.len(), 1);
+
+    let message = property(&mut mem, "message", errors[0].clone()).unwrap();
+    assert_eq!(list_to_string(message).unwrap(), "unexpected end of input");
+    let hint    = property(&mut mem, "hint", errors[0].clone()).unwrap();
+    assert_eq!(list_to_string(hint).unwrap(), "unclosed paren opened at 4:3, a ')' may be missing before the '(' at 6:1");
+}
+
+
+#[test]
+fn check_syntax_without_errors() {
+    let mut mem = Memory::new();
+
+    let input  = string_to_list(&mut mem, "(a 'b)\n#| c |#\n(d\n (e))");
+    let errors = check_syntax(&mut mem, &[input], GcRef::nil(), 0).ok().unwrap();
+    assert!(errors.is_nil());
+
+    let input  = string_to_list(&mut mem, "(list\n(a)\n(b))");
+    let errors = check_syntax(&mut mem, &[input], GcRef::nil(), 0).ok().unwrap();
+    assert!(errors.is_nil());
+
+    let input  = string_to_list(&mut mem, "(a)\n\"b");
+    let errors = list_to_vec(check_syntax(&mut mem, &[input], GcRef::nil(), 0).ok().unwrap()).unwrap();
+    let hint   = property(&mut mem, "hint", errors[0].clone()).unwrap();
+    assert_eq!(list_to_string(hint).unwrap(), "unterminated string literal");
+}
@@ -1,7 +1,8 @@
 use crate::memory::*;
 use crate::metadata::*;
 use crate::util::*;
-use crate::native::list::make_plist;
+use crate::native::list::{make_plist, property};
+use crate::native::read::number_property;
 use crate::error_utils::*;
 use super::NativeFunctionMetaData;
 
@@ -38,7 +39,10 @@ For native functions and special operators body will be nil."
 pub fn destructure_function(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
     validate_args!(mem, DESTRUCTURE_FUNCTION.name, args, (let f: TypeLabel::Function));
 
-    let params = f.get_param_names().iter().map(|pn| mem.symbol_for(&pn)).collect::<Vec<GcRef>>();
+    let params = match f {
+        Function::NormalFunction(nf) => lambda_list_to_vec(mem, nf.get_lambda_list()),
+        Function::NativeFunction(nf) => nf.get_param_names().iter().map(|pn| mem.symbol_for(pn)).collect::<Vec<GcRef>>(),
+    };
     let vec    = vec![mem.symbol_for("kind"),        mem.symbol_for(f.get_kind().to_string()),
                       mem.symbol_for("parameters"),  vec_to_list(mem, &params),
                       mem.symbol_for("body"),        f.get_body(),
@@ -48,6 +52,37 @@ pub fn destructure_function(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recu
 }
 
 
+/// Turn `lambda_list` back into the form it was written in (e.g. `(x &optional (y 1) & z)`).
+fn lambda_list_to_vec(mem: &mut Memory, lambda_list: LambdaList) -> Vec<GcRef> {
+    fn push_specs(mem: &mut Memory, params: &mut Vec<GcRef>, marker: &str, specs: Vec<(GcRef, GcRef)>) {
+        if !specs.is_empty() {
+            params.push(mem.symbol_for(marker));
+        }
+        for (name, default) in specs {
+            if default.is_nil() {
+                params.push(name);
+            }
+            else {
+                params.push(vec_to_list(mem, &[name, default]));
+            }
+        }
+    }
+
+    let mut params = lambda_list.required;
+
+    push_specs(mem, &mut params, "&optional", lambda_list.optional);
+
+    if let Some(rest) = lambda_list.rest {
+        params.push(mem.symbol_for("&"));
+        params.push(rest);
+    }
+
+    push_specs(mem, &mut params, "&key", lambda_list.key);
+
+    params
+}
+
+
 pub const TYPE_OF: NativeFunctionMetaData =
 NativeFunctionMetaData{
     function:      type_of,
@@ -80,13 +115,66 @@ pub fn type_of(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth:
 }
 
 
+/// Describe `location` as key-value pairs (`file`, and `line` and `column` if they are known).
+pub fn location_details(mem: &mut Memory, location: &Location) -> Vec<(&'static str, GcRef)> {
+    let file;
+    let line;
+    let column;
+    match location {
+        Location::Native                             => {
+            file = mem.symbol_for("native");
+            line = GcRef::nil();
+            column = GcRef::nil();
+        },
+        Location::Prelude{ line: ln, column: cn }    => {
+            file = mem.symbol_for("prelude");
+            line = mem.allocate_number(*ln as i64);
+            column = mem.allocate_number(*cn as i64);
+        },
+        Location::Stdin{ line: ln, column: cn }      => {
+            file = mem.symbol_for("stdin");
+            line = mem.allocate_number(*ln as i64);
+            column = mem.allocate_number(*cn as i64);
+        },
+        Location::File{ path, line: ln, column: cn } => {
+            file = string_to_list(mem, &path.clone().into_os_string().into_string().unwrap());
+            line = mem.allocate_number(*ln as i64);
+            column = mem.allocate_number(*cn as i64);
+        },
+    }
+    let mut vec = vec![("file", file)];
+
+    if !line.is_nil() {
+        vec.push(("line", line));
+    }
+
+    if !column.is_nil() {
+        vec.push(("column", column));
+    }
+
+    vec
+}
+
+
+/// Describe the end and the byte offsets of `span` as key-value pairs (its start is already described by the location).
+pub fn span_details(mem: &mut Memory, span: &Span) -> Vec<(&'static str, GcRef)> {
+    vec![("end-line",   mem.allocate_number(span.end.line     as i64)),
+         ("end-column", mem.allocate_number(span.end.column   as i64)),
+         ("offset",     mem.allocate_number(span.start.offset as i64)),
+         ("end-offset", mem.allocate_number(span.end.offset   as i64))]
+}
+
+
 pub const GET_METADATA: NativeFunctionMetaData =
 NativeFunctionMetaData{
     function:      get_metadata,
     name:          "get-metadata",
     kind:          FunctionKind::Lambda,
     parameters:    &["object"],
-    documentation: "Return all metadata stored about `object` in a property-list."
+    documentation: "Return all metadata stored about `object` in a property-list.
+
+For an object read from the source, `line` and `column` are where it begins, `end-line` and `end-column`
+are where its last character is (the `)` of a list), `offset` and `end-offset` are the byte offsets of these characters."
 };
 
 pub fn get_metadata(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
@@ -96,42 +184,13 @@ pub fn get_metadata(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_de
 
     match metadata {
         Some(md) => {
-            let doc = string_to_list(mem, &md.documentation);
-            let file;
-            let line;
-            let column;
-            match &md.location {
-                Location::Native                             => {
-                    file = mem.symbol_for("native");
-                    line = GcRef::nil();
-                    column = GcRef::nil();
-                },
-                Location::Prelude{ line: ln, column: cn }    => {
-                    file = mem.symbol_for("prelude");
-                    line = mem.allocate_number(*ln as i64);
-                    column = mem.allocate_number(*cn as i64);
-                },
-                Location::Stdin{ line: ln, column: cn }      => {
-                    file = mem.symbol_for("stdin");
-                    line = mem.allocate_number(*ln as i64);
-                    column = mem.allocate_number(*cn as i64);
-                },
-                Location::File{ path, line: ln, column: cn } => {
-                    file = string_to_proper_list(mem, &path.clone().into_os_string().into_string().unwrap());
-                    line = mem.allocate_number(*ln as i64);
-                    column = mem.allocate_number(*cn as i64);
-                },
-            }
-            let mut vec = vec![("documentation", doc), ("file", file)];
-
-            if !line.is_nil() {
-                vec.push(("line", line));
+            let doc     = string_to_list(mem, &md.documentation);
+            let mut vec = vec![("documentation", doc)];
+            vec.extend(location_details(mem, &md.location));
+            if let Some(span) = md.span {
+                vec.extend(span_details(mem, &span));
             }
 
-            if !column.is_nil() {
-                vec.push(("column", column));
-            }
- 
             Ok(make_plist(mem, &vec))
         },
         None => {
@@ -140,3 +199,46 @@ pub fn get_metadata(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_de
     }
 }
 
+
+
+pub const SOURCE_EXCERPT: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      source_excerpt,
+    name:          "source-excerpt",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["location"],
+    documentation: "Return the line of the source file at `location`, with the part at `location` underlined by carets.
+
+`location` is a property-list with the keys `file`, `line`, `column`, and optionally `end-line` and `end-column`
+(like the result of `get-metadata`, or the location of a syntax error).
+Return nil if the source is not a file that can be read."
+};
+
+pub fn source_excerpt(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, SOURCE_EXCERPT.name, args, (let location: TypeLabel::Any));
+
+    let path   = property(mem, "file", location.clone()).and_then(list_to_string).filter(|p| !p.is_empty());
+    let line   = number_property(mem, "line",   location.clone()).filter(|l| *l > 0);
+    let column = number_property(mem, "column", location.clone()).filter(|c| *c > 0);
+    let (Some(path), Some(line), Some(column)) = (path, line, column) else {
+        return Ok(GcRef::nil());
+    };
+    let Some(source_line) = std::fs::read_to_string(path).ok().and_then(|text| text.lines().nth(line as usize - 1).map(|l| l.to_string())) else {
+        return Ok(GcRef::nil());
+    };
+
+    // an expression that continues on the next lines is underlined up to the end of its first line
+    let line_length = source_line.chars().count() as i64;
+    let end_column  =
+    match (number_property(mem, "end-line", location.clone()), number_property(mem, "end-column", location)) {
+        (Some(end_line), Some(end_column)) if end_line == line => end_column,
+        (Some(_), Some(_))                                     => line_length,
+        _                                                      => column,
+    }.clamp(column, line_length.max(column));
+
+    let gutter  = line.to_string();
+    let padding = source_line.chars().take(column as usize - 1).map(|c| if c == '\t' {'\t'} else {' '}).collect::<String>();
+    let carets  = "^".repeat((end_column - column + 1) as usize);
+    let excerpt = format!("{gutter} | {source_line}\n{} | {padding}{carets}", " ".repeat(gutter.len()));
+    Ok(string_to_list(mem, &excerpt))
+}
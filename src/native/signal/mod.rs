@@ -1,4 +1,11 @@
 use crate::memory::*;
+use crate::util::*;
+use crate::native::list::make_plist;
+use crate::native::list::property;
+use crate::native::reflection::location_details;
+use crate::native::eval::{eval, parameterize, lookup_in_environment};
+use crate::native::print::print;
+use crate::native::syntax::first_metadata;
 use crate::error_utils::*;
 use super::NativeFunctionMetaData;
 
@@ -20,7 +27,7 @@ pub fn signal(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: u
         let error_details = vec![("argument-value", signal.clone()),
                                  ("expected", mem.symbol_for("any-non-nil-type")),
                                  ("actual", mem.symbol_for("nil-type"))];
-        let error         = make_error(mem, "wrong-argument-type", SIGNAL.name, &error_details);
+        let error         = LispError::new("wrong-argument-type", SIGNAL.name).details(&error_details).build(mem);
         return Err(error);
     }
     
@@ -28,6 +35,110 @@ pub fn signal(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: u
 }
 
 
+pub const MAKE_ERROR: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      make_error,
+    name:          "make-error",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["kind", "source", "message", "&optional", "details", "location"],
+    documentation: "Make an error in the same shape as the errors emitted by native functions:
+a property list with the keys `kind`, `source`, `message`, `details` (a property list of the values involved)
+and `location` (a property list with the keys `file`, `line` and `column`, or nil if it is unknown).
+`message` is a string, or nil for the message of `kind` with the values in `details` filled in, e.g. \"the symbol `x` is unbound\".",
+};
+
+pub fn make_error(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let args = fill_optional_args(mem, MAKE_ERROR.name, args, 3, &[GcRef::nil(), GcRef::nil()])?;
+    validate_args!(mem, MAKE_ERROR.name, &args, (let kind: TypeLabel::Symbol), (let source: TypeLabel::Symbol), (let message: TypeLabel::Any),
+                                                (let details: TypeLabel::List), (let location: TypeLabel::Any));
+
+    let message = match list_to_string(message.clone()) {
+        _ if message.is_nil() => None,
+        Some(message)         => Some(message),
+        None                  => {
+            let details = vec![("argument-value", message.clone()),
+                               ("expected", mem.symbol_for("string-type")),
+                               ("actual", mem.symbol_for(extended_get_type(message).to_string()))];
+            return Err(LispError::new("wrong-argument-type", MAKE_ERROR.name).details(&details).build(mem));
+        },
+    };
+    if details.len() % 2 != 0 || details.chunks(2).any(|kv| kv[0].get_type() != TypeLabel::Symbol) {
+        return Err(LispError::new("invalid-plist", MAKE_ERROR.name).detail("argument-value", args[3].clone()).build(mem));
+    }
+    let details = details.chunks(2).map(|kv| (kv[0].get().unwrap().as_symbol().get_name(), kv[1].clone())).collect::<Vec<_>>();
+
+    Ok(error_plist(mem, &kind.get_name(), &source.get_name(), message, &details, location))
+}
+
+
+pub const MAKE_ASSERTION_ERROR: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      make_assertion_error,
+    name:          "make-assertion-error",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["expression", "&optional", "message", "source"],
+    documentation: "Make an `assertion-failed` error (see `make-error`) for the failed assertion `expression`, emitted by `source`.
+Its details are the text of `expression` and the values of the variables in `expression`
+that are bound in the environment of the call, and its location is that of `expression` in the source.
+`message` is a string, or nil for a default message. Used by `assert`.",
+};
+
+pub fn make_assertion_error(mem: &mut Memory, args: &[GcRef], env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let assert_symbol = mem.symbol_for("assert");
+    let args = fill_optional_args(mem, MAKE_ASSERTION_ERROR.name, args, 1, &[GcRef::nil(), assert_symbol])?;
+    validate_args!(mem, MAKE_ASSERTION_ERROR.name, &args, (let expression: TypeLabel::Any), (let message: TypeLabel::Any), (let source: TypeLabel::Symbol));
+
+    let text = print(mem, std::slice::from_ref(&expression), GcRef::nil(), recursion_depth + 1)?;
+
+    let mut variables = vec![];
+    variables_in(expression.clone(), &mut variables);
+    let mut values = vec![];
+    for variable in variables {
+        let value = lookup_in_environment(&variable, env.clone()).or_else(|| lookup_in_environment(&variable, mem.get_dynamic_environment()));
+        if let Some(value) = value {
+            values.push((variable.get().unwrap().as_symbol().get_name(), value));
+        }
+    }
+    let values = values.iter().map(|(name, value)| (name.as_str(), value.clone())).collect::<Vec<_>>();
+    let values = make_plist(mem, &values);
+
+    let mut error = LispError::new("assertion-failed", &source.get_name()).details(&[("expression", text), ("values", values)]);
+    if !message.is_nil() {
+        let Some(message) = list_to_string(message.clone()) else {
+            let details = vec![("argument-value", message.clone()),
+                               ("expected", mem.symbol_for("string-type")),
+                               ("actual", mem.symbol_for(extended_get_type(message).to_string()))];
+            return Err(LispError::new("wrong-argument-type", MAKE_ASSERTION_ERROR.name).details(&details).build(mem));
+        };
+        error = error.message(message);
+    }
+    if let Some(md) = first_metadata(&expression) {
+        error = error.location_of(&md);
+    }
+    Ok(error.build(mem))
+}
+
+/// Collect the symbols that `form` evaluates as variables into `variables`: every symbol that is neither quoted nor an operator.
+fn variables_in(form: GcRef, variables: &mut Vec<GcRef>) {
+    match form.get() {
+        Some(PrimitiveValue::Symbol(_)) if !variables.iter().any(|v| symbol_eq!(v, form)) => variables.push(form),
+        Some(PrimitiveValue::Cons(_)) => {
+            let elems = list_to_vec(form).unwrap_or_default();
+            for (i, elem) in elems.iter().enumerate() {
+                if i == 0 && elem.get_type() == TypeLabel::Symbol {
+                    if elem.get().unwrap().as_symbol().get_name() == "quote" {
+                        return;
+                    }
+                    continue;
+                }
+                variables_in(elem.clone(), variables);
+            }
+        },
+        _ => (),
+    }
+}
+
+
 pub const ABORT: NativeFunctionMetaData =
 NativeFunctionMetaData{
     function:      abort,
@@ -35,7 +146,10 @@ NativeFunctionMetaData{
     kind:          FunctionKind::Lambda,
     parameters:    &["abort"],
     documentation: "Immediately abort evaluation.
-Unlike signals, an abort cannot be trapped or undone by any method after it was invoked. "
+Unlike signals, an abort cannot be trapped or undone by any method after it was invoked.
+The cleanup forms of `unwind-protect` (and the `finally` part of `try`) are still evaluated while it unwinds.
+The debugger's ABORT command aborts the same way, while its INTERRUPT command emits an `interrupted` signal,
+which can be caught. "
 };
 
 pub fn abort(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
@@ -43,3 +157,258 @@ pub fn abort(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: us
     
     Err(GcRef::nil())
 }
+
+
+/// Add the backtrace recorded for `signal` (see `attach-backtrace`) as its `backtrace` property.
+pub fn with_backtrace(mem: &mut Memory, signal: GcRef) -> GcRef {
+    let Some(mut elems) = list_to_vec(signal.clone()).filter(|elems| elems.len() % 2 == 0) else {
+        return signal;
+    };
+    let Some(backtrace) = mem.get_backtrace(&signal) else {
+        return signal;
+    };
+    let omitted = backtrace.omitted;
+    let frames  = backtrace.frames.iter().map(|f| (f.function.clone(), f.call_site.clone())).collect::<Vec<_>>();
+
+    let mut trace = vec![];
+    for (function, call_site) in frames {
+        let mut frame = vec![("function", string_to_list(mem, &function))];
+        if let Some(location) = call_site {
+            frame.extend(location_details(mem, &location));
+        }
+        trace.push(make_plist(mem, &frame));
+    }
+    if omitted > 0 {
+        let omitted = mem.allocate_number(omitted as i64);
+        trace.push(make_plist(mem, &[("omitted", omitted)]));
+    }
+
+    elems.push(mem.symbol_for("backtrace"));
+    elems.push(vec_to_list(mem, &trace));
+    vec_to_list(mem, &elems)
+}
+
+
+pub const ATTACH_BACKTRACE: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      attach_backtrace,
+    name:          "attach-backtrace",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["signal"],
+    documentation: "Return `signal` with the function calls it propagated through added as its `backtrace` property.
+
+The backtrace is a list of property lists, innermost call first, with the keys `function` (the name of the function),
+and `file`, `line` and `column` (where it was called) if they are known.
+Calls in tail position replace their caller in the backtrace.
+If the backtrace was too long, its last element is `(omitted <number of calls left out>)`.
+Return `signal` unchanged if it is not a property list or it didn't leave any function call."
+};
+
+pub fn attach_backtrace(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, ATTACH_BACKTRACE.name, args, (let signal: TypeLabel::Any));
+
+    Ok(with_backtrace(mem, signal))
+}
+
+
+pub const DEFINE_SIGNAL_KIND: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      define_signal_kind,
+    name:          "define-signal-kind",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["kind", "&optional", "parent"],
+    documentation: "Declare the signal kind `kind` as a special case of the signal kind `parent` (by default `error`),
+so that catching `parent` also catches signals of kind `kind`. Return `kind`.
+Redefining a kind moves it (and its descendants) under `parent`.
+Error if `parent` is `kind` or one of its descendants."
+};
+
+pub fn define_signal_kind(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let defaults = [mem.symbol_for("error")];
+    let args     = fill_optional_args(mem, DEFINE_SIGNAL_KIND.name, args, 1, &defaults)?;
+    validate_args!(mem, DEFINE_SIGNAL_KIND.name, &args, (let kind: TypeLabel::Symbol), (let parent: TypeLabel::Symbol));
+
+    if !mem.define_signal_kind(&kind.get_name(), &parent.get_name()) {
+        let details = vec![("kind", args[0].clone()), ("parent", args[1].clone())];
+        return Err(LispError::new("invalid-signal-kind", DEFINE_SIGNAL_KIND.name).details(&details).build(mem));
+    }
+
+    Ok(args[0].clone())
+}
+
+
+pub const SIGNAL_KIND_PARENT: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      signal_kind_parent,
+    name:          "signal-kind-parent",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["kind"],
+    documentation: "Return the signal kind that `kind` is a special case of, or `nil` if `kind` is `error` or it wasn't declared."
+};
+
+pub fn signal_kind_parent(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, SIGNAL_KIND_PARENT.name, args, (let kind: TypeLabel::Symbol));
+
+    match mem.get_signal_kind_parent(&kind.get_name()).map(|parent| parent.to_string()) {
+        Some(parent) => Ok(mem.symbol_for(&parent)),
+        None         => Ok(GcRef::nil()),
+    }
+}
+
+
+pub const IS_SIGNAL_KIND_OF: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      is_signal_kind_of,
+    name:          "signal-kind-of",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["kind", "ancestor"],
+    documentation: "Return `t` if the signal kind `kind` is `ancestor` or one of its descendants, otherwise `nil`.
+Return `nil` if `kind` is not a symbol, e.g. the kind of a signal that is not a property list."
+};
+
+pub fn is_signal_kind_of(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, IS_SIGNAL_KIND_OF.name, args, (let kind: TypeLabel::Any), (let ancestor: TypeLabel::Symbol));
+
+    let result = match kind.get() {
+        Some(PrimitiveValue::Symbol(kind)) => mem.is_signal_kind_of(&kind.get_name(), &ancestor.get_name()),
+        _                                  => false,
+    };
+    Ok(if result {mem.symbol_for("t")} else {GcRef::nil()})
+}
+
+
+/// Offer the restarts named `restarts` to the handlers established by `handler-bind` that handle `signal`,
+/// without unwinding the stack.
+///
+/// The handlers are called innermost first with `signal` and its `restarts` property,
+/// each of them seeing only the handlers outside of it.
+/// A handler declines by returning normally.
+/// The search stops at the first `(trap predicate)` entry established by `try` whose predicate accepts `signal`.
+/// Return the name of the restart and its value if a handler invoked one of `restarts` (see `invoke-restart`),
+/// otherwise emit `signal` (or the signal emitted by the handler).
+pub fn offer_restarts(mem: &mut Memory, signal: GcRef, restarts: &[&str], recursion_depth: usize) -> Result<(String, GcRef), GcRef> {
+    let handlers = mem.get_dynamic("*signal-handlers*").and_then(list_to_vec).unwrap_or_default();
+    let kind     = property(mem, "kind", signal.clone()).unwrap_or_else(GcRef::nil);
+    let kind     = match kind.get() {
+        Some(PrimitiveValue::Symbol(kind)) => kind.get_name(),
+        _                                  => return Err(signal),
+    };
+    let Some(mut elems) = list_to_vec(signal.clone()).filter(|elems| elems.len() % 2 == 0) else {
+        return Err(signal);
+    };
+
+    let restart_names = restarts.iter().map(|name| mem.symbol_for(name)).collect::<Vec<GcRef>>();
+    elems.push(mem.symbol_for("restarts"));
+    elems.push(vec_to_list(mem, &restart_names));
+    let offered = vec_to_list(mem, &elems);
+
+    for (i, handler) in handlers.iter().enumerate() {
+        let (handled_kind, function) = match list_to_vec(handler.clone()).as_deref() {
+            Some([handled_kind, function]) => (handled_kind.clone(), function.clone()),
+            _                              => continue,
+        };
+        let outer_handlers = vec_to_list(mem, &handlers[i + 1 ..]);
+        let bindings       = [(mem.symbol_for("*signal-handlers*"), outer_handlers)];
+        let quote          = mem.symbol_for("quote");
+
+        // a `try` that catches the signal takes precedence over the handlers outside of it
+        if symbol_eq!(handled_kind, mem.symbol_for("trap")) {
+            let argument = vec_to_list(mem, &[quote, signal.clone()]);
+            let call     = vec_to_list(mem, &[function, argument]);
+            if !parameterize(mem, &bindings, |mem| eval(mem, &[call], GcRef::nil(), recursion_depth + 1))?.is_nil() {
+                return Err(signal);
+            }
+            continue;
+        }
+
+        let handles = match handled_kind.get() {
+            Some(PrimitiveValue::Symbol(handled_kind)) => mem.is_signal_kind_of(&kind, &handled_kind.get_name()),
+            _                                          => false,
+        };
+        if !handles {
+            continue;
+        }
+
+        let argument = vec_to_list(mem, &[quote, offered.clone()]);
+        let call     = vec_to_list(mem, &[function, argument]);
+        match parameterize(mem, &bindings, |mem| eval(mem, &[call], GcRef::nil(), recursion_depth + 1)) {
+            Ok(_) => {},
+            Err(invocation) => {
+                if let Some(name) = restart_invocation(mem, &invocation).filter(|name| restarts.contains(&name.as_str())) {
+                    let value = property(mem, "value", invocation).unwrap_or_else(GcRef::nil);
+                    return Ok((name, value));
+                }
+                return Err(invocation);
+            },
+        }
+    }
+
+    Err(signal)
+}
+
+
+/// The name of the restart if `signal` was emitted by `invoke-restart`.
+fn restart_invocation(mem: &mut Memory, signal: &GcRef) -> Option<String> {
+    let kind = property(mem, "kind", signal.clone())?;
+    if !symbol_eq!(kind, mem.symbol_for("restart-invocation")) {
+        return None;
+    }
+    match property(mem, "restart", signal.clone())?.get() {
+        Some(PrimitiveValue::Symbol(name)) => Some(name.get_name()),
+        _                                  => None,
+    }
+}
+
+
+pub const INVOKE_RESTART: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      invoke_restart,
+    name:          "invoke-restart",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["name", "&optional", "value"],
+    documentation: "Invoke the restart called `name` with `value`.
+Meant to be called from a handler established by `handler-bind`: the evaluation continues from where the signal
+was emitted, as the restart describes (e.g. `use-value` uses `value` instead of the value of an unbound symbol).
+Technically it emits a signal of kind `restart-invocation` that is not caught by `(catch error ...)`,
+so invoking a restart that wasn't offered (like `abort` in the REPL) unwinds to whoever handles it."
+};
+
+pub fn invoke_restart(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let args = fill_optional_args(mem, INVOKE_RESTART.name, args, 1, &[GcRef::nil()])?;
+    validate_args!(mem, INVOKE_RESTART.name, &args, (let _name: TypeLabel::Symbol), (let _value: TypeLabel::Any));
+
+    let kind = mem.symbol_for("restart-invocation");
+    Err(make_plist(mem, &[("kind", kind), ("restart", args[0].clone()), ("value", args[1].clone())]))
+}
+
+
+pub const OFFER_RESTARTS: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      offer_restarts_native,
+    name:          "offer-restarts",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["signal", "restarts"],
+    documentation: "Offer the restarts named in the list `restarts` to the handlers of `signal` (see `handler-bind`).
+If a handler invokes one of them, return the list `(name value)` of the restart that was invoked,
+so that the caller can continue accordingly. Otherwise emit `signal`."
+};
+
+pub fn offer_restarts_native(mem: &mut Memory, args: &[GcRef], _env: GcRef, recursion_depth: usize) -> Result<GcRef, GcRef> {
+    validate_args!(mem, OFFER_RESTARTS.name, args, (let signal: TypeLabel::Any), (let restarts: TypeLabel::List));
+
+    let mut names = vec![];
+    for restart in restarts.iter() {
+        let Some(PrimitiveValue::Symbol(name)) = restart.get() else {
+            let details = vec![("argument-value", restart.clone()),
+                               ("expected", mem.symbol_for("symbol-type")),
+                               ("actual", mem.symbol_for(restart.get_type().to_string()))];
+            return Err(LispError::new("wrong-argument-type", OFFER_RESTARTS.name).details(&details).build(mem));
+        };
+        names.push(name.get_name());
+    }
+    let names = names.iter().map(|name| name.as_str()).collect::<Vec<&str>>();
+
+    let (name, value) = offer_restarts(mem, signal, &names, recursion_depth)?;
+    let name          = mem.symbol_for(&name);
+    Ok(vec_to_list(mem, &[name, value]))
+}
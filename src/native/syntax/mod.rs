@@ -0,0 +1,485 @@
+use crate::memory::*;
+use crate::metadata::*;
+use crate::util::*;
+use crate::native::misc::equal_internal;
+use crate::native::eval::TYPE_GUARDS;
+use crate::native::globals::WITH_CURRENT_MODULE;
+use crate::error_utils::*;
+use super::NativeFunctionMetaData;
+use std::collections::{HashMap, HashSet};
+
+
+
+// `syntax-rules` style macros.
+//
+// A rule is `(pattern template)`. The first element of the pattern stands for the name of the macro and is ignored.
+// In the rest of the pattern:
+//
+//   _                matches anything
+//   literal          (one of the literals of the macro) matches only the same symbol
+//   symbol           matches anything, and binds it to the symbol (a pattern variable)
+//   (p ... q)        `p` followed by `...` matches zero or more elements, the patterns after it match the end of the list
+//   anything else    matches values that are equal to it
+//
+// The template is copied with the pattern variables replaced by what they matched; `x ...` in the template
+// is replaced by all the forms matched by the corresponding `...` in the pattern.
+//
+// The expansion is hygienic in the following sense: the symbols bound by the template itself (by `let`, `lambda`,
+// `match`...) are renamed to fresh symbols, so they can't capture the variables of the macro's user.
+// The other symbols introduced by the template refer to the globals visible from the module where the macro was
+// defined, even private ones or ones defined later, and the local variables of the user can't capture them either:
+// macros are inserted as-is, constants (like `t` and `nil`) are quoted, anything else is looked up at run time with
+// `with-current-module`. Special forms, type names and dynamic variables (`*like-this*`) are left unchanged,
+// as are the symbols in quoted data (in a quasiquote only the unquoted parts are code).
+// The forms matched by the pattern variables (and their metadata) are inserted unchanged.
+
+
+const ELLIPSIS: &str = "...";
+
+const RESERVED: [&str; 19] = ["quote", "if", "lambda", "macro", "trap", "let", "let*", "letrec", "parameterize", "unwind-protect", "match",
+                              "quasiquote", "unquote", "unquote-splicing", "&", "&optional", "&key", "_", "?"];
+
+
+#[derive(Clone)]
+enum Binding {
+    One(GcRef),
+    Many(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+
+struct Expander<'a> {
+    literals: &'a [GcRef],
+    module:   String,           // the module where the macro was defined
+    binders:  HashSet<String>,  // the symbols bound by the template
+    renames:  HashMap<String, GcRef>,
+}
+
+
+/// What a part of a template is.
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    Code,
+    Quote,
+    Quasiquote,  // quoted, except for the `unquote`d parts
+    Clause,      // a `match` clause: a pattern followed by code
+    Pattern,     // a `match` pattern: only its variables are renamed
+}
+
+
+fn symbol_name(x: &GcRef) -> Option<String> {
+    if let Some(PrimitiveValue::Symbol(s)) = x.get() {
+        Some(s.get_name())
+    }
+    else {
+        None
+    }
+}
+
+
+fn is_ellipsis(x: &GcRef) -> bool {
+    symbol_name(x).map(|name| name == ELLIPSIS).unwrap_or(false)
+}
+
+
+/// Whether the symbol `name` is left unchanged in the expansion, wherever it appears.
+fn is_verbatim(name: &str) -> bool {
+    let is_dynamic = name.len() > 2 && name.starts_with('*') && name.ends_with('*');
+    is_dynamic || name == ELLIPSIS || RESERVED.contains(&name) || TYPE_GUARDS.contains(&name)
+}
+
+
+/// The contexts of the elements of `elems`, a list in `context`.
+fn element_contexts(elems: &[GcRef], context: Context) -> Vec<Context> {
+    let head = elems.first().and_then(symbol_name).unwrap_or_default();
+    let mut contexts =
+    match (context, head.as_str()) {
+        (Context::Code | Context::Pattern, "quote")           => vec![Context::Quote; elems.len()],
+        (Context::Code, "quasiquote")                         => vec![Context::Quasiquote; elems.len()],
+        (Context::Quasiquote, "unquote" | "unquote-splicing") => vec![Context::Code; elems.len()],
+        (Context::Clause, _)                                  => vec![Context::Code; elems.len()],
+        (context, _)                                          => vec![context; elems.len()],
+    };
+    match (context, head.as_str()) {
+        (Context::Code, "match") if elems.len() > 2 => contexts[2..].fill(Context::Clause),
+        (Context::Clause, _) if !elems.is_empty()   => contexts[0] = Context::Pattern,
+        (Context::Pattern, "?") if elems.len() > 1  => contexts[1] = Context::Code,
+        _                                           => {},
+    }
+    contexts
+}
+
+
+fn invalid_rule(mem: &mut Memory, rule: &GcRef) -> GcRef {
+    let error_details = vec![("rule", rule.clone())];
+    LispError::new("invalid-syntax-rule", EXPAND_SYNTAX_RULES.name).details(&error_details).build(mem)
+}
+
+
+/// Split a list pattern (or template) at its ellipsis: the elements before the repeated element,
+/// the repeated element, and the elements after the ellipsis.
+fn split_ellipsis(elems: &[GcRef]) -> Option<(&[GcRef], &GcRef, &[GcRef])> {
+    let i = elems.iter().position(is_ellipsis)?;
+    if i == 0 {
+        return None;
+    }
+    Some((&elems[..i - 1], &elems[i - 1], &elems[i + 1..]))
+}
+
+
+/// Collect the names of the pattern variables in `pattern`.
+fn pattern_variables(expander: &Expander, pattern: &GcRef, vars: &mut Vec<String>) {
+    if let Some(elems) = list_to_vec(pattern.clone()) {
+        for elem in elems.iter() {
+            pattern_variables(expander, elem, vars);
+        }
+    }
+    else if let Some(name) = symbol_name(pattern) {
+        if name != "_" && name != ELLIPSIS && !expander.literals.iter().any(|l| symbol_eq!(l, pattern)) {
+            vars.push(name);
+        }
+    }
+}
+
+
+/// Check that a pattern has at most one ellipsis per list and that it doesn't bind a variable twice.
+fn validate_pattern(mem: &mut Memory, expander: &Expander, pattern: &GcRef, rule: &GcRef) -> Result<(), GcRef> {
+    fn check_ellipses(pattern: &GcRef) -> bool {
+        if let Some(elems) = list_to_vec(pattern.clone()) {
+            let count = elems.iter().filter(|x| is_ellipsis(x)).count();
+            (count == 0 || (count == 1 && !is_ellipsis(&elems[0]))) && elems.iter().all(check_ellipses)
+        }
+        else {
+            true
+        }
+    }
+
+    let mut vars = vec![];
+    pattern_variables(expander, pattern, &mut vars);
+    let mut sorted = vars.clone();
+    sorted.sort();
+    sorted.dedup();
+
+    if !check_ellipses(pattern) || sorted.len() != vars.len() {
+        return Err(invalid_rule(mem, rule));
+    }
+    Ok(())
+}
+
+
+fn match_syntax(expander: &Expander, pattern: &GcRef, form: &GcRef, bindings: &mut Bindings) -> bool {
+    if let Some(name) = symbol_name(pattern) {
+        if name == "_" {
+            true
+        }
+        else if expander.literals.iter().any(|l| symbol_eq!(l, pattern)) {
+            symbol_eq!(form, pattern)
+        }
+        else {
+            bindings.insert(name, Binding::One(form.clone()));
+            true
+        }
+    }
+    else if let Some(pattern_elems) = list_to_vec(pattern.clone()) {
+        let Some(form_elems) = list_to_vec(form.clone()) else {
+            return false;
+        };
+
+        if let Some((before, repeated, after)) = split_ellipsis(&pattern_elems) {
+            if form_elems.len() < before.len() + after.len() {
+                return false;
+            }
+            let repeat_end = form_elems.len() - after.len();
+
+            let fixed_ok =
+                before.iter().zip(form_elems.iter()).all(|(p, f)| match_syntax(expander, p, f, bindings)) &&
+                after.iter().zip(form_elems[repeat_end..].iter()).all(|(p, f)| match_syntax(expander, p, f, bindings));
+            if !fixed_ok {
+                return false;
+            }
+
+            let mut vars = vec![];
+            pattern_variables(expander, repeated, &mut vars);
+            let mut matches: Vec<Vec<Binding>> = vars.iter().map(|_| vec![]).collect();
+            for f in form_elems[before.len()..repeat_end].iter() {
+                let mut inner = Bindings::new();
+                if !match_syntax(expander, repeated, f, &mut inner) {
+                    return false;
+                }
+                for (var, m) in vars.iter().zip(matches.iter_mut()) {
+                    m.push(inner.remove(var).expect("pattern variable not bound"));
+                }
+            }
+            for (var, m) in vars.into_iter().zip(matches) {
+                bindings.insert(var, Binding::Many(m));
+            }
+            true
+        }
+        else {
+            pattern_elems.len() == form_elems.len() &&
+            pattern_elems.iter().zip(form_elems.iter()).all(|(p, f)| match_syntax(expander, p, f, bindings))
+        }
+    }
+    else {
+        equal_internal(pattern.clone(), form.clone())
+    }
+}
+
+
+fn is_macro(value: &GcRef) -> bool {
+    matches!(value.get(), Some(PrimitiveValue::Function(f)) if f.get_kind() == FunctionKind::Macro)
+}
+
+
+/// Collect the variables of the destructuring or `match` pattern `pattern`.
+fn pattern_binders(pattern: &GcRef, binders: &mut HashSet<String>) {
+    if let Some(name) = symbol_name(pattern) {
+        if !is_verbatim(&name) && name != "nil" {
+            binders.insert(name);
+        }
+    }
+    else if let Some(elems) = list_to_vec(pattern.clone()) {
+        let head = elems.first().and_then(symbol_name).unwrap_or_default();
+        let skip = match head.as_str() {
+            "quote"         => elems.len(),
+            "?"             => 2,
+            "list" | "cons" => 1,
+            _               => 0,
+        };
+        for elem in elems.iter().skip(skip) {
+            pattern_binders(elem, binders);
+        }
+    }
+}
+
+
+/// Collect the parameters of the parameter list `params`, but not the symbols in the default values.
+fn parameter_binders(params: &GcRef, binders: &mut HashSet<String>) {
+    let Some(elems) = list_to_vec(params.clone()) else {
+        return pattern_binders(params, binders);
+    };
+    let mut after_optional = false;
+    for elem in elems.iter() {
+        let name = symbol_name(elem).unwrap_or_default();
+        after_optional = after_optional || name == "&optional" || name == "&key";
+        match list_to_vec(elem.clone()).as_deref() {
+            // `(name default)`, as opposed to a type annotation `(type name)`
+            Some([spec, ..]) if after_optional && !symbol_name(spec).map(|s| TYPE_GUARDS.contains(&s.as_str())).unwrap_or(false) => {
+                pattern_binders(spec, binders);
+            },
+            _ => pattern_binders(elem, binders),
+        }
+    }
+}
+
+
+/// Collect the symbols that `template` binds with `let`, `let*`, `letrec`, `lambda`, `macro` and `match`.
+fn template_binders(template: &GcRef, context: Context, binders: &mut HashSet<String>) {
+    let Some(elems) = list_to_vec(template.clone()) else {
+        return;
+    };
+    if context == Context::Code {
+        let head = elems.first().and_then(symbol_name).unwrap_or_default();
+        match head.as_str() {
+            "let" | "let*" | "letrec" => {
+                let is_named_let = elems.len() == 4 && elems[1].get_type() == TypeLabel::Symbol;
+                if is_named_let {
+                    pattern_binders(&elems[1], binders);
+                }
+                let bindings = elems.get(if is_named_let {2} else {1}).and_then(|b| list_to_vec(b.clone())).unwrap_or_default();
+                for pattern in bindings.iter().step_by(2) {
+                    pattern_binders(pattern, binders);
+                }
+            },
+            "lambda" | "macro" => {
+                if let Some(params) = elems.get(1) {
+                    parameter_binders(params, binders);
+                }
+            },
+            "match" => {
+                for clause in elems.iter().skip(2) {
+                    if let Some(pattern) = list_to_vec(clause.clone()).and_then(|clause| clause.first().cloned()) {
+                        pattern_binders(&pattern, binders);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    for (elem, context) in elems.iter().zip(element_contexts(&elems, context)) {
+        if context != Context::Quote {
+            template_binders(elem, context, binders);
+        }
+    }
+}
+
+
+/// Return what `symbol` (introduced by a template) should be replaced with.
+fn rename(mem: &mut Memory, expander: &mut Expander, symbol: &GcRef, name: String) -> GcRef {
+    if is_verbatim(&name) {
+        return symbol.clone();
+    }
+
+    if !expander.binders.contains(&name) {
+        // a reference to a global of the module where the macro was defined
+        let quote = mem.symbol_for("quote");
+        return match mem.get_global(&name, &expander.module) {
+            Ok(value) if is_macro(&value)          => value,
+            Ok(value) if value.is_nil()            => value,
+            Ok(value) if symbol_eq!(value, symbol) => vec_to_list(mem, &[quote, value]),
+            _ => {
+                let lookup = mem.symbol_for(WITH_CURRENT_MODULE.name);
+                let name   = vec_to_list(mem, &[quote.clone(), symbol.clone()]);
+                let module = mem.symbol_for(&expander.module);
+                let module = vec_to_list(mem, &[quote, module]);
+                vec_to_list(mem, &[lookup, name, module])
+            },
+        };
+    }
+
+    if let Some(renamed) = expander.renames.get(&name) {
+        return renamed.clone();
+    }
+    let fresh   = mem.unique_symbol();
+    let renamed =
+    if let Some(md) = symbol.get_meta() {
+        let md = Metadata{ read_name: md.read_name.clone(), location: md.location.clone(), documentation: String::new(), span: md.span };
+        mem.allocate_metadata(fresh, md)
+    }
+    else {
+        fresh
+    };
+    expander.renames.insert(name, renamed.clone());
+    renamed
+}
+
+
+fn expand_template(mem: &mut Memory, expander: &mut Expander, template: &GcRef, bindings: &Bindings, context: Context) -> Result<GcRef, GcRef> {
+    if let Some(name) = symbol_name(template) {
+        match bindings.get(&name) {
+            Some(Binding::One(form)) => Ok(form.clone()),
+            Some(Binding::Many(_))   => {
+                let error_details = vec![("variable", template.clone())];
+                Err(LispError::new("missing-ellipsis", EXPAND_SYNTAX_RULES.name).details(&error_details).build(mem))
+            },
+            None => match context {
+                Context::Code | Context::Clause                         => Ok(rename(mem, expander, template, name)),
+                Context::Pattern if expander.binders.contains(&name)    => Ok(rename(mem, expander, template, name)),
+                Context::Pattern | Context::Quote | Context::Quasiquote => Ok(template.clone()),
+            },
+        }
+    }
+    else if let Some(elems) = list_to_vec(template.clone()) {
+        let contexts = element_contexts(&elems, context);
+
+        let mut result = vec![];
+        let mut i      = 0;
+        while i < elems.len() {
+            if elems.get(i + 1).map(is_ellipsis).unwrap_or(false) {
+                let mut vars = vec![];
+                pattern_variables(expander, &elems[i], &mut vars);
+                let repeated = vars.into_iter().filter_map(|var| match bindings.get(&var) {
+                    Some(Binding::Many(ms)) => Some((var, ms)),
+                    _                       => None,
+                }).collect::<Vec<_>>();
+
+                let Some(count) = repeated.first().map(|(_, ms)| ms.len()) else {
+                    let error_details = vec![("template", template.clone())];
+                    return Err(LispError::new("invalid-ellipsis", EXPAND_SYNTAX_RULES.name).details(&error_details).build(mem));
+                };
+                if repeated.iter().any(|(_, ms)| ms.len() != count) {
+                    let error_details = vec![("template", template.clone())];
+                    return Err(LispError::new("ellipsis-length-mismatch", EXPAND_SYNTAX_RULES.name).details(&error_details).build(mem));
+                }
+
+                for j in 0..count {
+                    // the repeated variables are bound to their `j`th match, the rest is visible as-is
+                    let mut inner = bindings.clone();
+                    for (var, ms) in repeated.iter() {
+                        inner.insert(var.clone(), ms[j].clone());
+                    }
+                    result.push(expand_template(mem, expander, &elems[i], &inner, contexts[i])?);
+                }
+                i += 2;
+            }
+            else {
+                result.push(expand_template(mem, expander, &elems[i], bindings, contexts[i])?);
+                i += 1;
+            }
+        }
+        Ok(vec_to_list(mem, &result))
+    }
+    else {
+        Ok(template.clone())
+    }
+}
+
+
+/// Return the metadata of `form`, or of the first expression in it that has some.
+pub(crate) fn first_metadata(form: &GcRef) -> Option<Metadata> {
+    if let Some(md) = form.get_meta() {
+        return Some(md.clone());
+    }
+    list_to_vec(form.clone())?.iter().find_map(first_metadata)
+}
+
+
+pub const EXPAND_SYNTAX_RULES: NativeFunctionMetaData =
+NativeFunctionMetaData{
+    function:      expand_syntax_rules,
+    name:          "expand-syntax-rules",
+    kind:          FunctionKind::Lambda,
+    parameters:    &["literals", "rules", "form", "&optional", "module"],
+    documentation: "Expand `form` using the first rule in `rules` whose pattern matches it.
+
+Each rule is a list of the form `(pattern template)`. The first element of a pattern
+(the name of the macro) is ignored. Symbols in `literals` only match themselves,
+`_` matches anything, other symbols are pattern variables. A pattern followed by `...`
+matches zero or more elements. The symbols bound by the template are renamed
+to fresh symbols, the other symbols introduced by the template refer to the globals
+of `module` (the current module by default), where the macro was defined.
+See also `define-syntax`.",
+};
+
+pub fn expand_syntax_rules(mem: &mut Memory, args: &[GcRef], _env: GcRef, _recursion_depth: usize) -> Result<GcRef, GcRef> {
+    let current_module = mem.get_current_module();
+    let current_module = mem.symbol_for(&current_module);
+    let args           = fill_optional_args(mem, EXPAND_SYNTAX_RULES.name, args, 3, &[current_module])?;
+    validate_args!(mem, EXPAND_SYNTAX_RULES.name, &args, (let literals: TypeLabel::List), (let rules: TypeLabel::List), (let form: TypeLabel::Any),
+                                                         (let module: TypeLabel::Symbol));
+
+    let mut expander = Expander{ literals: &literals, module: module.get_name(), binders: HashSet::new(), renames: HashMap::new() };
+
+    for rule in rules.iter() {
+        let rule_elems = list_to_vec(rule.clone()).filter(|elems| elems.len() == 2 && list_to_vec(elems[0].clone()).map(|p| !p.is_empty()).unwrap_or(false));
+        let Some(rule_elems) = rule_elems else {
+            return Err(invalid_rule(mem, rule));
+        };
+        validate_pattern(mem, &expander, &rule_elems[0], rule)?;
+
+        let pattern_elems = list_to_vec(rule_elems[0].clone()).unwrap();
+        let form_elems    = list_to_vec(form.clone()).unwrap_or_default();
+        let pattern_rest  = vec_to_list(mem, &pattern_elems[1..]);
+        let form_rest     = if form_elems.is_empty() {form.clone()} else {vec_to_list(mem, &form_elems[1..])};
+
+        let mut bindings = Bindings::new();
+        if match_syntax(&expander, &pattern_rest, &form_rest, &mut bindings) {
+            template_binders(&rule_elems[1], Context::Code, &mut expander.binders);
+            return expand_template(mem, &mut expander, &rule_elems[1], &bindings, Context::Code);
+        }
+    }
+
+    // the name of the macro is inserted by `define-syntax`, so its location is where the macro was defined
+    let arguments = list_to_vec(form.clone()).map(|elems| elems.into_iter().skip(1).collect::<Vec<GcRef>>()).unwrap_or_default();
+    let mut error = LispError::new("no-matching-syntax-rule", EXPAND_SYNTAX_RULES.name).detail("form", form.clone());
+    if let Some(md) = arguments.iter().find_map(first_metadata) {
+        error = error.location_of(&md);
+    }
+    Err(error.build(mem))
+}
+
+
+#[cfg(test)]
+mod tests;
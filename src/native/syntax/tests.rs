@@ -0,0 +1,112 @@
+use pretty_assertions::assert_eq;
+use crate::util::{vec_to_list, list_to_vec, assert_eq_symbol};
+use super::*;
+
+
+
+fn make_rule(mem: &mut Memory, pattern: &[GcRef], template: GcRef) -> GcRef {
+    let pattern = vec_to_list(mem, pattern);
+    vec_to_list(mem, &[pattern, template])
+}
+
+
+#[test]
+fn expand_with_ellipsis() {
+    let mut mem = Memory::new();
+
+    // rule: ((_ x ...) (x ...)), form: (m 1 2 3)
+    let pattern  = vec![mem.symbol_for("_"), mem.symbol_for("x"), mem.symbol_for("...")];
+    let template = vec![mem.symbol_for("x"), mem.symbol_for("...")];
+    let template = vec_to_list(&mut mem, &template);
+    let rule     = make_rule(&mut mem, &pattern, template);
+    let rules    = vec_to_list(&mut mem, &[rule]);
+    let form     = vec![mem.symbol_for("m"), mem.allocate_number(1), mem.allocate_number(2), mem.allocate_number(3)];
+    let form     = vec_to_list(&mut mem, &form);
+
+    let result = expand_syntax_rules(&mut mem, &[GcRef::nil(), rules, form], GcRef::nil(), 0).ok().unwrap();
+    let result = list_to_vec(result).unwrap();
+    assert_eq!(result.iter().map(|x| *x.get().unwrap().as_number()).collect::<Vec<i64>>(), vec![1, 2, 3]);
+}
+
+#[test]
+fn expand_renames_introduced_symbols() {
+    let mut mem = Memory::new();
+
+    // rule: ((_ x) (let (tmp x) tmp)), form: (m tmp)
+    let pattern  = vec![mem.symbol_for("_"), mem.symbol_for("x")];
+    let bindings = vec![mem.symbol_for("tmp"), mem.symbol_for("x")];
+    let template = vec![mem.symbol_for("let"), vec_to_list(&mut mem, &bindings), mem.symbol_for("tmp")];
+    let template = vec_to_list(&mut mem, &template);
+    let rule     = make_rule(&mut mem, &pattern, template);
+    let rules    = vec_to_list(&mut mem, &[rule]);
+    let form     = vec![mem.symbol_for("m"), mem.symbol_for("tmp")];
+    let form     = vec_to_list(&mut mem, &form);
+
+    let result   = expand_syntax_rules(&mut mem, &[GcRef::nil(), rules, form], GcRef::nil(), 0).ok().unwrap();
+    let result   = list_to_vec(result).unwrap();
+    let bindings = list_to_vec(result[1].clone()).unwrap();
+    assert_eq_symbol!(result[0], mem.symbol_for("let"));
+    assert!(!symbol_eq!(bindings[0], mem.symbol_for("tmp")));
+    assert_eq_symbol!(bindings[1], mem.symbol_for("tmp"));
+    assert_eq_symbol!(result[2], bindings[0]);
+}
+
+#[test]
+fn expand_literal() {
+    let mut mem = Memory::new();
+
+    // rules: ((_ else) 1) ((_ x) 2), literals: (else)
+    let pattern1 = vec![mem.symbol_for("_"), mem.symbol_for("else")];
+    let one      = mem.allocate_number(1);
+    let rule1    = make_rule(&mut mem, &pattern1, one);
+    let pattern2 = vec![mem.symbol_for("_"), mem.symbol_for("x")];
+    let two      = mem.allocate_number(2);
+    let rule2    = make_rule(&mut mem, &pattern2, two);
+    let rules    = vec_to_list(&mut mem, &[rule1, rule2]);
+    let literals = vec![mem.symbol_for("else")];
+    let literals = vec_to_list(&mut mem, &literals);
+
+    let form   = vec![mem.symbol_for("m"), mem.symbol_for("else")];
+    let form   = vec_to_list(&mut mem, &form);
+    let result = expand_syntax_rules(&mut mem, &[literals.clone(), rules.clone(), form], GcRef::nil(), 0).ok().unwrap();
+    assert_eq!(*result.get().unwrap().as_number(), 1);
+
+    let form   = vec![mem.symbol_for("m"), mem.symbol_for("other")];
+    let form   = vec_to_list(&mut mem, &form);
+    let result = expand_syntax_rules(&mut mem, &[literals, rules, form], GcRef::nil(), 0).ok().unwrap();
+    assert_eq!(*result.get().unwrap().as_number(), 2);
+}
+
+#[test]
+fn expand_no_matching_rule() {
+    let mut mem = Memory::new();
+
+    // rule: ((_) 1), form: (m 2)
+    let pattern = vec![mem.symbol_for("_")];
+    let one     = mem.allocate_number(1);
+    let rule    = make_rule(&mut mem, &pattern, one);
+    let rules   = vec_to_list(&mut mem, &[rule]);
+    let form    = vec![mem.symbol_for("m"), mem.allocate_number(2)];
+    let form    = vec_to_list(&mut mem, &form);
+
+    let error = expand_syntax_rules(&mut mem, &[GcRef::nil(), rules, form], GcRef::nil(), 0).err().unwrap();
+    let kind  = crate::native::list::property(&mut mem, "kind", error).unwrap();
+    assert_eq_symbol!(kind, mem.symbol_for("no-matching-syntax-rule"));
+}
+
+#[test]
+fn invalid_rule_with_two_ellipses() {
+    let mut mem = Memory::new();
+
+    // rule: ((_ x ... y ...) 1)
+    let pattern = vec![mem.symbol_for("_"), mem.symbol_for("x"), mem.symbol_for("..."), mem.symbol_for("y"), mem.symbol_for("...")];
+    let one     = mem.allocate_number(1);
+    let rule    = make_rule(&mut mem, &pattern, one);
+    let rules   = vec_to_list(&mut mem, &[rule]);
+    let form    = vec![mem.symbol_for("m")];
+    let form    = vec_to_list(&mut mem, &form);
+
+    let error = expand_syntax_rules(&mut mem, &[GcRef::nil(), rules, form], GcRef::nil(), 0).err().unwrap();
+    let kind  = crate::native::list::property(&mut mem, "kind", error).unwrap();
+    assert_eq_symbol!(kind, mem.symbol_for("invalid-syntax-rule"));
+}
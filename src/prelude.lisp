@@ -1,6 +1,6 @@
-(export '(t nil *stdin* *stdout* defmacro defun unzip-list let when output input foldl foldr
+(export '(t nil *stdin* *stdout* defmacro defun define-syntax unzip-list when output input with-output-to-string foldl foldr
           reverse zip length enumerate map apply last init block and or not /= <= >= + - * /
-          range append concat describe case catch catch-all try throw get-property-safe
+          range append concat describe case catch catch-all finally try throw *signal-handlers* handler-bind assert get-property-safe
           load read-simple infinite-loop))
 
 
@@ -13,41 +13,74 @@
 (define '*stdout* '*stdout* "standard output")
 
 (define 'defmacro (macro (name params doc-string body)
-    (list 'define (list 'quote name) (list 'macro params body) doc-string)) "Globally define `name` as a macro.")
-
-(defmacro defun (name params doc-string body)
-  "Globally define `name` as a lambda function."
-  (list 'define (list 'quote name) (list 'lambda params body) doc-string))
+    `(define ',name (macro ,params ,body) ,doc-string)) "Globally define `name` as a macro.")
+
+(define '-split-contract
+  (lambda (forms pre post)
+    (let (wrong-count (lambda (count) (signal (make-error 'wrong-number-of-arguments 'defun nil (list 'expected 1 'actual count)))))
+      (if forms
+          (if (if (= (car forms) ':pre) t (= (car forms) ':post))
+              (if (cdr forms)
+                  (if (= (car forms) ':pre)
+                      (-split-contract (cdr (cdr forms)) (cdr forms) post)
+                      (-split-contract (cdr (cdr forms)) pre (cdr forms)))
+                  (wrong-count 0))
+              (if (cdr forms)
+                  (wrong-count (length forms))
+                  (list (car forms) pre post)))
+          (wrong-count 0))))
+  "Split `forms`, the part of a `defun` after the documentation string, into a list of the body,
+the precondition and the postcondition. A condition is a list of the condition expression, or nil if there is none.
+Emit `wrong-number-of-arguments` if there is not exactly one body, or if `:pre` or `:post` is not followed by a condition.")
+
+(define '-contract-message
+  (lambda (which condition name)
+    (concat "the " which " " (print (car condition)) " of `" (print name) "` failed"))
+  "The message of the error emitted when the contract `condition` (see `-split-contract`) of the function `name` fails.")
+
+(defmacro defun (name params doc-string & forms)
+  "Globally define `name` as a lambda function.
+`body`, the last one of `forms`, can be preceded by a precondition `:pre condition` and a postcondition `:post condition`.
+The precondition is checked before evaluating `body`, and the postcondition after it, where it can refer to
+the return value as `result`. A condition that evaluates to nil emits an `assertion-failed` signal (see `assert`)."
+  (let ((body pre post) (-split-contract forms nil nil))
+    (let (body (if post
+                   `(let (result ,body)
+                      (if ,(car post)
+                          result
+                          (signal (make-assertion-error ',(car post) ',(-contract-message "postcondition" post name) ',name))))
+                   body))
+      (let (body (if pre
+                     `(if ,(car pre)
+                          ,body
+                          (signal (make-assertion-error ',(car pre) ',(-contract-message "precondition" pre name) ',name)))
+                     body))
+        `(define ',name (lambda ,params ,body) ,doc-string)))))
+
+(defmacro define-syntax (name literals doc-string & rules)
+  "Globally define `name` as a hygienic macro.
+Each element of `rules` is a `(pattern template)` pair; the first rule whose pattern
+matches the macro call is used to expand it (see `expand-syntax-rules`).
+The symbols in `literals` only match themselves in the patterns.
+The globals referred to by the templates are the ones visible from the module where `name` is defined."
+  `(define ',name
+     (macro (& form) (expand-syntax-rules ',literals ',rules (cons ',name form) ',(get-current-module)))
+     ,doc-string))
 
 (defun unzip-list (pairs)
   "Group the odd and even numbered elements of `pairs` into two separate lists."
   (if pairs
-      ((lambda (fsts-snds)
-         (cons
-          (cons (car      pairs)  (car fsts-snds))
-          (cons (car (cdr pairs)) (cdr fsts-snds))))
-       (unzip-list (cdr (if (cdr pairs)
-                            (cdr pairs)
-                            (signal (list 'kind 'wrong-argument, 'source 'unzip-list, 'details 'odd-number-of-elements))))))
+      (let (fsts-snds (unzip-list (cdr (if (cdr pairs)
+                                           (cdr pairs)
+                                           (signal (make-error 'wrong-argument 'unzip-list "odd number of elements" (list 'argument-value pairs)))))))
+        (cons
+         (cons (car      pairs)  (car fsts-snds))
+         (cons (car (cdr pairs)) (cdr fsts-snds))))
       (cons nil nil)))
 
-(defmacro let (bindings body)
-"Bind variables according to `bindings` then eval `body`.
-The value of the last form in `body` is returned."
-  ((lambda (params-args)
-     ((lambda (params args)
-        (cons (list 'lambda params body) args))
-      (car params-args)
-      (cdr params-args)))
-   (eval (trap (unzip-list bindings)
-               (signal (list 'kind 'wrong-argument, 'source 'let, 'details 'incomplete-binding))))))
-
 (defmacro when (condition then)
   "Same as `if` but the `otherwise` arm is always `nil`."
-  (list 'if
-        condition
-        then
-        nil))
+  `(if ,condition ,then nil))
 
 (defun foldl (f init things)
   "Return the result of applying `f` to `init` and the first element of `things`,
@@ -69,50 +102,41 @@ If `things` is empty then just return `init`."
   "Reverse the order of elements in `things`."
   (foldl (lambda (xs x) (cons x xs)) nil things))
 
-(defun -zip (things1 things2 init)
-  ""
-  (if things1
-      (if things2
-          (-zip (cdr things1) (cdr things2) (cons (cons (car things1)
-                                                   (car things2))
-                                             init))
-          init)
-      init))
-
 (defun zip (things1 things2)
   "Group the head of each list, followed by the second element of
 each list, and so on. The number of returned groupings is equal
 to the length of the shortest input list."
-  (reverse (-zip things1 things2 nil)))
-
-(defun -length (things n)
-  ""
-  (if things
-      (-length (cdr things) (add n 1))
-      n))
+  (let loop (things1 things1, things2 things2, init nil)
+    (if things1
+        (if things2
+            (loop (cdr things1) (cdr things2) (cons (cons (car things1)
+                                                         (car things2))
+                                                   init))
+            (reverse init))
+        (reverse init))))
 
 (defun length (things)
   "Return the number of elements in `things`."
-  (-length things 0))
+  (let loop (things things, n 0)
+    (if things
+        (loop (cdr things) (add n 1))
+        n)))
 
 (defun enumerate (things)
   "Zip each element of `things` with its index (starting from 0)."
   (zip things (range (length things))))
 
 
-(defun -map (f things init)
-  ""
-  (if things
-      (-map f (cdr things) (cons (f (car things)) init))
-      init))
-
 (defun map (f things)
   "Apply `f` to each element of `things`, and make a list of the results."
-  (reverse (-map f things nil)))
+  (let loop (things things, init nil)
+    (if things
+        (loop (cdr things) (cons (f (car things)) init))
+        (reverse init))))
 
 (defmacro apply (f args-list)
   "Apply `f` to `args-list`, as if each element of `args-list` were a parameter of `f`."
-  (list (list 'unrest f) args-list))
+  `((unrest ,f) ,args-list))
 
 (defun last (things)
   "Return the last element of `things`."
@@ -120,7 +144,7 @@ to the length of the shortest input list."
       (if (cdr things)
           (last (cdr things))
           (car things))
-      (signal (list 'kind 'wrong-argument, 'soruce 'last, 'details 'empty-list))))
+      (signal (make-error 'wrong-argument 'last "the list is empty"))))
 
 (defun init (things)
   "Return all elements of `things` except the last one."
@@ -133,33 +157,39 @@ to the length of the shortest input list."
 (defmacro block (& body)
   "Execute all forms in `body` then return the result of the last one."
   (if body
-      (let (init-body (init body))
-        (let (params (map (lambda (_) (gensym)) init-body)
-              end    (last body))
-          (cons (list 'lambda params end) init-body)))
+      (let* (init-body (init body)
+             params    (map (lambda (_) (gensym)) init-body)
+             end       (last body))
+        `((lambda ,params ,end) ,@init-body))
       nil))
 
 (defun output (msg)
-  "Write `msg` to stdout."
+  "Write `msg` to `*stdout*`."
   (output-file *stdout* (concat msg "\n")))
 
+(defmacro with-output-to-string (body)
+  "Evaluate `body` while `*stdout*` is redirected to a string buffer.
+Return everything written to `*stdout*` during that time as a string."
+  `(capture-output (lambda () ,body)))
+
 (defun input (prompt)
   "Write `prompt` to stdout then read a line from stdin."
   (block
     (output-file *stdout* prompt)
     (input-file *stdin*)))
 
-(defmacro and (x y)
+(define-syntax and ()
   "Logical and."
-  (list 'if x y nil))
+  ((_ x y) (if x y nil)))
 
-(defmacro or (x y)
+(define-syntax or ()
   "Logical or."
-  (list 'if x x y))
+  ((_ x y) (let (value x)
+             (if value value y))))
 
 (defmacro not (x)
   "Logical not."
-  (list 'if x nil t))
+  `(if ,x nil t))
 
 (defun /= (x y)
   "Not equals"
@@ -205,22 +235,19 @@ Otherwise substract all but the first argument from the first one."
             (divide 1 first)))
       1))
 
-(defun -range (n init)
-  ""
-  (if (= n -1)
-      init
-      (-range (substract n 1) (cons n init))))
-
 (defun range (n)
   "Range of numbers from 0 to `n` (including 0, excluding `n`)."
-  (-range (substract n 1) nil))
+  (let loop (n (substract n 1), init nil)
+    (if (= n -1)
+        init
+        (loop (substract n 1) (cons n init)))))
 
 (defun concat (& lists)
   "Concatenate all lists in `lists`."
-  (let (f (lambda (f xs) (if xs
-                             (append (car xs) (f f (cdr xs)))
-                             nil)))
-    (f f lists)))
+  (let loop (xs lists)
+    (if xs
+        (append (car xs) (loop (cdr xs)))
+        nil)))
 
 (defun describe (thing)
   "Print all available metadata about `thing` in a human-readable format."
@@ -240,7 +267,7 @@ Otherwise substract all but the first argument from the first one."
                 (let (source (. metadata 'file))
                   (if (= source 'native)
                       "Rust source."
-                      (concat (print source)
+                      (concat (if (= (type-of source) 'string-type) source (print source))
                               ":"
                               (print (. metadata 'line))
                               ":"
@@ -251,8 +278,7 @@ Otherwise substract all but the first argument from the first one."
   "Each element of `cases` should be in the following form: `(condition value)`.
 Return the `value` of the first element whose `condition` evaluates to true.
 If non of them is true then return `nil`."
-  (foldr (lambda (c acc) (let (condition (car c), value (car (cdr c)))
-                           (list 'if condition value acc)))
+  (foldr (lambda ((condition value) acc) `(if ,condition ,value ,acc))
         nil
         cases)) 
 
@@ -263,43 +289,75 @@ If non of them is true then return `nil`."
    nil)))
 
 (defmacro catch (kind body)
-  "Catches any signal whose `kind` property is equal to `kind`.
+  "Catches any signal whose `kind` property is `kind` or one of its descendants in the signal kind tree
+(see `define-signal-kind`), e.g. `(catch arithmetic-error ...)` also catches `divide-by-zero`.
 Meant to be used as part of the `try` macro.
 `body` should be a lambda with one parameter. This parameters will be set to the caught signal."
-  (list 'test
-        (list '=
-              (list 'get-property-safe
-                    (list 'quote
-                          'kind)
-                    '*trapped-signal*)
-              (list 'quote kind))
-        'body
-        body))
+  `(test (signal-kind-of (get-property-safe 'kind *trapped-signal*) ',kind)
+    body ,body))
 
 (defmacro catch-all (body)
-  "Catches any signal.
+  "Catches any signal, except the `restart-invocation` emitted by `invoke-restart` (see `handler-bind`),
+which is let through so that a restart invoked in `body` reaches the code that offered it.
 Meant to be used as part of the `try` macro.
 `body` should be a lambda with one parameter. This parameters will be set to the caught signal."
-  (list 'test t
-        'body body))
+  `(test (not (= (get-property-safe 'kind *trapped-signal*) 'restart-invocation))
+    body ,body))
+
+(defmacro finally (cleanup)
+  "Evaluate `cleanup` after the rest of the `try`, whether it returned normally, a signal was emitted
+(even if none of the catchers caught it, or a catcher emitted one) or the evaluation was aborted.
+Meant to be used as the last part of the `try` macro (see `unwind-protect`)."
+  `(cleanup ,cleanup))
 
 (defmacro try (body & catchers)
   "Try to evaluate `body`.
 If a signal is emitted while evaluating `body`, evaluate the first catcher in `catchers`
-that catches the signal."
-  (list 'eval
-         (list 'trap
-               body
-               (cons 'case
-                     (map (lambda (catcher) (list (. catcher 'test)
-                                                  (list (. catcher 'body) '*trapped-signal*)))
-                          catchers)))))
+that catches the signal. If none of them catches it, return nil, unless the signal is a `restart-invocation`,
+which is emitted again.
+While `body` is evaluated, the catchers take part in `*signal-handlers*`: a signal caught by one of them is
+not given to the handlers established by `handler-bind` outside of the `try`.
+If the last element of `catchers` is a `finally`, its cleanup is evaluated at the end in every case."
+  (let* (finally? (and catchers (= (car (last catchers)) 'cleanup))
+         cleanup  (when finally? (. (last catchers) 'cleanup))
+         catchers (if finally? (init catchers) catchers)
+         catches? `(lambda (*trapped-signal*)
+                     (case ,@(map (lambda (catcher) `(,(. catcher 'test) t))
+                                  catchers)))
+         trapped  `(eval (trap (parameterize (*signal-handlers* (cons (list 'trap ,catches?) *signal-handlers*))
+                                 ,body)
+                               (case ,@(map (lambda (catcher) `(,(. catcher 'test) (,(. catcher 'body) *trapped-signal*)))
+                                            catchers)
+                                     ((= (get-property-safe 'kind *trapped-signal*) 'restart-invocation) (signal *trapped-signal*))))))
+    (if finally?
+        `(unwind-protect ,trapped ,cleanup)
+        trapped)))
 
 (defmacro throw (& body)
   "Emit a signal that is a property-list made of the key-value pairs in `body`."
-  (list 'signal
-        (cons 'list
-              body)))
+  `(signal (list ,@body)))
+
+(define '*signal-handlers* nil
+  "The handlers established by `handler-bind`, innermost first, each in the form of `(kind handler)`.
+The entries in the form of `(trap predicate)` are established by `try`: `predicate` is called with the signal
+and, if it returns true, the signal is left to the `try` and the handlers after the entry are not called.")
+
+(defmacro handler-bind (kind handler body)
+  "Evaluate `body` with `handler` handling the signals whose kind is `kind` or one of its descendants,
+if they offer restarts (e.g. `unbound-symbol` offers `use-value` and `define-and-retry`).
+`handler` is a function with one parameter, the signal, which has the names of the offered restarts
+as its `restarts` property. It is called where the signal was emitted, before the stack is unwound:
+it can continue the evaluation by calling `invoke-restart`, or decline by returning normally."
+  `(parameterize (*signal-handlers* (cons (list ',kind ,handler) *signal-handlers*))
+     ,body))
+
+(defmacro assert (condition & message)
+  "Evaluate `condition` and emit an `assertion-failed` signal if it is nil, otherwise return `t`.
+The signal has the text of `condition`, the values of the variables in it and its location (see `make-assertion-error`).
+`message`, if given, is a string that replaces the default message of the signal."
+  `(if ,condition
+       t
+       (signal (make-assertion-error ',condition ,@message))))
 
 (defun --remove-extension (path)
   ""
@@ -313,16 +371,19 @@ that catches the signal."
   (or (--remove-extension (reverse path)) path))
 
 (defun load (path)
-  "Load the lisp module at the file path `path`."
-  (load-all (input-file path) (-remove-extension path)))
+  "Load the lisp module at the file path `path`.
+The metadata of the expressions read from it refers to the absolute path of the file."
+  (let (full-path (absolute-path path))
+    (load-all (input-file full-path) (-remove-extension path) full-path)))
 
 (defun read-simple (input)
   "Like `read`, but simple return the result without any additional data.
 Error if `read` would return non-ok status."
-  (let (read-result (read input 'stdin 1 1))
+  (let (read-result (read input))
     (if (= (. read-result 'status) 'ok)
         (. read-result 'result)
-        (throw 'kind 'read-error, 'details read-result))))
+        (let (error (. read-result 'error))
+          (signal (make-error 'read-error 'read-simple (. error 'message) (list 'status (. read-result 'status)) (. error 'location)))))))
 
 (defun infinite-loop (x)
   "for testing purposes"
@@ -18,6 +18,6 @@
    (list 1 2 (add 3 x))
    (catch unbound-symbol
      (lambda(error) (concat (print "This is the bad one: ")
-                            (print (. error 'symbol)))))))
+                            (print (. (. error 'details) 'symbol)))))))
 
 (output (print (do-something-bad)))
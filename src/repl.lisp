@@ -1,79 +1,174 @@
 (export '(repl read-eval-print))
 
-(defun -pretty-print-syntax-error (error)
-  ""
-  (concat "message: \n"
-          (. error 'message)
-          "\n at: "
-          (let (location (. error 'location))
-            (concat (print (. location 'file))
-                    ":"
-                    (print (. location 'line))
-                    ":"
-                    (print (. location 'column))))
-          "\n"))
+(defun -pretty-print-location (location)
+  "`location` (a property list with the keys `file`, `line` and `column`) in the format `file:line:column`."
+  (concat (let (file (. location 'file))
+            (if (= (type-of file) 'string-type) file (print file)))
+          (if (and (. location 'line) (. location 'column))
+              (concat ":" (print (. location 'line)) ":" (print (. location 'column)))
+              "")))
 
-(defun pretty-print-error (error)
-  "Print `erorr` in a more human-readable format.
-If `error` is not a valid property-list then just simply print it using the `print` function."
-  (if error
-      (try
-       (let (key (car error), value (car (cdr error)))
-         (concat (print key)
-                 ":\n"
-                 (if (or (= key 'symbol) (= key 'argument-value))
-                     (let (md (get-metadata value))
-                       (concat (print value)
-                               "\n at: "
-                               (print (. md 'file))
-                               (if (and (. md 'line) (. md 'column))
-                                   (concat ":" (print (. md 'line)) ":" (print (. md 'column)))
+(defun -pretty-print-backtrace (frames)
+  "One line for each function call in `frames` (see `attach-backtrace`)."
+  (foldr (lambda (frame acc)
+           (concat " "
+                   (if (. frame 'omitted)
+                       (concat "... and " (print (. frame 'omitted)) " more")
+                       (concat (. frame 'function)
+                               (if (. frame 'file)
+                                   (concat " at " (-pretty-print-location frame))
                                    "")))
-                     (print value))
-                 "\n\n"
-                 (if (= value 'syntax-error)
-                     (-pretty-print-syntax-error (. error 'details))
-                     (pretty-print-error         (cdr (cdr error))))))
-       (catch-all (lambda (_) (print error))))
+                   "\n"
+                   acc))
+         ""
+         frames))
+
+(defun -pretty-print-excerpt (location)
+  "The source excerpt at `location` on a new line, or an empty string if there is none."
+  (let (excerpt (source-excerpt location))
+    (if excerpt
+        (concat "\n" excerpt)
+        "")))
+
+(defun -syntax-error (source read-error)
+  "The `syntax-error` emitted by `source` for `read-error`, an error returned by `read` or `reader-feed`."
+  (make-error 'syntax-error
+              source
+              (. read-error 'message)
+              (if (. read-error 'hint) (list 'hint (. read-error 'hint)) nil)
+              (. read-error 'location)))
+
+(defun -pretty-print-details (details)
+  "One indented line for each key-value pair of the property list `details`."
+  (if details
+      (let ((key value & rest) details)
+        (concat " "
+                (print key)
+                ": "
+                (if (= (type-of value) 'string-type) value (print value))
+                "\n"
+                (-pretty-print-details rest)))
+      ""))
+
+(defun -pretty-print-plist (plist)
+  "Each key-value pair of `plist` on separate lines, the value of `backtrace` in a readable form."
+  (if plist
+      (let ((key value & rest) plist)
+        (concat (print key)
+                ":\n"
+                (if (= key 'backtrace)
+                    (-pretty-print-backtrace value)
+                    (concat (print value) "\n"))
+                "\n"
+                (-pretty-print-plist rest)))
       ""))
 
-(defun repl (prompt initial-input)
+(defun pretty-print-error (error)
+  "Print `error` in a more human-readable format.
+An error in the shape of the errors of native functions (see `make-error`) is shown as its source, its message
+and its location, e.g. \"add: expected number-type for argument `y`, got string-type at foo.lisp:3:10\",
+followed by an excerpt of the source, its kind, its details and its backtrace.
+Other property lists are shown one key-value pair after the other.
+If `error` is not a valid property-list then just simply print it using the `print` function."
+  (try
+   (if (and (. error 'kind) (. error 'message))
+       (let (location (. error 'location))
+         (concat (print (. error 'source))
+                 ": "
+                 (. error 'message)
+                 (if location
+                     (concat " at " (-pretty-print-location location) (-pretty-print-excerpt location))
+                     "")
+                 "\n kind: "
+                 (print (. error 'kind))
+                 "\n"
+                 (-pretty-print-details (. error 'details))
+                 (if (. error 'backtrace)
+                     (concat "backtrace:\n" (-pretty-print-backtrace (. error 'backtrace)))
+                     "")
+                 "\n"))
+       (-pretty-print-plist error))
+   (catch-all (lambda (_) (print error)))))
+
+(defun -remove-property (key plist)
+  "`plist` without the property `key`."
+  (when plist
+    (let ((k v & rest) plist)
+      (if (= k key)
+          rest
+          (cons k (cons v (-remove-property key rest)))))))
+
+(defun -print-restarts (restarts n)
+  "One numbered line for each restart in `restarts`, starting from `n`."
+  (when restarts
+    (block (output (concat " " (print n) ": " (print (car restarts))))
+           (-print-restarts (cdr restarts) (+ n 1)))))
+
+(defun -read-restart (restarts)
+  "Ask the user for the number of a restart in `restarts` until it is a valid one.
+At the end of the input, choose `abort`."
+  (let (choice (try (read-simple (input "restart> "))
+                    (catch read-error (lambda (_) nil))
+                    (catch eof (lambda (_) 'abort))))
+    (case ((= choice 'abort)
+           'abort)
+          ((and (= (type-of choice) 'number-type) (and (>= choice 1) (<= choice (length restarts))))
+           (let loop (restarts restarts, n choice)
+             (if (= n 1) (car restarts) (loop (cdr restarts) (- n 1)))))
+          (t
+           (-read-restart restarts)))))
+
+(defun -choose-restart (signal)
+  "Handler for the signals that offer restarts: show the restarts as a numbered menu, then invoke the chosen one.
+The last choice is always `abort`, which goes back to the prompt.
+For other restarts, the value is read and evaluated."
+  (let (restarts (. signal 'restarts))
+    (when restarts
+      (block (output (concat "ERROR:\n\n" (pretty-print-error (-remove-property 'restarts signal)) "RESTARTS:"))
+             (-print-restarts (append restarts '(abort)) 1)
+             (let (restart (-read-restart (append restarts '(abort))))
+               (if (= restart 'abort)
+                   (invoke-restart 'abort)
+                   (invoke-restart restart (eval (read-simple (input "value> "))))))))))
+
+(defun repl (prompt reader)
   "(R)ead an expression from standard input,
 (E)valuated it,
 (P)rint the result to standard output,
 then repeat (or (L)oop) from the beginning.
-Stop the loop when end of input (EOF) is reached."
+Stop the loop when end of input (EOF) is reached.
+`reader` holds the unfinished input (see `make-reader`), nil means nothing was read yet."
   (try
-   (let (current-input (concat initial-input (input prompt)))
-     (let (read-result (read current-input 'stdin 1 1))
-       (let (read-status (. read-result 'status))
-         (case ((= read-status 'invalid)    (throw 'kind 'invalid-string, 'source 'repl))
-               ((= read-status 'nothing)    (repl prompt nil))
-               ((= read-status 'incomplete) (repl "... " current-input))
-               ((= read-status 'error)      (throw 'kind 'syntax-error, 'source 'repl, 'details (. read-result 'error)))
-               ((= read-status 'ok)         (block (output (print (eval (. read-result 'result))))
-                                                          (repl ">>> " nil)))
-               (t                                  (throw 'kind 'unknown-read-status, 'source (qoute repl), 'read-status read-status))))))
+   (let* (feed-result (reader-feed (if reader reader (make-reader)) (input prompt))
+          feed-status (. feed-result 'status))
+     (block (map (lambda (form) (output (print (handler-bind error -choose-restart (eval form)))))
+                 (. feed-result 'forms))
+            (case ((= feed-status 'error) (signal (-syntax-error 'repl (. feed-result 'error))))
+                  ((= feed-status 'ok)    (repl (if (. feed-result 'complete) ">>> " "... ")
+                                                (. feed-result 'reader)))
+                  (t                      (signal (make-error 'unknown-read-status 'repl nil (list 'status feed-status)))))))
    (catch eof
      (lambda (_) (block (output "")
                         'ok)))
+   (catch restart-invocation
+     (lambda (_) (repl ">>> " nil)))
    (catch-all
-    (lambda (error) (block (output (concat "UNHANDLED ERROR:\n\n" (pretty-print-error error)))
+    (lambda (error) (block (output (concat "UNHANDLED ERROR:\n\n" (pretty-print-error (attach-backtrace error))))
                            (repl ">>> " nil))))))
 
 (defun read-eval-print (string pretty-print-errors?)
   "Read a string, evaluate it then print it into a string.
 If a signal is emmited during read evaluate or print then pretty-print it then forward it."
   (try
-   (let (read-result (read string 'stdin 1 1))
-     (let (read-status (. read-result 'status))
-       (case ((= read-status 'invalid)    (throw 'kind 'invalid-string, 'source 'repl))
-             ((= read-status 'nothing)    "")
-             ((= read-status 'incomplete) (throw 'kind 'syntax-error,   'source 'read-eval-print, 'details 'incomplete-input))
-             ((= read-status 'error)      (throw 'kind 'syntax-error,   'source 'repl,            'details (. read-result 'error)))
-             ((= read-status 'ok)         (print (eval (. read-result 'result))))
-             (t                           (throw 'kind 'unknown-read-status, 'source (qoute repl), 'read-status read-status)))))
+   (let* (read-result (read string)
+          read-status (. read-result 'status))
+     (case ((= read-status 'invalid)    (signal (make-error 'input-invalid-string 'read-eval-print nil)))
+           ((= read-status 'nothing)    "")
+           ((= read-status 'incomplete) (signal (make-error 'input-incomplete 'read-eval-print nil)))
+           ((= read-status 'error)      (signal (-syntax-error 'read-eval-print (. read-result 'error))))
+           ((= read-status 'ok)         (print (eval (. read-result 'result))))
+           (t                           (signal (make-error 'unknown-read-status 'read-eval-print nil (list 'status read-status))))))
   (catch-all
    (lambda (error) (if pretty-print-errors?
-                       (signal (pretty-print-error error))
+                       (signal (pretty-print-error (attach-backtrace error)))
                        (signal error))))))
@@ -4,10 +4,11 @@ use crate::native::eval::eval_external;
 
 
 
-fn load(mem: &mut Memory, string: &str, module: &str) -> Result<(), String> {
+/// Load `string` into `module`; `source` is where it comes from (see `load-all`).
+fn load(mem: &mut Memory, string: &str, module: &str, source: GcRef) -> Result<(), String> {
     let prelude     = string_to_proper_list(mem, string);
-    let source_name = string_to_proper_list(mem, module);
-    let vec         = vec![mem.symbol_for("load-all"), prelude, source_name];
+    let module_name = string_to_proper_list(mem, module);
+    let vec         = vec![mem.symbol_for("load-all"), prelude, module_name, source];
     let expression  = vec_to_list(mem, &vec);
     eval_external(mem, expression)?;
 
@@ -17,17 +18,21 @@ fn load(mem: &mut Memory, string: &str, module: &str) -> Result<(), String> {
 
 pub fn load_prelude(mem: &mut Memory) -> Result<(), String> {
     let prelude_str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/prelude.lisp"));  
-    load(mem, prelude_str, "prelude")
+    let source      = vec![mem.symbol_for("quote"), mem.symbol_for("prelude")];
+    let source      = vec_to_list(mem, &source);
+    load(mem, prelude_str, "prelude", source)
 }
 
 pub fn load_debugger(mem: &mut Memory) -> Result<(), String> {
     let prelude_str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/debugger.lisp"));  
-    load(mem, prelude_str, "debugger")
+    let source      = string_to_proper_list(mem, "debugger");
+    load(mem, prelude_str, "debugger", source)
 }
 
 pub fn load_repl(mem: &mut Memory) -> Result<(), String> {
     let prelude_str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/repl.lisp"));  
-    load(mem, prelude_str, "repl")
+    let source      = string_to_proper_list(mem, "repl");
+    load(mem, prelude_str, "repl", source)
 }
 
 
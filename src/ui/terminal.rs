@@ -1,6 +1,8 @@
 use crate::memory::*;
-use crate::util::{vec_to_list, string_to_proper_list, list_to_string};
+use crate::util::{vec_to_list, string_to_list, string_to_proper_list, list_to_string, list_to_vec};
 use crate::native::eval::eval_external;
+use crate::native::list::property;
+use crate::native::read::{check_syntax, number_property};
 use crate::native::load_native_functions;
 
 
@@ -52,3 +54,34 @@ pub fn run_file(path: &str) -> Result<(), String> {
 
     Ok(())
 }
+
+
+pub fn check_file(path: &str) -> Result<(), String> {
+    let input = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {path}: {e}"))?;
+
+    let mut mem = Memory::new();
+    load_native_functions(&mut mem);
+
+    // (check-syntax "input..." "path")
+    let args   = vec![string_to_list(&mut mem, &input), string_to_list(&mut mem, path)];
+    let errors = check_syntax(&mut mem, &args, GcRef::nil(), 0).map_err(|_| format!("Cannot check {path}"))?;
+    let errors = list_to_vec(errors).unwrap_or_default();
+
+    for error in errors.iter() {
+        let location = property(&mut mem, "location", error.clone()).unwrap_or_else(GcRef::nil);
+        let line     = number_property(&mut mem, "line", location.clone()).unwrap_or_default();
+        let column   = number_property(&mut mem, "column", location).unwrap_or_default();
+        let message  = property(&mut mem, "message", error.clone()).and_then(list_to_string).unwrap_or_default();
+        println!("{path}:{line}:{column}: {message}");
+
+        if let Some(hint) = property(&mut mem, "hint", error.clone()).and_then(list_to_string).filter(|x| !x.is_empty()) {
+            println!("    hint: {hint}");
+        }
+    }
+
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err("1 syntax error".to_string()),
+        n => Err(format!("{n} syntax errors")),
+    }
+}
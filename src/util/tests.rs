@@ -213,15 +213,15 @@ fn util_symbol_eq_through_meta() {
     let mut mem = Memory::new();
     
     let s1  = mem.symbol_for("parrot");
-    let md1 = Metadata{ read_name: "parrot".to_string(), location: Location::Native, documentation: "it is some kind of bird".to_string() };
+    let md1 = Metadata{ read_name: "parrot".to_string(), location: Location::Native, documentation: "it is some kind of bird".to_string(), span: None };
     let x1  = mem.allocate_metadata(s1, md1);
     let s2  = mem.symbol_for("parrot");
-    let md2 = Metadata{ read_name: "parrot".to_string(), location: Location::Native, documentation: "a parrot".to_string() };
+    let md2 = Metadata{ read_name: "parrot".to_string(), location: Location::Native, documentation: "a parrot".to_string(), span: None };
     let x2  = mem.allocate_metadata(s2, md2);
     assert!(symbol_eq!(x1, x2));
 
     let s1  = mem.symbol_for("bat");
-    let md1 = Metadata{ read_name: "bat".to_string(), location: Location::Native, documentation: "not a bird at all".to_string() };
+    let md1 = Metadata{ read_name: "bat".to_string(), location: Location::Native, documentation: "not a bird at all".to_string(), span: None };
     let x1  = mem.allocate_metadata(s1, md1);
     let x2  = mem.symbol_for("bat");
     assert!(symbol_eq!(x1, x2));
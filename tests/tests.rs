@@ -15,6 +15,12 @@ fn check_error(input: &str, error_kind: &str, error_details: &str) {
                                   .assert().stderr(str::contains(format!("kind {error_kind}")).and(str::contains(error_details)));
 }
 
+fn check_repl(input: &str, output: &str) {
+    Command::cargo_bin("picilisp").unwrap()
+                                  .write_stdin(format!("{input}\n"))
+                                  .assert().stdout(str::contains(format!(">>> {output}\n")));
+}
+
 
 #[test]
 fn number_literals() {
@@ -27,6 +33,22 @@ fn number_literals() {
     check_error("2.0", "syntax-error", "unexpected character in number literal: '.'");
 }
 
+#[test]
+fn extended_number_literals() {
+    check("0xff", "255");
+    check("-0XFF", "-255");
+    check("0o17", "15");
+    check("0b1010_1010", "170");
+    check("1_000_000", "1000000");
+    check("'(0ca 0c( 0c\\n)", "(97 40 10)");
+    check("-9223372036854775808", "-9223372036854775808");
+    check_error("9223372036854775808", "syntax-error", "number literal out of range: '9223372036854775808'");
+    check_error("0b102", "syntax-error", "unexpected character in number literal: '2'");
+    check_error("1__000", "syntax-error", "misplaced digit separator in number literal: '1__000'");
+    check_error("0x", "syntax-error", "missing digits in number literal: '0x'");
+    check_error("0cab", "syntax-error", "invalid character code literal: '0cab'");
+}
+
 #[test]
 fn character_literals() {
     check("%a", "%a");
@@ -40,6 +62,20 @@ fn character_literals() {
     check_error("%abc", "syntax-error", "invalid character: '%abc'");
 }
 
+#[test]
+fn unicode_escapes() {
+    check(r#""a\u{1F600}\u{41}""#, "\"a😀A\"");
+    check(r"%\u{1F600}", "%😀");
+    check(r"%\newline", r"%\n");
+    check(r"%\space", r"%\s");
+    check(r"%\nul", r"%\nul");
+    check(r"%\u{7}", r"%\u{7}");
+    check(r#""\\ \u{1} \r""#, r#""\\ \u{1} \r""#);
+    check(r#"(print (. (read (print "\\ \u{1} \r \"")) 'result))"#, r#""\"\\\\ \\u{1} \\r \\\"\"""#);
+    check_error(r#""\u{D800}""#, "syntax-error", "invalid unicode escape");
+    check_error(r"%\u{110000}", "syntax-error", "invalid character");
+}
+
 #[test]
 fn symbols_and_quoting() {
     check("(quote abc)", "abc");
@@ -129,7 +165,7 @@ fn branches() {
 #[test]
 fn traps() {
     check("(eval (trap unquoted-symbol (. *trapped-signal* 'kind)))", "unbound-symbol");
-    check("(eval (trap (list (list (list 1 2 deeply-nested 3))) (. *trapped-signal* 'symbol)))", "deeply-nested");
+    check("(eval (trap (list (list (list 1 2 deeply-nested 3))) (. (. *trapped-signal* 'details) 'symbol)))", "deeply-nested");
     check("(eval (trap (list 1 2 (signal \"Boo!\") 3) *trapped-signal*))", "\"Boo!\"");
 }
 
@@ -140,6 +176,110 @@ fn let_macro() {
     check_error("(let (x 1, y (add x 1)) y)", "unbound-symbol", "symbol x");
 }
 
+#[test]
+fn destructuring() {
+    check("((lambda ((a b) c) (list a b c)) '(1 2) 3)", "(1 2 3)");
+    check("((lambda ((a (b c)) & (d)) (list a b c d)) '(1 (2 3)) 4)", "(1 2 3 4)");
+    check("((lambda ((head & tail)) tail) '(1 2 3))", "(2 3)");
+    check("((lambda ((&key size color)) (list color size)) '(color red, size 3))", "(red 3)");
+    check("((lambda ((&key missing)) missing) '(color red))", "()");
+    check("(let ((a b) '(1 2), c 3) (list a b c))", "(1 2 3)");
+    check("(let* ((a & rest) '(1 2 3), (b c) rest) (list a b c))", "(1 2 3)");
+    check("(let loop ((x & xs) '(1 2 3), acc 0) (if xs (loop xs (add acc x)) (add acc x)))", "6");
+    check("(block (defun second ((_ x & _)) \"\" x) (second '(a b c)))", "b");
+    check("(. (destructure-function (lambda ((a b) & c) nil)) 'parameters)", "((a b) & c)");
+    check_error("((lambda ((a b)) a) '(1 2 3))", "pattern-mismatch", "pattern (a b) value (1 2 3)");
+    check_error("(let ((a b) 1) a)", "pattern-mismatch", "pattern (a b) value 1");
+    check("(list ((lambda (()) 1) nil) (let (() nil) 2) (block (defun f (() x) \"\" x) (f nil 3)))", "(1 2 3)");
+    check_error("((lambda (()) 1) 2)", "pattern-mismatch", "pattern () value 2");
+    check_error("(let (() 3) 1)", "pattern-mismatch", "pattern () value 3");
+    check_error("((lambda ((&key a)) a) 'not-a-plist)", "pattern-mismatch", "pattern (&key a) value not-a-plist");
+    check_error("(lambda ((a 1)) a)", "param-is-not-symbol", "parameter 1");
+    check_error("(lambda ((a &)) a)", "missing-rest-parameter", "");
+    check_error("(letrec ((a b) '(1 2)) a)", "binding-name-is-not-symbol", "name (a b)");
+}
+
+#[test]
+fn optional_and_key_parameters() {
+    check("((lambda (a &optional b (c (add a 10))) (list a b c)) 1)", "(1 () 11)");
+    check("((lambda (a &optional b (c (add a 10))) (list a b c)) 1 2 3)", "(1 2 3)");
+    check("((lambda (&optional a & rest) (list a rest)) 1 2 3)", "(1 (2 3))");
+    check("((lambda (&key (size 3) color) (list size color)) 'color 'red)", "(3 red)");
+    check("((lambda (x &optional (y x) &key (z (list x y))) z) 1)", "(1 1)");
+    check("(block (defun f (x &optional (y (when x 5))) \"\" y) (f t))", "5");
+    check("(. (destructure-function (lambda (a &optional b (c 1) & d) nil)) 'parameters)", "(a &optional b (c 1) & d)");
+    check("(. (destructure-function (lambda (a &key (b 2) c) nil)) 'parameters)", "(a &key (b 2) c)");
+    check("(. (destructure-function read) 'parameters)", "(input &optional source start-line start-column start-offset)");
+    check("(. (read \"(x y)\") 'result)", "(x y)");
+    check_error("((lambda (a &optional b) a))", "wrong-number-of-arguments", "expected-min 1 expected-max 2 actual 0");
+    check_error("((lambda (a & r) a))", "wrong-number-of-arguments", "expected-min 1 actual 0");
+    check_error("((lambda (&key size) size) 'colour 'red)", "unknown-key-argument", "key colour allowed (size)");
+    check_error("((lambda (&key size) size) 'size)", "odd-number-of-key-arguments", "arguments (size)");
+    check_error("(lambda (&key a & b) a)", "misplaced-lambda-list-keyword", "parameter &");
+    check_error("(lambda (&optional (a)) a)", "invalid-parameter-spec", "parameter (a)");
+    check_error("(read)", "wrong-number-of-arguments", "expected-min 1 expected-max 5 actual 0");
+}
+
+#[test]
+fn quasiquote() {
+    check("`(a b)", "(a b)");
+    check("(let (x 1) `(x ,x))", "(x 1)");
+    check("(let (xs '(2 3)) `(1 ,@xs 4))", "(1 2 3 4)");
+    check("(let (xs '(2 3)) `(,@xs ,@xs))", "(2 3 2 3)");
+    check("`(1 (2 ,(add 1 2)))", "(1 (2 3))");
+    check("`(1 `(2 ,(3 ,(add 2 2))))", "(1 (quasiquote (2 (unquote (3 4)))))");
+    check("(let (list 'shadowed) `(,list 2))", "(shadowed 2)");
+    check("(let (a 1, b 2) (list a b))", "(1 2)");
+    check("(list %` %a)", "\"`a\"");
+    check("(block (defmacro swap (a b) \"\" `(list ,b ,a)) (eval '(swap 1 2)))", "(2 1)");
+    check_error("`,@(list 1)", "unquote-splicing-outside-list", "form (unquote-splicing (list 1))");
+    check_error(",x", "unquote-outside-quasiquote", "form (unquote x)");
+    check_error("(list 1 ,(add 1 1))", "unquote-outside-quasiquote", "form (unquote (add 1 1))");
+    check_error("`(a (unquote))", "invalid-quasiquote", "form (unquote)");
+}
+
+#[test]
+fn match_form() {
+    check("(match 5 (1 'one) (5 'five))", "five");
+    check("(match 'x ('y 1) ('x 2))", "2");
+    check("(match \"ab\" (\"ba\" 'no) (\"ab\" 'yes))", "yes");
+    check("(match nil (() 'empty) (_ 'other))", "empty");
+    check("(match '(1 (2 3) 4 5) ((a (b c) & rest) (list c b a rest)))", "(3 2 1 (4 5))");
+    check("(match '(1 2) ((list a) 'one) ((list a b) 'two))", "two");
+    check("(match (cons 1 2) ((cons a b) (list a b)))", "(1 2)");
+    check("(match 7 ((symbol-type s) s) ((number-type n) (add n 1)))", "8");
+    check("(match 8 ((? (lambda (n) (> n 10))) 'big) ((? (lambda (n) (> n 5)) n) (list 'medium n)))", "(medium 8)");
+    check("(let (x 'outer) (match 1 (x x)))", "1");
+    check("(let loop (xs '(1 a 2 b), acc 0) (match xs (() acc) (((number-type n) & rest) (loop rest (add acc n))) ((_ & rest) (loop rest acc))))", "3");
+    check_error("(match 9 (1 'one))", "no-match", "details (value 9) location (file stdin line 1 column 2)");
+    check_error("(match 9 (1))", "invalid-match-clause", "clause (1)");
+    check_error("(match 9 ((quote) 1))", "invalid-pattern", "pattern (quote)");
+}
+
+#[test]
+fn let_star() {
+    check("(let* (x 1, y (add x 1)) y)", "2");
+    check("(let* () 'empty)", "empty");
+    check("(let (x 1) (let* (x (add x 1), x (add x 1)) x))", "3");
+    check_error("(let* (x 1, y) y)", "incomplete-binding", "name y");
+    check_error("(let* (1 2) 3)", "binding-name-is-not-symbol", "name 1");
+}
+
+#[test]
+fn letrec() {
+    check("(letrec (even? (lambda (n) (if (= n 0) t (odd? (substract n 1)))), odd? (lambda (n) (if (= n 0) nil (even? (substract n 1))))) (even? 100))", "t");
+    check("(letrec (f (lambda (n) (if (= n 0) 'done (f (substract n 1))))) (f 10000))", "done");
+    check_error("(letrec (f) f)", "incomplete-binding", "name f");
+}
+
+#[test]
+fn named_let() {
+    check("(let loop (i 0, acc nil) (if (= i 3) acc (loop (add i 1) (cons i acc))))", "(2 1 0)");
+    check("(let loop (i 10000) (if (= i 0) 'done (loop (substract i 1))))", "done");
+    check("(let (loop 'outer) (block (let loop (i 1) i) loop))", "outer");
+    check_error("(let loop (i) i)", "incomplete-binding", "name i");
+}
+
 #[test]
 fn print() {
     check(r#"(print 123)"#, r#""123""#);
@@ -257,7 +397,7 @@ fn apply() {
 
 #[test]
 fn last() {
-    check_error("(last nil)", "wrong-argument", "details empty-list");
+    check_error("(last nil)", "wrong-argument", "the list is empty");
     check("(last (list 1))", "1");
     check("(last (list 1 2 3 4 5))", "5");
 }
@@ -290,6 +430,51 @@ fn or() {
     check("(or nil t)", "t");
     check("(or nil nil)", "()");
     check("(or t (output \"monkey\"))", "t");
+    check("(let (value 'mine) (or nil value))", "mine");
+}
+
+#[test]
+fn macroexpand_1_and_trace() {
+    check("(macroexpand-1 '(when (not x) y))", "(if (not x) y nil)");
+    check("(block (defmacro m1 (a) \"\" (list 'quote a)) (defmacro m2 () \"\" (signal 'boom)) (macroexpand-1 '(m1 (m2))))", "(quote (m2))");
+    check("(macroexpand-1 '(f (when x y)))", "(f (when x y))");
+    check("(macroexpand-1 'x)", "x");
+    check("(map (lambda (step) (. step 'macro)) (macroexpand-trace '(when (not x) y)))", "(not when)");
+    check("(. (car (macroexpand-trace '(not x))) 'expansion)", "(if x nil t)");
+    check("(. (car (macroexpand-trace '(not x))) 'form)", "(not x)");
+    check("(macroexpand-trace '(f x))", "()");
+}
+
+#[test]
+fn expansion_cache() {
+    check("(block (defmacro m () \"\" 1) (define 'code '(m) \"\") (define 'a (eval code) \"\") (undefine 'm) (defmacro m () \"\" 2) (list a (eval code)))", "(1 2)");
+    check("(block (define 'code '(when t 1) \"\") (list (eval code) (eval code)))", "(1 1)");
+    // the same code evaluated in a new environment on every call is only expanded once
+    check("(block (defmacro m () \"\" (block (output \"expanded\") 1)) (defun f (x) \"\" (eval '(+ x (m)))) (list (f 1) (f 2)))", "expanded\n(2 3)");
+    // code in an environment that shadows one of its macros is expanded again, in both orders
+    check("(block (define 'code '(when t 1) \"\") (list ((lambda (when) (eval code)) (lambda (a b) 'fn)) (eval code)))", "(fn 1)");
+    check("(block (define 'code '(when t 1) \"\") (list (eval code) ((lambda (when) (eval code)) (lambda (a b) 'fn))))", "(1 fn)");
+    check("(match 3 ((? not) 'not-three) ((? (lambda (n) (when t (> n 2))) n) (list 'big n)))", "(big 3)");
+    check("(match nil ((? not) 'empty) (_ 'other))", "empty");
+}
+
+#[test]
+fn define_syntax() {
+    check("(block (define-syntax swap () \"\" ((_ a b) (let (tmp a) (list b tmp)))) (eval '(let (tmp 1, other 2) (swap tmp other))))", "(2 1)");
+    check("(block (define-syntax rev () \"\" ((_ x ...) '(x ...)) ((_) nil)) (eval '(rev 1 2 3)))", "(1 2 3)");
+    check("(block (define-syntax m () \"\" ((_ (a b ...) ...) '((b ... a) ...))) (eval '(m (1 2 3) (4) (5 6))))", "((2 3 1) (4) (6 5))");
+    check("(block (define-syntax my-if (then else) \"\" ((_ c then x else y) (if c x y))) (eval '(my-if nil then 1 else 2)))", "2");
+    check("(block (define-syntax my-let* () \"\" ((_ () body) body) ((_ ((x v) rest ...) body) (let (x v) (my-let* (rest ...) body)))) (eval '(my-let* ((a 1) (b (add a 1))) (list a b))))", "(1 2)");
+    // symbols bound by the template are renamed, the others refer to the globals of the module of the macro
+    check("(block (load-all \"(export '(m)) (defun helper (x) \\\"\\\" (* x 2)) (define-syntax m () \\\"\\\" ((_ x) (helper x)))\" \"syn\") (eval '(m 21)))", "42");
+    check("(block (define-syntax m () \"\" ((_ x) (later x))) (eval '(defun f () \"\" (m 1))) (eval '(defun later (x) \"\" (+ x 1))) (f))", "2");
+    check("(block (define-syntax m () \"\" ((_ x) (list x x))) (eval '(let (list 5) (m list))))", "(5 5)");
+    check("(block (define-syntax m () \"\" ((_ x) (match x ((cons a _) (list a))))) (eval '(let (a 1) (m (cons a 2)))))", "(1)");
+    check("(block (define-syntax m () \"\" ((_ x) (let (tmp x) `(tmp ,tmp)))) (eval '(m 1)))", "(tmp 1)");
+    check("(macroexpand '(and x y))", "(if x y ())");
+    check_error("(or 1)", "no-matching-syntax-rule", "details (form (or 1)) location (file stdin line 1 column 5 end-line 1 end-column 5");
+    check_error("(block (define-syntax m () \"\" ((_ x ... y ...) 1)) (eval '(m)))", "invalid-syntax-rule", "rule");
+    check_error("(block (define-syntax m () \"\" ((_ x ...) x)) (eval '(m 1)))", "missing-ellipsis", "variable x");
 }
 
 #[test]
@@ -357,7 +542,7 @@ Convert `input` to its string representation.
 Defined in:
  Rust source.\"");
 
-    check("(describe read)", "\"(lambda (input source start-line start-column) ...)
+    check("(describe read)", "\"(lambda (input &optional source start-line start-column start-offset) ...)
 
 Converts a Lisp-style string to an AST.
 
@@ -371,15 +556,18 @@ The `status` key can have one of the following values:
  * `error`:      The input is not a valid AST, not even the beginning of one. The `error` key contains the error details.
  * `invalid`:    The input is not a valid string.
 
-Whenever there is a `rest` key, the `line` and `column` keys are also present,
-whose values are respectively the first line and column of the rest of the input.
+Whenever there is a `rest` key, the `line`, `column` and `offset` keys are also present,
+whose values are respectively the first line, column and byte offset of the rest of the input.
 
-`source`, `start-line` and `start-column` describe where we are reading from.
+`source`, `start-line`, `start-column` and `start-offset` describe where we are reading from
+(by default `stdin`, line 1, column 1, byte offset 0).
 Possible values of `source`:
  * prelude
  * stdin
  * a string representing a file-path.
 
+The metadata of the atoms read records where they begin and end (see `get-metadata`).
+
 Defined in:
  Rust source.\"");
 }
@@ -392,14 +580,69 @@ fn read_simple() {
 
 #[test]
 fn try_catch() {
-    check("(try 123 (catch unbound-symbol (lambda (x) (. x 'symbol))) (catch-all (lambda (_) 'something-else)))", "123");
-    check("(try something (catch unbound-symbol (lambda (x) (. x 'symbol))) (catch-all (lambda (_) 'something-else)))", "something");
-    check("(try (1 2 3) (catch unbound-symbol (lambda (x) (. x 'symbol))) (catch-all (lambda (_) 'something-else)))", "something-else");
+    check("(try 123 (catch unbound-symbol (lambda (x) (. (. x 'details) 'symbol))) (catch-all (lambda (_) 'something-else)))", "123");
+    check("(try something (catch unbound-symbol (lambda (x) (. (. x 'details) 'symbol))) (catch-all (lambda (_) 'something-else)))", "something");
+    check("(try (1 2 3) (catch unbound-symbol (lambda (x) (. (. x 'details) 'symbol))) (catch-all (lambda (_) 'something-else)))", "something-else");
+}
+
+#[test]
+fn signal_kind_hierarchy() {
+    check("(try (/ 1 0) (catch arithmetic-error (lambda (e) (. e 'kind))))", "divide-by-zero");
+    check("(try (car 1) (catch io-error (lambda (_) 'io)) (catch error (lambda (e) (. e 'kind))))", "wrong-argument-type");
+    check("(try (throw 'kind 'other) (catch error (lambda (_) 'error)) (catch-all (lambda (_) 'other)))", "other");
+    check("(try (signal 1) (catch error (lambda (_) 'error)) (catch-all (lambda (_) 'other)))", "other");
+    check("(list (signal-kind-parent 'divide-by-zero) (signal-kind-parent 'arithmetic-error) (signal-kind-parent 'error))", "(arithmetic-error error ())");
+    check("(list (signal-kind-of 'stackoverflow 'eval-error) (signal-kind-of 'stackoverflow 'read-error))", "(t ())");
+    check("(list (define-signal-kind 'my-error) (define-signal-kind 'my-sub-error 'my-error) (try (throw 'kind 'my-sub-error) (catch my-error (lambda (e) (. e 'kind)))))", "(my-error my-sub-error my-sub-error)");
+    check_error("(define-signal-kind 'error 'arithmetic-error)", "invalid-signal-kind", "kind error parent arithmetic-error");
+}
+
+#[test]
+fn restarts() {
+    check("(handler-bind unbound-symbol (lambda (_) (invoke-restart 'use-value 42)) (+ x 1))", "43");
+    check("(handler-bind error (lambda (_) (invoke-restart 'define-and-retry 5)) (list x x))", "(5 5)");
+    check("(handler-bind error (lambda (e) (invoke-restart 'use-value (. e 'restarts))) x)", "(use-value define-and-retry)");
+    check("(handler-bind error (lambda (_) (invoke-restart 'retry 3)) (offer-restarts '(kind wrong-argument) '(retry)))", "(retry 3)");
+    check("(try (handler-bind error (lambda (_) (invoke-restart 'abort)) x) (catch error (lambda (_) 'error)) (catch restart-invocation (lambda (e) (. e 'restart))))", "abort");
+    // a restart invocation is not caught by `catch-all`
+    check("(try (try (invoke-restart 'abort) (catch-all (lambda (_) 'caught))) (catch restart-invocation (lambda (e) (. e 'restart))))", "abort");
+    check_error("(try (invoke-restart 'abort) (catch-all (lambda (_) 'caught)))", "restart-invocation", "restart abort");
+    // declining handlers and handlers of other kinds
+    check("(try (handler-bind error (lambda (_) nil) x) (catch unbound-symbol (lambda (e) (. (. e 'details) 'symbol))))", "x");
+    check("(try (handler-bind io-error (lambda (_) (invoke-restart 'use-value 1)) x) (catch unbound-symbol (lambda (e) (. (. e 'details) 'symbol))))", "x");
+    // a `try` catching the signal wins over the handlers outside of it
+    check("(handler-bind error (lambda (_) (invoke-restart 'use-value 0)) (try undefined-xyz (catch unbound-symbol (lambda (_) 42))))", "42");
+    check("(handler-bind error (lambda (_) (invoke-restart 'use-value 0)) (try undefined-xyz (catch io-error (lambda (_) 42))))", "0");
+    check_repl("(try undefined-xyz (catch unbound-symbol (lambda (e) 42)))", "42");
+    check_repl("(try (invoke-restart 'abort) (catch-all (lambda (e) 42)))", ">>> ");
+    // the end of the input at the restart prompt chooses `abort`, then the REPL stops
+    Command::cargo_bin("picilisp").unwrap()
+                                  .write_stdin("(+ 1 x)\n")
+                                  .timeout(std::time::Duration::from_secs(30))
+                                  .assert().success().stdout(str::contains("RESTARTS:").and(str::contains("restart> ")));
+    // a handler only sees the handlers outside of it
+    check("(handler-bind error (lambda (_) (invoke-restart 'use-value 2)) (handler-bind error (lambda (_) (invoke-restart 'use-value y)) x))", "2");
+}
+
+#[test]
+fn unwind_protect() {
+    check("(list (with-output-to-string (unwind-protect 1 (output \"cleanup\"))))", "(\"cleanup\n\")");
+    check("(try (unwind-protect (car 1) (output \"cleanup\")) (catch error (lambda (e) (. e 'kind))))", "cleanup\nwrong-argument-type");
+    check("(try (car 1) (catch error (lambda (_) 'caught)) (finally (output \"cleanup\")))", "cleanup\ncaught");
+    check("(try (car 1) (catch io-error (lambda (_) 'io)) (finally (output \"cleanup\")))", "cleanup\n()");
+    check("(try 1 (finally (output \"cleanup\")))", "cleanup\n1");
+    check("(list (try 1 (finally nil)) (try (car 1) (catch-all (lambda (_) 'caught)) (finally nil)))", "(1 caught)");
+    check_error("(unwind-protect 1 (car 1))", "wrong-argument-type", "source car");
+    Command::cargo_bin("picilisp").unwrap()
+                                  .args(["--expression", "(unwind-protect (abort) (output \"cleanup\"))"])
+                                  .assert().stdout("cleanup\n").stderr(str::contains("Evaluation aborted."));
+    // a failed `load-all` still restores the current module
+    check("(try (load-all \"(car 1)\" \"some-module\") (catch-all (lambda (_) (get-current-module))))", "default");
 }
 
 #[test]
 fn metadata() {
-    check("(get-metadata (read-simple \"   123\"))", "(documentation () file stdin line 1 column 4)");
+    check("(get-metadata (read-simple \"   123\"))", "(documentation () file stdin line 1 column 4 end-line 1 end-column 6 offset 3 end-offset 5)");
 }
 
 
@@ -412,3 +655,170 @@ fn defun() {
 fn recursion() {
     check("(block (defun factorial (n) \"\" (if (= n 0) 1 (* n (factorial (- n 1))))) (factorial 5))", "120");
 }
+
+#[test]
+fn parameterize() {
+    check("(parameterize (*stdout* 'somewhere-else) *stdout*)", "somewhere-else");
+    check("(block (defun f () \"\" *stdout*) (parameterize (*stdout* 'somewhere-else) (f)))", "somewhere-else");
+    check("(let (*stdout* 'lexical) (parameterize (*stdout* 'dynamic) *stdout*))", "lexical");
+    check("(block (eval (trap (parameterize (*stdout* 'somewhere-else) (signal 'boom)) nil)) *stdout*)", "*stdout*");
+    check_error("(parameterize (x 1, y) x)", "incomplete-binding", "name y");
+}
+
+#[test]
+fn with_output_to_string() {
+    check("(with-output-to-string (output \"hello\"))", "\"hello\n\"");
+    check("(with-output-to-string (block (output \"outer\") (output (with-output-to-string (output \"inner\")))))", "\"outer\ninner\n\n\"");
+    check("(block (eval (trap (with-output-to-string (signal 'boom)) nil)) (output \"visible\"))", "visible\nok");
+    check("(with-output-to-string (output-file '*stdout* \"quoted\"))", "\"quoted\"");
+}
+
+#[test]
+fn reader_macros() {
+    let not_reader = "(set-reader-macro %! (lambda (input source line column) (let (r (read input source line column)) (if (= (. r 'status) 'ok) (list 'status 'ok 'result (list 'not (. r 'result)) 'rest (. r 'rest) 'line (. r 'line) 'column (. r 'column)) r))))";
+    check(&format!("(block {not_reader} (. (read \"(a !x b)\") 'result))"), "(a (not x) b)");
+    check(&format!("(block {not_reader} (. (get-metadata (car (cdr (car (cdr (. (read \"(a\n  !x)\") 'result)))))) 'column))"), "4");
+    check(&format!("(block {not_reader} (. (. (read \"(a !)\") 'error) 'location))"), "(file stdin line 1 column 5)");
+    check(&format!("(block {not_reader} (remove-reader-macro %!) (list (get-reader-macro %!) (. (read \"!x\") 'result)))"), "(() !x)");
+    check_error("(block (set-reader-macro %! (lambda (input source line column) 42)) (read \"!x\"))", "invalid-reader-macro-result", "result 42");
+    check_error("(set-reader-macro %\\s list)", "invalid-reader-macro-character", "");
+}
+
+#[test]
+fn block_and_datum_comments() {
+    check("'(1 #| 2 #| nested |# 3 |# 4)", "(1 4)");
+    check("'(1 #;(2\n 3) 4)", "(1 4)");
+    check("'(1 #;#;2 3 4)", "(1 4)");
+    check("(. (read \"(1 #| 2\") 'status)", "incomplete");
+    check("(. (. (read \"#|\n|# (a #; )\") 'error) 'location)", "(file stdin line 2 column 10)");
+    check_error("'(1 #;)", "syntax-error", "expected an expression before ')'");
+}
+
+#[test]
+fn resumable_reader() {
+    check("(let* (r1 (reader-feed (make-reader) \"(a b\nc\") r2 (reader-feed (. r1 'reader) \"d) 1 x\")) (list (. r1 'forms) (. r1 'complete) (. r2 'forms) (. r2 'complete)))", "(() () ((a b cd) 1) ())");
+    check("(let* (r1 (reader-feed (make-reader \"f.lisp\" 10 3) \"#| x\") r2 (reader-feed (. r1 'reader) \"|# y\n\")) (get-metadata (car (. r2 'forms))))", "(documentation () file \"f.lisp\" line 10 column 10 end-line 10 end-column 10 offset 7 end-offset 7)");
+    check("(let (r (reader-feed (make-reader) \"1 ) 2\")) (list (. r 'status) (. r 'forms) (. (. r 'error) 'message) (. (. r 'reader) 'column)))", "(error (1) \"too many closing parentheses\" 4)");
+    check("(. (reader-feed (make-reader) \"(1\" t) 'status)", "incomplete");
+    check_error("(reader-feed 42 \"x\")", "invalid-reader", "reader 42");
+}
+
+
+#[test]
+fn source_spans() {
+    check("(get-metadata (. (read \"  \\\"a\nbc\\\"\") 'result))", "(documentation () file stdin line 1 column 3 end-line 2 end-column 3 offset 2 end-offset 7)");
+    check("(let (r (read \"é b\" \"f.lisp\" 3 1 10)) (list (. r 'offset) (. (get-metadata (. r 'result)) 'end-offset)))", "(12 10)");
+
+    let path = std::env::temp_dir().join("picilisp-source-spans.lisp");
+    std::fs::write(&path, "(first)\n  (second third)\n").unwrap();
+    let path = path.to_str().unwrap();
+    check(&format!("(source-excerpt (list 'file {path:?} 'line 2 'column 11 'end-line 2 'end-column 15))"), "\"2 |   (second third)\n  |           ^^^^^\"");
+    check(&format!("(source-excerpt (list 'file {path:?} 'line 2 'column 3 'end-line 3 'end-column 1))"), "\"2 |   (second third)\n  |   ^^^^^^^^^^^^^^\"");
+    check("(source-excerpt (list 'file 'stdin 'line 1 'column 1))", "()");
+
+    // a list is underlined from its `(` to its `)`
+    let path = std::env::temp_dir().join("picilisp-source-spans-assert.lisp");
+    std::fs::write(&path, "(defun f (x)\n  \"doc\"\n  (assert (= x 1)))\n(f 2)\n").unwrap();
+    let path = path.to_str().unwrap();
+    check(&format!("(try (load {path:?}) (catch-all (lambda (e) (source-excerpt (. e 'location)))))"), "\"3 |   (assert (= x 1)))\n  |           ^^^^^^^\"");
+}
+
+#[test]
+fn loaded_file_locations() {
+    let dir  = std::env::temp_dir().join("picilisp-loaded-file-locations");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("m.lisp"), "(export '(f))\n\n(defun f (x)\n  \"Double `x`.\"\n  (* 2 x))\n").unwrap();
+    let path = std::fs::canonicalize(dir.join("m.lisp")).unwrap();
+    let path = path.to_str().unwrap();
+    let load = format!("(load {:?})", dir.join(".").join("m.lisp").to_str().unwrap());
+
+    check(&format!("(block {load} (. (get-metadata f) 'file))"), &format!("{path:?}"));
+    check(&format!("(block {load} (describe f))"), &format!("\"(lambda (x) ...)\n\nDouble `x`.\n\nDefined in:\n {path}:3:8\""));
+    check(&format!("(load-all \"(defun g () \\\"\\\" 1)\" \"m2\" {path:?})"), "ok");
+    check_error("(absolute-path \"/no/such/file.lisp\")", "cannot-read-file", "source absolute-path");
+}
+
+#[test]
+fn backtraces() {
+    check_error("(block (defun f (x) \"\" (car x)) (f 1))", "wrong-argument-type", "backtrace ((function \\\"car\\\" file stdin line 1 column 25) (function \\\"f\\\" file stdin line 1 column 34)");
+    check("(try (car 1) (catch-all (lambda (e) (. (car (. (attach-backtrace e) 'backtrace)) 'function))))", "\"car\"");
+    check("(try (signal '(kind k)) (catch-all (lambda (e) (attach-backtrace e))))", "(kind k)");
+    // signalling the same plist again starts a new backtrace
+    check_error("(list (define 'e '(kind k) \"\") (defun f () \"\" (signal e)) (defun g () \"\" (signal e)) (try (f) (catch-all (lambda (_) nil))) (g))", "k", "backtrace ((function \\\"g\\\" file stdin line 1 column");
+    check_error("(block (defun r (n) \"\" (if (= n 0) (car 1) (+ 1 (r (- n 1))))) (r 100))", "wrong-argument-type", "(omitted 40)");
+}
+
+#[test]
+fn syntax_check() {
+    check("(check-syntax \"(a b)\n(c)\")", "()");
+    check("(map (lambda (e) (. e 'message)) (check-syntax \"(a\n(b ))\n(c 0xg)\"))", "(\"unexpected character in number literal: 'g'\")");
+    check("(. (car (check-syntax \"(a\n(b)\n\")) 'hint)", "\"unclosed paren opened at 1:1, a ')' may be missing before the '(' at 2:1\"");
+    check("(. (car (check-syntax \"(a\" \"f.lisp\" 5 1)) 'location)", "(file \"f.lisp\" line 5 column 3)");
+}
+
+#[test]
+fn error_shape() {
+    check("(try (car 1) (catch-all (lambda (e) (list (. e 'kind) (. e 'source) (. e 'message) (. e 'details)))))", "(wrong-argument-type car \"expected conscell-type for argument `cons`, got number-type\" (argument-value 1 parameter cons expected conscell-type actual number-type))");
+    check("(try (/ 1 0) (catch-all (lambda (e) (list (. e 'kind) (. e 'details)))))", "(divide-by-zero (arguments (1 0)))");
+    check("(make-error 'divide-by-zero 'f nil '(x 1))", "(kind divide-by-zero source f message \"division by zero\" details (x 1) location ())");
+    check("(make-error 'my-error 'f \"custom\")", "(kind my-error source f message \"custom\" details () location ())");
+    check_error("(make-error 'error 'x nil '(1 2))", "invalid-plist", "details (argument-value (1 2))");
+    check_error("(make-error 'error 'x nil '(a))", "invalid-plist", "details (argument-value (a))");
+    check_error("(block (define 'shape-x 1 \"\") (define 'shape-x 2 \"\"))", "already-defined", "details (symbol shape-x)");
+}
+
+#[test]
+fn error_messages() {
+    check("(try (+ 1 \"a\") (catch-all (lambda (e) (. e 'message))))", "\"expected number-type for argument `y`, got string-type\"");
+    check("(try (/ 1 0) (catch-all (lambda (e) (. e 'message))))", "\"division by zero with the arguments (1 0)\"");
+    check("(try (load-all 1 \"m\") (catch-all (lambda (e) (. e 'message))))", "\"expected string-type for argument `string`, got number-type\"");
+    check("(. (make-error 'unbound-symbol 'f nil '(symbol x)) 'message)", "\"the symbol `x` is unbound\"");
+    check("(try ((lambda (a b) a) 1) (catch-all (lambda (e) (. e 'message))))", "\"wrong number of arguments: expected 2, got 1\"");
+    check("(try ((lambda (a &optional b) a)) (catch-all (lambda (e) (. e 'message))))", "\"wrong number of arguments: expected 1 to 2, got 0\"");
+    check("(try ((lambda (a & b) a)) (catch-all (lambda (e) (. e 'message))))", "\"wrong number of arguments: expected at least 1, got 0\"");
+    check("(try ((lambda (&key a) a) 'b 1) (catch-all (lambda (e) (. e 'message))))", "\"unknown key argument `b`, expected one of (a)\"");
+    check("(try (from-module 'car 'no-module) (catch-all (lambda (e) (. e 'message))))", "\"there is no module named `no-module`\"");
+    check_error("(list (load-all \"(export '(amb)) (define 'amb 1 \\\"\\\")\" \"amb1\" \"a.lisp\") (load-all \"(export '(amb)) (define 'amb 2 \\\"\\\")\" \"amb2\" \"b.lisp\") amb)", "ambiguous-name", "message \\\"the symbol `amb` is exported by more than one module: (amb");
+
+    check("(try (read-eval-print \"(car 1)\" t) (catch-all (lambda (e) e)))", "\"car: expected conscell-type for argument `cons`, got number-type at stdin:1:6\n kind: wrong-argument-type\n argument-value: 1\n parameter: cons\n expected: conscell-type\n actual: number-type\nbacktrace:\n car at stdin:1:2\n\n\"");
+    check("(try (read-eval-print \"(1 2\" t) (catch-all (lambda (e) e)))", "\"read-eval-print: the input ended in the middle of an expression\n kind: input-incomplete\n\n\"");
+    check("(try (read-eval-print \") 1\" t) (catch-all (lambda (e) e)))", "\"read-eval-print: too many closing parentheses at stdin:1:1\n kind: syntax-error\n\n\"");
+
+    let path = std::env::temp_dir().join("picilisp-error-messages.lisp");
+    std::fs::write(&path, "(defun g (x)\n  \"\" (car x))\n(g 5)\n").unwrap();
+    let path = std::fs::canonicalize(path).unwrap();
+    let path = path.to_str().unwrap();
+    check(&format!("(try (load {path:?}) (catch-all (lambda (e) (list (. e 'message) (. e 'location)))))"), &format!("(\"expected conscell-type for argument `cons`, got number-type\" (file {path:?} line 3 column 4 end-line 3 end-column 4 offset 30 end-offset 30))"));
+}
+
+#[test]
+fn assertions() {
+    check("(assert (= 1 1))", "t");
+    check("(let (a 1 b 2) (try (assert (> a b)) (catch assertion-failed (lambda (e) e))))", "(kind assertion-failed source assert message \"the assertion (> a b) failed\" details (expression \"(> a b)\" values (a 1 b 2)) location (file stdin line 1 column 29 end-line 1 end-column 35 offset 28 end-offset 34))");
+    check("(let (xs '(1 2)) (try (assert (= (car xs) 'y) \"not y\") (catch error (lambda (e) (list (. e 'message) (. (. e 'details) 'values))))))", "(\"not y\" (xs (1 2)))");
+    check_error("(make-assertion-error '(= x 1) 'oops)", "wrong-argument-type", "argument-value oops expected string-type");
+
+    check("(list (defun half (x) \"Half of the positive `x`.\" :pre (> x 0) :post (< result x) (/ x 2)) (half 10))", "(ok 5)");
+    check("(list (defun half (x) \"\" :pre (> x 0) (/ x 2)) (try (half -4) (catch-all (lambda (e) e))))", "(ok (kind assertion-failed source half message \"the precondition (> x 0) of `half` failed\" details (expression \"(> x 0)\" values (x -4)) location (file stdin line 1 column 31 end-line 1 end-column 37 offset 30 end-offset 36)))");
+    check("(list (defun dec (x) \"\" :post (> result x) (- x 1)) (try (dec 3) (catch-all (lambda (e) (list (. e 'message) (. (. e 'details) 'values))))))", "(ok (\"the postcondition (> result x) of `dec` failed\" (result 2 x 3)))");
+    check_error("(defun f (x) \"\" x x)", "wrong-number-of-arguments", "source defun");
+    check_error("(defun nb (x) \"\")", "wrong-number-of-arguments", "source defun message \\\"wrong number of arguments: expected 1, got 0");
+    check_error("(defun nb (x) \"\" :pre)", "wrong-number-of-arguments", "source defun");
+    check_error("(defun nb (x) \"\" :post (> result 0))", "wrong-number-of-arguments", "source defun");
+}
+
+#[test]
+fn type_annotations() {
+    check("((lambda ((number-type x) (string-type s)) (list x s)) 1 \"a\")", "(1 \"a\")");
+    check_error("((lambda ((number-type x)) x) \"a\")", "wrong-argument-type", "details (argument-value \\\"a\\\" parameter x expected number-type actual string-type)");
+    check("((lambda (&optional ((number-type x) 5) (list-type l)) (list x l)))", "(5 ())");
+    check_error("((lambda (&optional (number-type x)) x) 'a)", "wrong-argument-type", "parameter x expected number-type actual symbol-type");
+    check("((lambda (&key (number-type k) ((symbol-type m) 'z)) (list k m)) 'k 3)", "(3 z)");
+    check_error("((lambda (&key (number-type k)) k) 'j 1)", "unknown-key-argument", "details (key j allowed (k))");
+    check("((lambda ((list-type (a b)) & (list-type r)) (list (+ a b) r)) '(1 2) 3)", "(3 (3))");
+    check("(try ((lambda ((list-type (a b))) a) 5) (catch-all (lambda (e) (. e 'message))))", "\"expected list-type for argument `(a b)`, got number-type\"");
+
+    check("(list (defun typed (x (number-type y)) \"Add `y` to `x`.\" (+ x y)) (typed 1 2) (. (destructure-function typed) 'parameters))", "(ok 3 (x (number-type y)))");
+    check_error("(list (defun typed (x (number-type y)) \"\" (+ x y)) (typed 1 \"2\"))", "wrong-argument-type", "source typed message \\\"expected number-type for argument `y`, got string-type\\\"");
+    check("(list (defun typed (x (number-type y)) \"Add `y` to `x`.\" (+ x y)) (describe typed))", "(ok \"(lambda (x (number-type y)) ...)\n\nAdd `y` to `x`.\n\nDefined in:\n stdin:1:14\")");
+}